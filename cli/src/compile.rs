@@ -1,28 +1,36 @@
 use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::io::{self, Read};
-use std::path::Path;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 
-use reflexo_typst::config::entry::{EntryOpts, MEMORY_MAIN_ENTRY};
+use reflexo_typst::config::entry::{EntryOpts, EntryState, MEMORY_MAIN_ENTRY};
 use reflexo_typst::config::CompileOpts;
-use reflexo_typst::features::{FeatureSet, DIAG_FMT_FEATURE};
-use reflexo_typst::{exporter_builtins::GroupExporter, path::PathClean};
+use reflexo_typst::features::{FeatureSet, DIAG_FMT_FEATURE, FONT_DIAGNOSTICS_FEATURE};
+use reflexo_typst::package::lock::PackageLock;
+use reflexo_typst::profile::{self, SourceCacheStats};
+use reflexo_typst::task::{CacheEvictPolicy, CacheEvictStats, CacheTask, CacheUserConfig};
+use reflexo_typst::{exporter_builtins::GroupExporter, path::PathClean, world};
 use reflexo_typst::{
-    CompilationHandle, CompileActor, CompileDriver, CompileExporter, CompileServerOpts,
-    CompileStarter, CompiledArtifact, CompilerFeat, ConsoleDiagReporter, DynExporter,
-    DynamicLayoutCompiler, EntryManager, EntryReader, GenericExporter, PureCompiler, ShadowApi,
-    SystemCompilerFeat, TypstSystemUniverse, TypstSystemWorld,
+    CompilationHandle, CompileActor, CompileDriver, CompileEnv, CompileExporter, CompileReporter,
+    CompileServerOpts, CompileStarter, CompiledArtifact, Compiler, CompilerFeat,
+    ConsoleDiagReporter, DepsExporter, DepsTarget, DynExporter, DynamicLayoutCompiler,
+    EntryManager, EntryReader, GenericExporter, PureCompiler, ShadowApi, SystemCompilerFeat,
+    TaskInputs, TypstSystemUniverse, TypstSystemWorld,
 };
 use tokio::sync::mpsc;
 use typst::diag::{FileError, FileResult};
 use typst::foundations::{Bytes, Dict, IntoValue};
 use typst::model::Document;
+use typst::World;
 
+use crate::export::{output_path, prepare_exporters};
 use crate::font::fonts;
-use crate::utils::current_dir;
+use crate::utils::make_absolute;
 use crate::{
     utils::{self, UnwrapOrExit},
-    CompileArgs, CompileOnceArgs,
+    CacheArgs, CompileArgs, CompileOnceArgs, InputsFormat,
 };
 
 pub fn create_driver(args: CompileOnceArgs) -> CompileDriver<PureCompiler<TypstSystemWorld>> {
@@ -42,6 +50,9 @@ pub fn create_driver(args: CompileOnceArgs) -> CompileDriver<PureCompiler<TypstS
     let is_stdin = entry == "-";
     let entry_file_path = if is_stdin || entry_file_path.is_absolute() {
         entry_file_path
+    } else if workspace_dir.is_file() {
+        // The entry is relative to the root of the workspace archive.
+        workspace_dir.join(entry_file_path)
     } else {
         let cwd = std::env::current_dir().unwrap_or_exit();
         cwd.join(entry_file_path)
@@ -60,17 +71,35 @@ pub fn create_driver(args: CompileOnceArgs) -> CompileDriver<PureCompiler<TypstS
         .exit()
     }
 
-    // Convert the input pairs to a dictionary.
-    let inputs: Dict = args
-        .inputs
-        .iter()
-        .map(|(k, v)| (k.as_str().into(), v.as_str().into_value()))
-        .collect();
+    if is_stdin && args.inputs_file.as_deref() == Some(Path::new("-")) {
+        clap::Error::raw(
+            clap::error::ErrorKind::ArgumentConflict,
+            "cannot read both the entry file and the inputs file from stdin\n",
+        )
+        .exit()
+    }
+
+    // Convert the input pairs to a dictionary, which override the entries of
+    // the inputs file.
+    let mut inputs = match &args.inputs_file {
+        Some(path) => read_inputs_file(path, args.inputs_format),
+        None => Dict::new(),
+    };
+    for (k, v) in &args.inputs {
+        inputs.insert(k.as_str().into(), v.as_str().into_value());
+    }
+
+    let mut package_registry = args.package.to_opts();
+    if package_registry.lockfile.is_none() {
+        let lockfile = workspace_dir.join(PackageLock::FILE_NAME);
+        package_registry.lockfile = lockfile.exists().then_some(lockfile);
+    }
 
     let universe = TypstSystemUniverse::new(CompileOpts {
         entry: EntryOpts::new_workspace(workspace_dir.clone()),
         inputs,
         font_paths: args.font.paths.clone(),
+        package_registry,
         with_embedded_fonts: fonts()
             .map(Cow::Borrowed)
             .chain(args.extra_embedded_fonts)
@@ -113,34 +142,32 @@ pub fn create_driver(args: CompileOnceArgs) -> CompileDriver<PureCompiler<TypstS
     CompileDriver::new(std::marker::PhantomData, world)
 }
 
-pub fn compile_export(args: CompileArgs, exporter: GroupExporter<Document>) -> ! {
+pub fn compile_export(
+    args: CompileArgs,
+    exporter: GroupExporter<Document>,
+    targets: Vec<DepsTarget>,
+) -> ! {
     let is_stdin = args.compile.entry == "-";
     let (intr_tx, intr_rx) = mpsc::unbounded_channel();
 
+    let workspace_archive = Path::new(&args.compile.workspace).is_file();
+    if workspace_archive && args.watch {
+        clap::Error::raw(
+            clap::error::ErrorKind::ArgumentConflict,
+            "cannot watch a workspace archive\n",
+        )
+        .exit()
+    }
+
     let driver = create_driver(args.compile.clone());
 
     // todo: make dynamic layout exporter
-    let output_dir = {
-        // If output is specified, use it.
-        let dir = (!args.compile.output.is_empty()).then(|| Path::new(&args.compile.output));
-        // Otherwise, use the parent directory of the entry file.
-        let entry = driver.entry_file().expect("entry_file is not set");
-        let dir = dir.map(Path::to_owned).unwrap_or_else(|| {
-            if is_stdin {
-                current_dir()
-            } else {
-                entry.parent().expect("entry_file has no parent").to_owned()
-            }
-        });
-        if is_stdin {
-            dir.join("main")
-        } else {
-            dir.join(entry.file_name().expect("entry_file has no file name"))
-        }
-    };
+    let entry = driver.entry_file().expect("entry_file is not set");
+    let output_dir = output_path(&args, (!is_stdin).then_some(entry.as_path()));
 
-    let feature_set =
-        FeatureSet::default().configure(&DIAG_FMT_FEATURE, args.diagnostic_format.into());
+    let feature_set = FeatureSet::default()
+        .configure(&DIAG_FMT_FEATURE, args.diagnostic_format.into())
+        .configure(&FONT_DIAGNOSTICS_FEATURE, !args.no_font_diagnostics);
 
     // CompileExporter + DynamicLayoutCompiler + WatchDriver
     let verse = driver.universe;
@@ -158,8 +185,24 @@ pub fn compile_export(args: CompileArgs, exporter: GroupExporter<Document>) -> !
         exporters.push(Box::new(CompileStarter::new(driver)));
     }
 
+    if args.deps.is_some() || args.deps_json.is_some() {
+        let mut deps = DepsExporter::new(targets);
+        if let Some(path) = &args.deps {
+            deps = deps.with_depfile(path.into());
+        }
+        if let Some(path) = &args.deps_json {
+            deps = deps.with_manifest(path.into());
+        }
+        exporters.push(Box::new(deps));
+    }
+
+    if args.timings.is_some() {
+        profile::enable();
+    }
+
     let handle = Arc::new(CompileHandler {
         exporter: GroupExporter::new(exporters),
+        timings: args.timings.clone(),
     });
 
     let actor = CompileActor::new_with(
@@ -169,7 +212,7 @@ pub fn compile_export(args: CompileArgs, exporter: GroupExporter<Document>) -> !
         CompileServerOpts {
             compile_handle: handle,
             feature_set,
-            ..Default::default()
+            cache: CacheTask::new(cache_config(&args.cache)),
         },
     )
     .with_watch(args.watch);
@@ -179,6 +222,248 @@ pub fn compile_export(args: CompileArgs, exporter: GroupExporter<Document>) -> !
     })
 }
 
+/// Compiles the entry and the additional entries against a single universe,
+/// so that loaded fonts, packages, parsed sources and caches are shared
+/// between entries. Exits with failure if any of the entries fails.
+pub fn compile_entries(args: CompileArgs) -> ! {
+    let conflict = |option: &str| -> ! {
+        clap::Error::raw(
+            clap::error::ErrorKind::ArgumentConflict,
+            format!("cannot use option \"{option}\" and \"--entries\" at the same time\n"),
+        )
+        .exit()
+    };
+    if args.compile.entry == "-" {
+        conflict("--entry -")
+    }
+    if args.watch {
+        conflict("--watch")
+    }
+    if args.dynamic_layout {
+        conflict("--dynamic-layout")
+    }
+    if args.deps.is_some() {
+        conflict("--deps")
+    }
+    if args.deps_json.is_some() {
+        conflict("--deps-json")
+    }
+
+    let mut entries = vec![];
+    if !args.compile.entry.is_empty() {
+        entries.push(PathBuf::from(&args.compile.entry));
+    }
+    for pattern in &args.entries {
+        let matched = utils::expand_glob(pattern).unwrap_or_exit();
+        if matched.is_empty() {
+            clap::Error::raw(
+                clap::error::ErrorKind::InvalidValue,
+                format!("no entry file matches pattern: {pattern}\n"),
+            )
+            .exit()
+        }
+        entries.extend(matched);
+    }
+    let mut seen = HashSet::new();
+    entries = entries
+        .into_iter()
+        .map(|entry| make_absolute(&entry).clean())
+        .filter(|entry| seen.insert(entry.clone()))
+        .collect();
+
+    let verse = create_driver(args.compile.clone()).universe;
+
+    let entries = entries
+        .into_iter()
+        .map(|entry| {
+            let state = verse.entry_state();
+            let state = state.try_select_path_in_workspace(&entry, false);
+            let state = state.ok().flatten().unwrap_or_else(|| {
+                clap::Error::raw(
+                    clap::error::ErrorKind::InvalidValue,
+                    format!(
+                        "entry file path must be in workspace directory: {entry}\n",
+                        entry = entry.display()
+                    ),
+                )
+                .exit()
+            });
+            (entry, state)
+        })
+        .collect::<Vec<_>>();
+
+    // Mirrors the directories of the entries in the workspace under the output
+    // directory, so that entries with the same file name don't overwrite each
+    // other's outputs.
+    let root = verse.entry_state().workspace_root();
+    let mut outputs = HashMap::new();
+    let entries = entries
+        .into_iter()
+        .map(|(entry, state)| {
+            let mut args = args.clone();
+            if !args.compile.output.is_empty() {
+                let dir = entry.parent().expect("entry_file has no parent");
+                let relative = root.as_deref().and_then(|root| dir.strip_prefix(root).ok());
+                let output =
+                    Path::new(&args.compile.output).join(relative.unwrap_or(Path::new("")));
+                std::fs::create_dir_all(&output).unwrap_or_exit();
+                args.compile.output = output.to_string_lossy().into_owned();
+            }
+
+            for target in prepare_exporters(&args, Some(&entry)).1 {
+                let (DepsTarget::File(path) | DepsTarget::Pages(path, _)) = target;
+                if let Some(other) = outputs.insert(path.clone(), entry.clone()) {
+                    clap::Error::raw(
+                        clap::error::ErrorKind::ArgumentConflict,
+                        format!(
+                            "entries {} and {} write to the same output: {}\n",
+                            other.display(),
+                            entry.display(),
+                            path.display()
+                        ),
+                    )
+                    .exit()
+                }
+            }
+
+            (entry, state, args)
+        })
+        .collect::<Vec<_>>();
+
+    let feature_set = FeatureSet::default()
+        .configure(&DIAG_FMT_FEATURE, args.diagnostic_format.into())
+        .configure(&FONT_DIAGNOSTICS_FEATURE, !args.no_font_diagnostics);
+    let feature_set = Arc::new(feature_set);
+
+    if args.timings.is_some() {
+        profile::enable();
+    }
+    let source_cache_stats = Mutex::new(vec![]);
+
+    let compile = |entry: &Path, state: &EntryState, args: &CompileArgs| {
+        let (exporter, _) = prepare_exporters(args, Some(entry));
+        let driver = CompileExporter::new(PureCompiler::<TypstSystemWorld>::default())
+            .with_exporter(exporter);
+        let mut driver = CompileReporter::<_, TypstSystemWorld>::new(driver);
+        driver.set_generic_reporter(ConsoleDiagReporter::default());
+
+        let world = verse.snapshot_with(Some(TaskInputs {
+            entry: Some(state.clone()),
+            inputs: None,
+        }));
+        let mut env = CompileEnv::default().configure_shared(feature_set.clone());
+        let res = driver.compile(&world, &mut env);
+
+        // Each entry is compiled in a fresh snapshot, whose slots only hold
+        // the files read by the entry.
+        let stats = world.source_db.cache_stats();
+        let name = entry.display().to_string();
+        source_cache_stats.lock().unwrap().push((name, stats));
+        res.is_ok()
+    };
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicUsize::new(0);
+    std::thread::scope(|s| {
+        for _ in 0..args.jobs.clamp(1, entries.len()) {
+            s.spawn(|| {
+                while let Some((entry, state, args)) =
+                    entries.get(next.fetch_add(1, Ordering::Relaxed))
+                {
+                    if !compile(entry, state, args) {
+                        failed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+
+    if let Some(path) = &args.timings {
+        let mut stats = source_cache_stats.into_inner().unwrap();
+        stats.sort_by(|a, b| a.0.cmp(&b.0));
+        write_timings(path, &verse.snapshot(), &stats);
+    }
+
+    let failed = failed.into_inner();
+    if failed > 0 {
+        eprintln!("{failed} of {} entries failed to compile", entries.len());
+    }
+    utils::logical_exit(failed == 0)
+}
+
+/// Writes the recorded timings as a Chrome trace-event JSON file, and clears
+/// them for the next compilation.
+fn write_timings(path: &Path, world: &dyn World, stats: &[(String, SourceCacheStats)]) {
+    let res = std::fs::File::create(path)
+        .map_err(|err| err.to_string())
+        .and_then(|file| profile::export_chrome_trace(world, stats, io::BufWriter::new(file)));
+    if let Err(err) = res {
+        log::error!("failed to write timings to {}: {err}", path.display());
+    }
+    profile::clear();
+}
+
+/// Creates the cache eviction config from command line arguments.
+fn cache_config(args: &CacheArgs) -> CacheUserConfig {
+    let default = CacheUserConfig::default();
+
+    let policy = if let Some(n) = args.evict_every {
+        CacheEvictPolicy::Revisions(n)
+    } else if let Some(secs) = args.evict_interval {
+        CacheEvictPolicy::Interval(std::time::Duration::from_secs(secs))
+    } else if let Some(mb) = args.evict_memory {
+        CacheEvictPolicy::MemoryThreshold(mb * 1024 * 1024)
+    } else {
+        default.policy
+    };
+    let policy = if policy.is_supported() {
+        policy
+    } else {
+        log::warn!(
+            "--cache-evict-memory is not supported on this platform, \
+             evicting the cache after every compilation instead"
+        );
+        default.policy
+    };
+
+    CacheUserConfig {
+        max_age: args.max_age.unwrap_or(default.max_age),
+        policy,
+    }
+}
+
+/// Reads the dictionary for `sys.inputs` from a file or stdin (`-`).
+fn read_inputs_file(path: &Path, format: Option<InputsFormat>) -> Dict {
+    let format = format
+        .map(Into::into)
+        .or_else(|| world::InputsFormat::from_path(path))
+        .unwrap_or_else(|| {
+            clap::Error::raw(
+                clap::error::ErrorKind::InvalidValue,
+                format!(
+                    "cannot infer the format of inputs file {}, please specify --inputs-format\n",
+                    path.display()
+                ),
+            )
+            .exit()
+        });
+
+    let data = if path == Path::new("-") {
+        read_from_stdin().map_err(|err| err.to_string())
+    } else {
+        std::fs::read(path).map_err(|err| err.to_string())
+    };
+
+    data.and_then(|data| world::parse_inputs(&data, format).map_err(|err| err.to_string()))
+        .unwrap_or_else(|err| {
+            clap::Error::raw(
+                clap::error::ErrorKind::InvalidValue,
+                format!("failed to read inputs file {}: {err}\n", path.display()),
+            )
+            .exit()
+        })
+}
+
 /// Read from stdin.
 fn read_from_stdin() -> FileResult<Vec<u8>> {
     let mut buf = Vec::new();
@@ -194,10 +479,34 @@ fn read_from_stdin() -> FileResult<Vec<u8>> {
 
 pub struct CompileHandler<F: CompilerFeat> {
     exporter: GroupExporter<CompiledArtifact<F>>,
+    /// The path to write timings of each compilation to.
+    timings: Option<PathBuf>,
 }
 
 impl<F: CompilerFeat + 'static> CompilationHandle<F> for CompileHandler<F> {
-    fn status(&self, _revision: usize, _rep: reflexo_typst::CompileReport) {}
+    fn status(&self, _revision: usize, rep: reflexo_typst::CompileReport) {
+        use reflexo_typst::package::DownloadStatus;
+
+        // Reports package downloads, which may block compilation for a while.
+        if let reflexo_typst::CompileReport::PackageDownload(_, status) = &rep {
+            match status {
+                DownloadStatus::Progress { .. } => log::debug!("{}", rep.message()),
+                DownloadStatus::Failed(..) => log::warn!("{}", rep.message()),
+                DownloadStatus::Started | DownloadStatus::Finished => {
+                    log::info!("{}", rep.message())
+                }
+            }
+        }
+    }
+
+    fn notify_cache_evict(&self, stats: CacheEvictStats) {
+        log::debug!(
+            "evicted cache in {:?}, {} evictions in {:?} totally",
+            stats.last_evict_duration,
+            stats.evict_count,
+            stats.total_evict_duration
+        );
+    }
 
     fn notify_compile(
         &self,
@@ -219,5 +528,14 @@ impl<F: CompilerFeat + 'static> CompilationHandle<F> for CompileHandler<F> {
                 );
             }
         }
+
+        if let Some(path) = &self.timings {
+            let world = compiled.world.as_ref();
+            let name = world
+                .main_id()
+                .map(|id| id.vpath().as_rootless_path().display().to_string());
+            let stats = world.source_db.cache_stats();
+            write_timings(path, world, &[(name.unwrap_or_default(), stats)]);
+        }
     }
 }
@@ -1,12 +1,19 @@
 use std::path::{Path, PathBuf};
 
 use chrono::{Datelike, Timelike};
-use reflexo_typst::exporter_builtins::{FsPathExporter, GroupExporter};
+use reflexo_typst::exporter_builtins::{
+    FsPagedPathExporter, FsPathExporter, GroupExporter, TimedExporter,
+};
+use reflexo_typst::exporter_utils::map_err;
+use reflexo_typst::path::PathClean;
 use reflexo_typst::program_meta::REPORT_BUG_MESSAGE;
-use reflexo_typst::svg::DefaultExportFeature;
-use reflexo_typst::TypstDatetime;
+use reflexo_typst::svg::{DefaultExportFeature, MinifyReport, SvgExportFeature, SvgExportOptions};
+use reflexo_typst::{
+    DepsTarget, PageSelection, SelectPagesExporter, TextExportMode, TypstDatetime,
+};
 
-use crate::{utils::current_dir, CompileArgs, ExportArgs};
+use crate::utils::{current_dir, make_absolute};
+use crate::{CompileArgs, ExportArgs};
 
 type GroupDocExporter = GroupExporter<typst::model::Document>;
 
@@ -16,11 +23,13 @@ pub static AVAILABLE_FORMATS: &[(/* format name */ &str, /* feature hint */ &str
     ("ast", REPORT_BUG_MESSAGE),
     ("nothing", REPORT_BUG_MESSAGE),
     ("pdf", "pdf"),
+    ("png", "png"),
     ("svg", "svg"),
     ("svg_html", "svg"),
     ("sir", "svg"),
     ("vector", "svg"),
     ("text", "text"),
+    ("text_json", "text"),
 ];
 
 /// Hint the user that the given format is not enable or not available.
@@ -56,8 +65,12 @@ fn prepare_exporters_impl(
     args: ExportArgs,
     out: PathBuf,
     mut formats: Vec<String>,
-) -> GroupDocExporter {
+) -> (GroupDocExporter, Vec<DepsTarget>) {
     let mut doc: ExporterVec<Doc> = vec![];
+    // exporters that select pages by themselves to name their outputs by page
+    // numbers
+    let mut paged: ExporterVec<Doc> = vec![];
+    let mut targets: Vec<DepsTarget> = vec![];
 
     /// connect export flow from $x to $y
     #[allow(unused_macros)]
@@ -73,16 +86,32 @@ fn prepare_exporters_impl(
     macro_rules! sink_path {
         ($exporter:ty as $ser:ty as $exporters:ident, $output_dir:ident @@ $extension:literal) => {{
             let output_path = $output_dir.with_extension($extension);
-            $exporters.push(Box::new(FsPathExporter::<$ser, _>::new(
-                output_path,
-                <$exporter>::default(),
+            targets.push(DepsTarget::File(output_path.clone()));
+            $exporters.push(Box::new(TimedExporter::new(
+                concat!("export ", $extension),
+                FsPathExporter::<$ser, _>::new(output_path, <$exporter>::default()),
             )));
         }};
         (|| $exporter:tt as $ser:ty as $exporters:ident, $output_dir:ident @@ $extension:literal) => {{
             let output_path = $output_dir.with_extension($extension);
+            targets.push(DepsTarget::File(output_path.clone()));
             let exporter = $exporter;
-            $exporters.push(Box::new(FsPathExporter::<$ser, _>::new(
-                output_path,
+            $exporters.push(Box::new(TimedExporter::new(
+                concat!("export ", $extension),
+                FsPathExporter::<$ser, _>::new(output_path, exporter),
+            )));
+        }};
+    }
+
+    /// write pages of $exporter to paths `$output_dir-{n} @@ $extension`
+    macro_rules! sink_pages {
+        (|| $exporter:tt as $exporters:ident, $output_dir:ident @@ $extension:literal) => {{
+            let output_path = $output_dir.with_extension($extension);
+            targets.push(DepsTarget::Pages(output_path.clone(), args.pages.clone()));
+            let exporter =
+                FsPagedPathExporter::new(output_path, $exporter).with_pages(args.pages.clone());
+            $exporters.push(Box::new(TimedExporter::new(
+                concat!("export ", $extension),
                 exporter,
             )));
         }};
@@ -100,26 +129,62 @@ fn prepare_exporters_impl(
             "pdf"         => sink_path!(|| {
                 WithPdf::default().with_ctime(args.creation_timestamp.and_then(convert_datetime))
             } as _ as doc, out @@ "pdf"),
+            #[cfg(feature = "png")]
+            "png"         => sink_pages!(|| {
+                WithPng::default().with_pixel_per_pt(args.pixel_per_pt)
+            } as paged, out @@ "png"),
+            #[cfg(feature = "svg")]
+            "svg" if args.svg.per_page => {
+                let output_path = out.with_extension("svg");
+                targets.push(DepsTarget::Pages(output_path.clone(), args.pages.clone()));
+                paged.push(Box::new(TimedExporter::new("export svg", SvgPages {
+                    paths: FsPagedPathExporter::new(output_path, ()),
+                    pages: args.pages.clone(),
+                    exporter: WithSvgPages::default().with_options(SvgExportOptions::from_feature::<SvgExportFeature>().merge(&args.svg.overrides())),
+                })));
+            }
             #[cfg(feature = "svg")]
-            "svg"         => sink_path!(WithSvg as _ as doc, out @@ "artifact.svg"),
+            "svg"         => sink_path!(|| {
+                ReportMinify(WithSvg::default().with_options(SvgExportOptions::from_feature::<SvgExportFeature>().merge(&args.svg.overrides())))
+            } as _ as doc, out @@ "artifact.svg"),
             #[cfg(feature = "svg")]
-            "svg_html"         => sink_path!(WithSvgHtml as _ as doc, out @@ "artifact.svg.html"),
+            "svg_html"    => sink_path!(|| {
+                ReportMinify(WithSvgHtml::default().with_options(SvgExportOptions::from_feature::<DefaultExportFeature>().merge(&args.svg.overrides())))
+            } as _ as doc, out @@ "artifact.svg.html"),
             #[cfg(feature = "svg")]
             "sir"         => sink_path!(WithSIR as _ as doc, out @@ "artifact.sir.in"),
             #[cfg(feature = "svg")]
             "vector"      => sink_path!(WithSIR as _ as doc, out @@ "artifact.sir.in"),
             #[cfg(feature = "text")]
-            "text"      => sink_path!(WithText as _ as doc, out @@ "txt"),
+            "text"      => sink_path!(|| {
+                WithText::default()
+                    .with_mode(if args.text_layout { TextExportMode::Layout } else { TextExportMode::Raw })
+                    .with_pages(args.pages.clone())
+            } as _ as paged, out @@ "txt"),
+            #[cfg(feature = "text")]
+            "text_json" => sink_path!(|| {
+                WithText::default().with_mode(TextExportMode::Json).with_pages(args.pages.clone())
+            } as _ as paged, out @@ "text.json"),
             _             => exit_by_unknown_format(f),
         });
     }
-    return GroupExporter::new(doc);
+
+    // only export the selected pages
+    if let Some(pages) = args.pages {
+        let group = GroupExporter::new(std::mem::take(&mut doc));
+        doc.push(Box::new(SelectPagesExporter::new(pages, group)));
+    }
+    doc.append(&mut paged);
+
+    return (GroupExporter::new(doc), targets);
 
     type Doc = typst::model::Document;
 
     type WithAst = reflexo_typst::AstExporter;
     type WithPdf = reflexo_typst::PdfDocExporter;
+    type WithPng = reflexo_typst::PngExporter;
     type WithSvg = reflexo_typst::PureSvgExporter;
+    type WithSvgPages = reflexo_typst::PureSvgPagesExporter;
     type WithSvgHtml = reflexo_typst::SvgHtmlExporter<DefaultExportFeature>;
     type WithSIR = reflexo_typst::SvgModuleExporter;
     type WithText = reflexo_typst::TextExporter;
@@ -127,26 +192,43 @@ fn prepare_exporters_impl(
     type ExporterVec<T> = Vec<Box<dyn reflexo_typst::Exporter<T> + Send + Sync>>;
 }
 
+/// Gets the path of the outputs, whose extension is replaced by the formats.
+///
+/// The outputs are put in the output directory if specified, or otherwise in
+/// the directory of the entry file, or of the workspace archive containing the
+/// entry file. The outputs of the standard input, i.e. `entry_file` is
+/// `None`, are named `main`.
+pub fn output_path(args: &CompileArgs, entry_file: Option<&Path>) -> PathBuf {
+    // If output is specified, use it.
+    let dir = (!args.compile.output.is_empty()).then(|| Path::new(&args.compile.output));
+    let workspace = Path::new(&args.compile.workspace);
+    let dir = dir.map(Path::to_owned).unwrap_or_else(|| match entry_file {
+        Some(_) if workspace.is_file() => make_absolute(workspace)
+            .clean()
+            .parent()
+            .expect("workspace has no parent")
+            .to_owned(),
+        Some(entry_file) => entry_file
+            .parent()
+            .expect("entry_file has no parent")
+            .to_owned(),
+        None => current_dir(),
+    });
+
+    match entry_file {
+        Some(entry_file) => dir.join(entry_file.file_name().expect("entry_file has no file name")),
+        None => dir.join("main"),
+    }
+}
+
 /// Prepare exporters from command line arguments.
-pub fn prepare_exporters(args: &CompileArgs, entry_file: Option<&Path>) -> GroupDocExporter {
-    let output_dir = {
-        // If output is specified, use it.
-        let dir = (!args.compile.output.is_empty()).then(|| Path::new(&args.compile.output));
-        // Otherwise, use the parent directory of the entry file.
-        let dir = dir.map(Path::to_owned).unwrap_or_else(|| match entry_file {
-            Some(entry_file) => entry_file
-                .parent()
-                .expect("entry_file has no parent")
-                .to_owned(),
-            None => current_dir(),
-        });
-        match entry_file {
-            Some(entry_file) => {
-                dir.join(entry_file.file_name().expect("entry_file has no file name"))
-            }
-            None => dir.join("main"),
-        }
-    };
+///
+/// Returns the exporters and the outputs they write.
+pub fn prepare_exporters(
+    args: &CompileArgs,
+    entry_file: Option<&Path>,
+) -> (GroupDocExporter, Vec<DepsTarget>) {
+    let output_dir = output_path(args, entry_file);
 
     let formats = {
         // If formats are specified, use them.
@@ -172,3 +254,55 @@ fn convert_datetime(date_time: chrono::DateTime<chrono::Utc>) -> Option<TypstDat
         date_time.second().try_into().ok()?,
     )
 }
+
+/// Writes a standalone SVG for each selected page like
+/// [`FsPagedPathExporter`], printing the reports of the stricter minification
+/// labeled by the page numbers.
+#[cfg(feature = "svg")]
+struct SvgPages {
+    paths: FsPagedPathExporter<()>,
+    pages: Option<PageSelection>,
+    exporter: reflexo_typst::PureSvgPagesExporter,
+}
+
+#[cfg(feature = "svg")]
+impl reflexo_typst::Exporter<typst::model::Document> for SvgPages {
+    fn export(
+        &self,
+        world: &dyn typst::World,
+        output: std::sync::Arc<typst::model::Document>,
+    ) -> typst::diag::SourceResult<()> {
+        let (doc, numbers) =
+            PageSelection::select_or_all(self.pages.as_ref(), output).map_err(map_err)?;
+        let pages: Vec<(String, Option<MinifyReport>)> = self.exporter.export(world, doc)?;
+        for (n, (svg, report)) in numbers.into_iter().zip(pages) {
+            if let Some(report) = report {
+                eprintln!("page {n}: {report}");
+            }
+            std::fs::write(self.paths.page_path(n), svg).map_err(map_err)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the report of the stricter minification of SVG.
+#[cfg(feature = "svg")]
+struct ReportMinify<E>(E);
+
+#[cfg(feature = "svg")]
+impl<E, O> reflexo_typst::Exporter<typst::model::Document, O> for ReportMinify<E>
+where
+    E: reflexo_typst::Exporter<typst::model::Document, (O, Option<MinifyReport>)>,
+{
+    fn export(
+        &self,
+        world: &dyn typst::World,
+        output: std::sync::Arc<typst::model::Document>,
+    ) -> typst::diag::SourceResult<O> {
+        let (output, report) = self.0.export(world, output)?;
+        if let Some(report) = report {
+            eprintln!("{report}");
+        }
+        Ok(output)
+    }
+}
@@ -3,17 +3,23 @@ pub mod export;
 pub mod font;
 #[cfg(feature = "gen-manual")]
 pub mod manual;
+pub mod package;
+pub mod project;
 pub mod query;
 pub mod query_repl;
 pub mod utils;
 pub mod version;
 
 use core::fmt;
-use std::{borrow::Cow, path::PathBuf};
+use std::{borrow::Cow, collections::BTreeMap, path::PathBuf};
 
 use chrono::{DateTime, Utc};
 use clap::{builder::ValueParser, ArgAction, Args, Command, Parser, Subcommand, ValueEnum};
 use reflexo_typst::build_info::VERSION;
+use reflexo_typst::config::{PackageRegistryOpts, PackageSource};
+#[cfg(feature = "svg")]
+use reflexo_typst::svg::SvgExportOverrides;
+use reflexo_typst::PageSelection;
 use version::VersionFormat;
 
 /// The character typically used to separate path components
@@ -84,6 +90,26 @@ pub enum FontSubCommands {
     List(ListFontsArgs),
     /// Measure fonts and generate a profile file for compiler
     Measure(MeasureFontsArgs),
+    /// Report the fonts and glyphs used by a document
+    Usage(FontUsageArgs),
+    /// Bundle the glyphs used by a document into a glyph pack
+    Pack(PackFontsArgs),
+    /// Verify or update a profile file generated by `font measure`
+    #[clap(subcommand)]
+    Profile(FontProfileSubCommands),
+}
+
+#[derive(Debug, Subcommand)]
+#[clap(
+    about = "Commands about font profile for typst.",
+    after_help = "",
+    next_display_order = None
+)]
+pub enum FontProfileSubCommands {
+    /// Check whether a profile file is up to date with the font files
+    Verify(VerifyFontProfileArgs),
+    /// Regenerate the out-of-date items of a profile file
+    Update(UpdateFontProfileArgs),
 }
 
 #[derive(Debug, Subcommand)]
@@ -96,6 +122,12 @@ pub enum FontSubCommands {
 pub enum PackageSubCommands {
     /// Lists all discovered packages in data and cache paths
     List(ListPackagesArgs),
+    /// Searches packages in the package index and local data path
+    Search(SearchPackagesArgs),
+    /// Downloads packages into the cache
+    Install(InstallPackagesArgs),
+    /// Copies the packages used by a document into a directory
+    Vendor(VendorPackagesArgs),
     /// Links a package to local data path
     Link(LinkPackagesArgs),
     /// Unlinks a package from local data path
@@ -124,14 +156,25 @@ pub struct CompileOnceArgs {
     #[clap(flatten)]
     pub font: FontArgs,
 
-    /// Path to typst workspace.
+    /// Shared arguments for package related commands.
+    #[clap(flatten)]
+    pub package: PackageArgs,
+
+    /// Path to typst workspace, or to a zip or tar archive bundling the
+    /// workspace, which is compiled without unpacking.
     #[clap(long, short, default_value = ".")]
     pub workspace: String,
 
     /// Path to input Typst file, use `-` to read input from stdin
-    #[clap(long, short, required = true)]
+    #[clap(long, short, required_unless_present = "target", default_value = "")]
     pub entry: String,
 
+    /// Uses the named target in the project configuration (`typst-ts.toml`)
+    /// at the workspace root. Command line options override the settings of
+    /// the target.
+    #[clap(long, short, value_name = "NAME")]
+    pub target: Option<String>,
+
     /// Add a string key-value pair visible through `sys.inputs`
     #[clap(
         long = "input",
@@ -141,6 +184,17 @@ pub struct CompileOnceArgs {
     )]
     pub inputs: Vec<(String, String)>,
 
+    /// Reads a dictionary visible through `sys.inputs` from a JSON, TOML or
+    /// YAML file, use `-` to read from stdin. Pairs given by `--input`
+    /// override the entries of the file.
+    #[clap(long = "inputs-file", value_name = "PATH")]
+    pub inputs_file: Option<PathBuf>,
+
+    /// The format of the inputs file, inferred from the extension of the file
+    /// if not specified. Required when reading inputs from stdin.
+    #[clap(long = "inputs-format", value_name = "FORMAT")]
+    pub inputs_format: Option<InputsFormat>,
+
     /// Output to directory, default in the same directory as the entry file.
     #[clap(long, short, default_value = "")]
     pub output: String,
@@ -149,6 +203,78 @@ pub struct CompileOnceArgs {
     pub extra_embedded_fonts: Vec<Cow<'static, [u8]>>,
 }
 
+/// Shared arguments for package related commands
+#[derive(Default, Debug, Clone, Parser)]
+pub struct PackageArgs {
+    /// Base URL of a registry serving the `preview` namespace, which can be
+    /// given multiple times to fall back in order. Default:
+    /// `https://packages.typst.org`
+    #[clap(long = "package-registry", value_name = "URL", action = ArgAction::Append)]
+    pub registries: Vec<String>,
+
+    /// Fetches packages in a namespace from a URL template containing
+    /// `{name}` and `{version}`, a registry base URL, or a local directory
+    /// containing packages at `{dir}/{namespace}/{name}/{version}`, which
+    /// can be given multiple times to fall back in order
+    #[clap(
+        long = "package-namespace",
+        value_name = "NAMESPACE=SOURCE",
+        action = ArgAction::Append,
+        value_parser = ValueParser::new(parse_input_pair),
+    )]
+    pub namespaces: Vec<(String, String)>,
+
+    /// Add additional directories containing packages at
+    /// `{dir}/{namespace}/{name}/{version}`, e.g. vendored packages
+    #[clap(
+        long = "package-path",
+        id = "package_paths",
+        value_name = "DIR",
+        action = ArgAction::Append
+    )]
+    pub paths: Vec<PathBuf>,
+
+    /// The timeout in seconds of each request to package registries
+    #[clap(long = "package-timeout", value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// The number of times to retry a package download after network
+    /// failures, default: 2
+    #[clap(long = "package-retries", value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Fails instead of downloading packages which are not on disk
+    #[clap(long)]
+    pub offline: bool,
+
+    /// Records the resolved packages with their checksums into the lockfile,
+    /// and verifies packages against it. Default: `typst-ts.lock` at the
+    /// workspace root if the file exists
+    #[clap(long, value_name = "PATH")]
+    pub lockfile: Option<PathBuf>,
+}
+
+impl PackageArgs {
+    pub fn to_opts(&self) -> PackageRegistryOpts {
+        let mut namespaces = BTreeMap::<_, Vec<_>>::new();
+        for (namespace, source) in &self.namespaces {
+            let sources = namespaces.entry(namespace.clone()).or_default();
+            sources.push(PackageSource::parse(source));
+        }
+
+        PackageRegistryOpts {
+            registries: self.registries.clone(),
+            namespaces,
+            paths: self.paths.clone(),
+            offline: self.offline,
+            lockfile: self.lockfile.clone(),
+            timeout: self.timeout,
+            retries: self.retries,
+            ..PackageRegistryOpts::default()
+        }
+    }
+}
+
 /// Parses key/value pairs split by the first equal sign.
 ///
 /// This function will return an error if the argument contains no equals sign
@@ -178,10 +304,146 @@ pub struct ExportArgs {
         value_parser = parse_source_date_epoch,
     )]
     pub creation_timestamp: Option<DateTime<Utc>>,
+
+    /// The number of pixels per point when exporting raster images (`png`).
+    #[clap(
+        long = "pixel-per-pt",
+        value_name = "SCALE",
+        default_value_t = 3.,
+        value_parser = parse_pixel_per_pt,
+    )]
+    pub pixel_per_pt: f32,
+
+    /// Which pages to export, e.g. `1,3-5`. Exports all pages if not specified.
+    #[clap(long = "pages", value_name = "PAGES")]
+    pub pages: Option<PageSelection>,
+
+    /// Exports text (`text`) in geometric reading order, with pages separated
+    /// by form feeds.
+    #[clap(long = "text-layout")]
+    pub text_layout: bool,
+
+    /// Shared arguments for SVG export.
+    #[clap(flatten)]
+    pub svg: SvgArgs,
+}
+
+/// Arguments overriding the default options of SVG export (`svg`,
+/// `svg_html`).
+#[derive(Default, Debug, Clone, Parser)]
+#[clap(next_help_heading = "SVG options")]
+pub struct SvgArgs {
+    /// Whether to render text elements, which make the text in SVG
+    /// selectable and searchable. Default: true
+    #[clap(long = "svg-text-element", value_name = "BOOL")]
+    pub text_element: Option<bool>,
+
+    /// Whether to include the builtin CSS. Default: true
+    #[clap(long = "svg-builtin-css", value_name = "BOOL")]
+    pub builtin_css: Option<bool>,
+
+    /// Whether to include the JavaScript for interactive and responsive
+    /// actions. Default: true for `svg_html`, false for `svg`
+    #[clap(long = "svg-responsive-js", value_name = "BOOL")]
+    pub responsive_js: Option<bool>,
+
+    /// Whether to use glyph ids which are stable across SVG files. Default:
+    /// true
+    #[clap(long = "svg-stable-glyph-id", value_name = "BOOL")]
+    pub stable_glyph_id: Option<bool>,
+
+    /// Whether to attach debug info to SVG elements. Default: false
+    #[clap(long = "svg-debug-info", value_name = "BOOL")]
+    pub debug_info: Option<bool>,
+
+    /// Whether to rasterize text, which takes effect only if the compiler is
+    /// built with the `aggresive-browser-rasterization` feature. Default:
+    /// false
+    #[clap(long = "svg-rasterize-text", value_name = "BOOL")]
+    pub rasterize_text: Option<bool>,
+
+    /// Whether to render text as `<text>` elements in the used fonts, which
+    /// are subset and embedded as WOFF2 web fonts. Text that cannot be
+    /// represented in its font is still rendered as outlines. Default: false
+    #[clap(long = "svg-web-fonts", value_name = "BOOL")]
+    pub web_fonts: Option<bool>,
+
+    /// Whether to minify SVG more strictly, which deduplicates identical clip
+    /// paths and gradients, drops empty groups and merges shared presentation
+    /// attributes into CSS classes. The size before and after minification is
+    /// reported. Default: false
+    #[clap(long = "svg-strict-minify", value_name = "BOOL")]
+    pub strict_minify: Option<bool>,
+
+    /// The number of decimal places kept in path data and translations. By
+    /// default, the numbers are kept as they are.
+    #[clap(long = "svg-precision", value_name = "N")]
+    pub precision: Option<u8>,
+
+    /// Writes a standalone SVG for each page as `<name>-<n>.svg` in the `svg`
+    /// format, instead of one SVG with all pages stacked vertically.
+    #[clap(long = "svg-per-page")]
+    pub per_page: bool,
+}
+
+#[cfg(feature = "svg")]
+impl SvgArgs {
+    /// Gets the overrides of the default options of an SVG exporter.
+    pub fn overrides(&self) -> SvgExportOverrides {
+        SvgExportOverrides {
+            render_text_element: self.text_element,
+            with_builtin_css: self.builtin_css,
+            with_responsive_js: self.responsive_js,
+            use_stable_glyph_id: self.stable_glyph_id,
+            attach_debug_info: self.debug_info,
+            rasterize_text: self.rasterize_text,
+            embed_web_fonts: self.web_fonts,
+            strict_minify: self.strict_minify,
+            numeric_precision: self.precision,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Parser)]
+#[clap(next_help_heading = "Cache options")]
+pub struct CacheArgs {
+    /// The maximum age of compilation cache entries kept by an eviction.
+    #[clap(long = "cache-max-age", value_name = "AGE")]
+    pub max_age: Option<usize>,
+
+    /// Evicts the compilation cache once every N compilations, instead of
+    /// after every compilation.
+    #[clap(
+        long = "cache-evict-every",
+        value_name = "N",
+        group = "cache-evict-policy"
+    )]
+    pub evict_every: Option<usize>,
+
+    /// Evicts the compilation cache when at least SECS seconds have elapsed
+    /// since the last eviction.
+    #[clap(
+        long = "cache-evict-interval",
+        value_name = "SECS",
+        group = "cache-evict-policy"
+    )]
+    pub evict_interval: Option<u64>,
+
+    /// Evicts the compilation cache when the memory usage of the process
+    /// exceeds MB megabytes. The memory usage is only known on Linux, and the
+    /// cache is evicted after every compilation on other platforms.
+    #[clap(
+        long = "cache-evict-memory",
+        value_name = "MB",
+        group = "cache-evict-policy"
+    )]
+    pub evict_memory: Option<u64>,
 }
 
 #[derive(Default, Debug, Clone, Parser)]
 #[clap(next_help_heading = "Compile options")]
+#[clap(mut_arg("entry", |arg| arg.required_unless_present("entries")))]
 pub struct CompileArgs {
     /// compile arguments before query.
     #[clap(flatten)]
@@ -190,6 +452,19 @@ pub struct CompileArgs {
     #[clap(flatten)]
     pub export: ExportArgs,
 
+    /// Additional entry files to compile along with `--entry`, if given. All
+    /// entries are compiled in a single run, sharing loaded fonts, packages
+    /// and caches. Glob patterns such as `docs/**/*.typ` are expanded. With
+    /// `--output`, the outputs of an entry are written to its directory
+    /// relative to the workspace root under the output directory.
+    #[clap(long, value_name = "PATH_OR_GLOB", num_args = 1..)]
+    pub entries: Vec<String>,
+
+    /// The number of entries to compile in parallel, when compiling multiple
+    /// entries.
+    #[clap(long, short = 'j', default_value_t = 1)]
+    pub jobs: usize,
+
     /// Runs compilation in watch mode.
     #[clap(long)]
     pub watch: bool,
@@ -200,7 +475,7 @@ pub struct CompileArgs {
     #[clap(long)]
     pub dynamic_layout: bool,
 
-    /// Outputs format(s), possible values: `ast`, `pdf`, `svg`, and,
+    /// Outputs format(s), possible values: `ast`, `pdf`, `png`, `svg`, and,
     /// `svg_html`.
     #[clap(long)]
     pub format: Vec<String>,
@@ -212,6 +487,30 @@ pub struct CompileArgs {
         value_parser = clap::value_parser!(DiagnosticFormat)
     )]
     pub diagnostic_format: DiagnosticFormat,
+
+    /// Disables the warnings about unknown font families and characters
+    /// which no font covers
+    #[clap(long)]
+    pub no_font_diagnostics: bool,
+
+    /// Writes a Makefile-style depfile listing the files read by the
+    /// compilation to the given path.
+    #[clap(long, value_name = "PATH")]
+    pub deps: Option<String>,
+
+    /// Writes a JSON manifest listing the files read by the compilation,
+    /// with their content hashes, to the given path.
+    #[clap(long, value_name = "PATH")]
+    pub deps_json: Option<String>,
+
+    /// Records the timings of compilation stages, such as parsing,
+    /// evaluation, layout, exporting, font loading and package downloading,
+    /// and writes them to the given path as a Chrome trace-event JSON file.
+    #[clap(long, value_name = "PATH")]
+    pub timings: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub cache: CacheArgs,
 }
 
 /// Processes an input file to extract provided metadata
@@ -262,6 +561,22 @@ pub struct ListFontsArgs {
     /// Also list style variants of each font family
     #[arg(long)]
     pub variants: bool,
+
+    /// Print the fonts of each family in JSON, including the paths, the
+    /// indices in the font files, the coverage hashes and the variants
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Report the fonts and glyphs used by a document
+#[derive(Debug, Clone, Parser)]
+pub struct FontUsageArgs {
+    /// Print the report in JSON
+    #[arg(long)]
+    pub json: bool,
+
+    #[clap(flatten)]
+    pub compile: CompileOnceArgs,
 }
 
 /// Measure fonts and generate a profile file for compiler
@@ -280,6 +595,42 @@ pub struct MeasureFontsArgs {
     pub no_system_fonts: bool,
 }
 
+/// Check whether a profile file is up to date with the font files
+#[derive(Debug, Clone, Parser)]
+pub struct VerifyFontProfileArgs {
+    /// Path to profile file
+    #[arg(long, required = true)]
+    pub profile: PathBuf,
+}
+
+/// Regenerate the out-of-date items of a profile file
+#[derive(Debug, Clone, Parser)]
+pub struct UpdateFontProfileArgs {
+    /// Shared arguments for font related commands.
+    #[clap(flatten)]
+    pub font: FontArgs,
+
+    /// Path to profile file, which is created if it doesn't exist
+    #[arg(long, required = true)]
+    pub profile: PathBuf,
+
+    /// Exclude system font paths
+    #[arg(long)]
+    pub no_system_fonts: bool,
+}
+
+/// Bundle the glyphs used by a document into a glyph pack
+#[derive(Debug, Clone, Parser)]
+pub struct PackFontsArgs {
+    /// Path to output glyph pack file, default to `<entry>.glyphs.json` in
+    /// the output directory
+    #[arg(long, value_name = "PATH")]
+    pub pack: Option<PathBuf>,
+
+    #[clap(flatten)]
+    pub compile: CompileOnceArgs,
+}
+
 #[derive(ValueEnum, Debug, Clone)]
 pub enum EnvKey {
     Features,
@@ -315,6 +666,48 @@ pub struct ListPackagesArgs {
     pub long: bool,
 }
 
+#[derive(Debug, Clone, Parser)]
+pub struct SearchPackagesArgs {
+    /// Text to search in the names and descriptions of packages
+    #[clap(default_value = "")]
+    pub query: String,
+
+    /// Fetches the package index even if the cached copy is up to date
+    #[clap(long)]
+    pub refresh: bool,
+
+    /// Shared arguments for package related commands.
+    #[clap(flatten)]
+    pub package: PackageArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct InstallPackagesArgs {
+    /// Packages to install, e.g. `@preview/example:0.1.0`
+    #[clap(required = true)]
+    pub specs: Vec<String>,
+
+    /// Shared arguments for package related commands.
+    #[clap(flatten)]
+    pub package: PackageArgs,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct VendorPackagesArgs {
+    /// Directory to copy packages into, which can be used by
+    /// `--package-path` later
+    #[clap(long, value_name = "DIR", default_value = "vendor")]
+    pub dir: PathBuf,
+
+    /// Vendors the packages discovered so far even if the document fails to
+    /// compile, which may miss some packages imported by the document
+    #[clap(long)]
+    pub allow_partial: bool,
+
+    #[clap(flatten)]
+    pub compile: CompileOnceArgs,
+}
+
 #[derive(Debug, Clone, Parser)]
 pub struct LinkPackagesArgs {
     /// Path to package manifest file
@@ -339,11 +732,30 @@ pub struct GenPackagesDocArgs {
     pub dynamic_layout: bool,
 }
 
+/// The format of structured data for `sys.inputs`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, ValueEnum)]
+pub enum InputsFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl From<InputsFormat> for reflexo_typst::world::InputsFormat {
+    fn from(fmt: InputsFormat) -> Self {
+        match fmt {
+            InputsFormat::Json => Self::Json,
+            InputsFormat::Toml => Self::Toml,
+            InputsFormat::Yaml => Self::Yaml,
+        }
+    }
+}
+
 /// Which format to use for diagnostics.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, ValueEnum)]
 pub enum DiagnosticFormat {
     Human,
     Short,
+    Json,
 }
 
 impl From<DiagnosticFormat> for reflexo_typst::DiagnosticFormat {
@@ -351,6 +763,7 @@ impl From<DiagnosticFormat> for reflexo_typst::DiagnosticFormat {
         match fmt {
             DiagnosticFormat::Human => Self::Human,
             DiagnosticFormat::Short => Self::Short,
+            DiagnosticFormat::Json => Self::Json,
         }
     }
 }
@@ -375,6 +788,17 @@ pub fn get_cli(sub_command_required: bool) -> Command {
     Opts::augment_args(cli).subcommand_required(sub_command_required)
 }
 
+/// Parses a positive and finite scale of raster images.
+fn parse_pixel_per_pt(raw: &str) -> Result<f32, String> {
+    let scale: f32 = raw
+        .parse()
+        .map_err(|err| format!("scale must be a number ({err})"))?;
+    if !scale.is_finite() || scale <= 0. {
+        return Err("scale must be a positive and finite number".to_owned());
+    }
+    Ok(scale)
+}
+
 /// Parses a UNIX timestamp according to <https://reproducible-builds.org/specs/source-date-epoch/>
 fn parse_source_date_epoch(raw: &str) -> Result<DateTime<Utc>, String> {
     let timestamp: i64 = raw
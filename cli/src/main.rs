@@ -1,5 +1,6 @@
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     path::{Path, PathBuf},
     process::exit,
     sync::Arc,
@@ -7,15 +8,29 @@ use std::{
 
 use clap::FromArgMatches;
 use reflexo_typst::config::{entry::EntryOpts, CompileOpts};
+use reflexo_typst::debug_loc::DataSource;
 use reflexo_typst::error::prelude::*;
 use reflexo_typst::exporter_builtins::GroupExporter;
 use reflexo_typst::exporter_utils::map_err;
+use reflexo_typst::font::system::SystemFontSearcher;
+use reflexo_typst::font::{get_font_coverage_hash, FontProfile, FontProfileError, FontUsage};
+use reflexo_typst::package::{http::HttpRegistry, PackageRegistry, PackageSpec};
 use reflexo_typst::path::{unix_slash, PathClean};
-use reflexo_typst::TypstSystemUniverse;
-use typst::{model::Document, text::FontVariant, World};
+use reflexo_typst::{
+    CompileEnv, CompileReporter, Compiler, ConsoleDiagReporter, Exporter, GlyphPackExporter,
+    PureCompiler, TypstSystemUniverse, TypstSystemWorld,
+};
+use serde::Serialize;
+use typst::{
+    diag::EcoString,
+    model::Document,
+    text::{FontInfo, FontVariant},
+    World,
+};
 use typst_assets::fonts;
-use typst_ts_cli::compile::compile_export;
+use typst_ts_cli::compile::{compile_entries, compile_export, create_driver};
 use typst_ts_cli::manual::generate_manual;
+use typst_ts_cli::project::{apply_target, apply_target_once};
 use typst_ts_cli::query::serialize;
 use typst_ts_cli::utils::*;
 use typst_ts_cli::version::*;
@@ -62,9 +77,18 @@ fn main() {
         Some(Subcommands::Font(font_sub)) => match font_sub {
             FontSubCommands::List(args) => list_fonts(args),
             FontSubCommands::Measure(args) => measure_fonts(args),
+            FontSubCommands::Usage(args) => font_usage(args),
+            FontSubCommands::Pack(args) => pack_fonts(args),
+            FontSubCommands::Profile(sub) => match sub {
+                FontProfileSubCommands::Verify(args) => verify_font_profile(args),
+                FontProfileSubCommands::Update(args) => update_font_profile(args),
+            },
         },
         Some(Subcommands::Package(pkg_sub)) => match pkg_sub {
             PackageSubCommands::List(args) => list_packages(args),
+            PackageSubCommands::Search(args) => search_packages(args),
+            PackageSubCommands::Install(args) => install_packages(args),
+            PackageSubCommands::Vendor(args) => vendor_packages(args),
             PackageSubCommands::Link(args) => link_packages(args, false),
             PackageSubCommands::Unlink(args) => link_packages(args, true),
             PackageSubCommands::Doc(args) => doc_packages(args),
@@ -82,6 +106,7 @@ fn compile(args: CompileArgs) -> ! {
     // adjust arguments
     let args = {
         let mut args = args;
+        apply_target(&mut args);
         if args.dynamic_layout {
             if !args.format.is_empty() {
                 clap::Error::raw(
@@ -97,18 +122,24 @@ fn compile(args: CompileArgs) -> ! {
         args
     };
 
+    if !args.entries.is_empty() {
+        compile_entries(args)
+    }
+
     let is_stdin = args.compile.entry == "-";
     let entry_file_path = (!is_stdin).then(|| Path::new(args.compile.entry.as_str()).clean());
-    let exporter = typst_ts_cli::export::prepare_exporters(&args, entry_file_path.as_deref());
+    let (exporter, targets) =
+        typst_ts_cli::export::prepare_exporters(&args, entry_file_path.as_deref());
 
-    compile_export(args, exporter)
+    compile_export(args, exporter, targets)
 }
 
 /// Execute a query command.
 pub fn query(args: QueryArgs) -> ! {
     use reflexo_typst::query::retrieve;
     use typst_ts_cli::query::format;
-    let compile_args = args.compile.clone();
+    let mut compile_args = args.compile.clone();
+    apply_target(&mut compile_args);
 
     let mut exporter = GroupExporter::<Document>::new(vec![]);
 
@@ -126,12 +157,13 @@ pub fn query(args: QueryArgs) -> ! {
         Ok(())
     }));
 
-    compile_export(compile_args, exporter)
+    compile_export(compile_args, exporter, vec![])
 }
 
 fn query_repl(args: QueryReplArgs) -> ! {
     use typst_ts_cli::query_repl::start_repl_test;
-    let compile_args = args.compile.clone();
+    let mut compile_args = args.compile.clone();
+    apply_target_once(&mut compile_args);
 
     start_repl_test(compile_args).unwrap();
     exit(0)
@@ -161,6 +193,32 @@ fn list_fonts(command: ListFontsArgs) -> ! {
     .unwrap_or_exit();
     let world = verse.snapshot();
 
+    if command.json {
+        let resolver = world.font_resolver.as_ref();
+        let mut families = BTreeMap::<_, FontFamilyRecord>::new();
+        for idx in 0..resolver.len() {
+            let Some(info) = world.book().info(idx) else {
+                continue;
+            };
+            let index = resolver.font_index_by_id(idx).unwrap_or_default();
+            let path = resolver.describe_font_by_id(idx);
+            let record = FontRecord::new(info, index, path.as_deref());
+
+            families
+                .entry(info.family.to_lowercase())
+                .or_insert_with(|| FontFamilyRecord {
+                    family: info.family.clone(),
+                    fonts: vec![],
+                })
+                .fonts
+                .push(record);
+        }
+
+        let families = families.into_values().collect::<Vec<_>>();
+        println!("{}", serde_json::to_string_pretty(&families).unwrap());
+        exit(0)
+    }
+
     for (name, infos) in world.book().families() {
         println!("{name}");
         if command.variants {
@@ -178,41 +236,202 @@ fn list_fonts(command: ListFontsArgs) -> ! {
     exit(0)
 }
 
-fn measure_fonts(args: MeasureFontsArgs) -> ! {
-    let mut root_path = PathBuf::new();
-    // todo: should cover default workspace path
-    root_path.push("-");
+#[derive(Serialize)]
+struct FontFamilyRecord {
+    family: String,
+    fonts: Vec<FontRecord>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FontRecord {
+    family: String,
+    /// The path to the font file, or `None` for fonts in memory.
+    path: Option<String>,
+    /// The index of the font in the font file.
+    index: u32,
+    coverage_hash: String,
+    variant: FontVariant,
+}
 
-    let mut font_profile_paths = vec![];
-    if args.output.exists() {
-        font_profile_paths.push(args.output.clone());
+impl FontRecord {
+    fn new(info: &FontInfo, index: u32, source: Option<&DataSource>) -> Self {
+        Self {
+            family: info.family.clone(),
+            path: match source {
+                Some(DataSource::Fs(source)) => Some(source.path.clone()),
+                _ => None,
+            },
+            index,
+            coverage_hash: get_font_coverage_hash(&info.coverage),
+            variant: info.variant,
+        }
     }
+}
 
-    let world = TypstSystemUniverse::new(CompileOpts {
-        entry: EntryOpts::new_workspace(root_path.as_path().into()),
-        font_paths: args.font.paths,
-        font_profile_cache_path: args.output.clone(),
-        no_system_fonts: args.no_system_fonts,
-        ..CompileOpts::default()
-    })
-    .unwrap_or_exit();
+#[derive(Serialize)]
+struct FontUsageRecord {
+    #[serde(flatten)]
+    font: FontRecord,
+    /// The ids of the used glyphs.
+    glyphs: Vec<u16>,
+    /// The code points shaped into the used glyphs.
+    codepoints: Vec<u32>,
+}
+
+fn font_usage(mut args: FontUsageArgs) -> ! {
+    apply_target_once(&mut args.compile);
+
+    let driver = create_driver(args.compile.clone());
+    let world = driver.snapshot();
+
+    let mut compiler = CompileReporter::<_, TypstSystemWorld>::new(PureCompiler::default());
+    compiler.set_generic_reporter(ConsoleDiagReporter::default());
+    let Ok(doc) = compiler.compile(&world, &mut CompileEnv::default()) else {
+        exit(1);
+    };
+
+    let resolver = world.font_resolver.as_ref();
+    let usages = FontUsage::collect(&doc).into_iter().map(|usage| {
+        let font = &usage.font;
+        let source = resolver.describe_font(font);
+        FontUsageRecord {
+            font: FontRecord::new(font.info(), font.index(), source.as_deref()),
+            glyphs: usage.glyphs.into_iter().collect(),
+            codepoints: usage.chars.into_iter().map(u32::from).collect(),
+        }
+    });
+    let usages = usages.collect::<Vec<_>>();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&usages).unwrap());
+        exit(0)
+    }
+
+    for usage in usages {
+        let font = &usage.font;
+        let path = font.path.as_deref().unwrap_or("<memory>");
+        println!("{} ({path}, index {})", font.family, font.index);
+
+        let text = usage.codepoints.iter().filter_map(|&c| char::from_u32(c));
+        println!(
+            "  {} glyphs, {} codepoints: {:?}",
+            usage.glyphs.len(),
+            usage.codepoints.len(),
+            text.collect::<String>()
+        );
+    }
+
+    exit(0)
+}
+
+/// Searches fonts and profiles the font files, reusing the up-to-date items
+/// of the previous profile if any.
+fn search_profiled_fonts(
+    font: FontArgs,
+    no_system_fonts: bool,
+    prev: Option<&Path>,
+) -> SystemFontSearcher {
+    let mut searcher = SystemFontSearcher::new();
+    searcher.set_can_profile(true);
+    searcher.set_profile_hash(true);
+    if let Some(prev) = prev {
+        match searcher.add_profile_by_path(prev) {
+            Ok(()) | Err(FontProfileError::NotFound(..)) => {}
+            Err(err) => eprintln!("warning: {err}, regenerating all items"),
+        }
+    }
+
+    for path in font.paths {
+        if path.is_dir() {
+            searcher.search_dir(&path);
+        } else {
+            let _ = searcher.search_file(&path);
+        }
+    }
+    if !no_system_fonts {
+        searcher.search_system();
+    }
+    searcher.flush();
+
+    searcher
+}
+
+fn measure_fonts(args: MeasureFontsArgs) -> ! {
+    let searcher = search_profiled_fonts(args.font, args.no_system_fonts, None);
+    searcher.write_profile(&args.output).unwrap_or_exit();
+
+    exit(0)
+}
 
-    // create directory for args.output
-    if let Some(output) = args.output.parent() {
-        std::fs::create_dir_all(output).unwrap();
+fn verify_font_profile(args: VerifyFontProfileArgs) -> ! {
+    let profile = FontProfile::read(&args.profile, reflexo_typst::world::build_info::VERSION)
+        .unwrap_or_exit();
+
+    let mut stale = 0;
+    for item in &profile.items {
+        if let Some(reason) = item.stale(true) {
+            let path = item.path().map(String::as_str).unwrap_or("<unknown>");
+            println!("{reason}: {path}");
+            stale += 1;
+        }
     }
 
-    let profile = serde_json::to_vec(world.font_resolver.profile()).unwrap();
+    eprintln!(
+        "{stale} of {} items are out of date in {}",
+        profile.items.len(),
+        unix_slash(&args.profile)
+    );
+    logical_exit(stale == 0)
+}
+
+fn update_font_profile(args: UpdateFontProfileArgs) -> ! {
+    let searcher = search_profiled_fonts(args.font, args.no_system_fonts, Some(&args.profile));
+    searcher.write_profile(&args.profile).unwrap_or_exit();
+
+    let stats = searcher.profile_stats();
+    eprintln!(
+        "updated {}: {} items reused, {} regenerated, {} removed",
+        unix_slash(&args.profile),
+        stats.reused,
+        stats.regenerated,
+        stats.removed
+    );
+    exit(0)
+}
 
-    // gzip
-    use flate2::write::GzEncoder;
-    use flate2::Compression;
-    use std::io::Write;
+fn pack_fonts(mut args: PackFontsArgs) -> ! {
+    apply_target_once(&mut args.compile);
 
-    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
-    encoder.write_all(&profile).unwrap();
-    std::fs::write(args.output, encoder.finish().unwrap()).unwrap();
+    let driver = create_driver(args.compile.clone());
+    let world = driver.snapshot();
 
+    let mut compiler = CompileReporter::<_, TypstSystemWorld>::new(PureCompiler::default());
+    compiler.set_generic_reporter(ConsoleDiagReporter::default());
+    let Ok(doc) = compiler.compile(&world, &mut CompileEnv::default()) else {
+        exit(1);
+    };
+
+    let pack_path = args.pack.unwrap_or_else(|| {
+        let entry = Path::new(&args.compile.entry);
+        let name = entry.with_extension("glyphs.json");
+        match args.compile.output.as_str() {
+            "" => name,
+            output => Path::new(output).join(name.file_name().unwrap()),
+        }
+    });
+
+    let Ok(pack) = GlyphPackExporter.export(&world, doc) else {
+        exit(1);
+    };
+    let glyphs = pack.fonts.iter().map(|f| f.glyphs.len()).sum::<usize>();
+    std::fs::write(&pack_path, pack.to_vec()).unwrap_or_exit();
+
+    eprintln!(
+        "packed {glyphs} glyphs of {} fonts into {}",
+        pack.fonts.len(),
+        unix_slash(&pack_path)
+    );
     exit(0)
 }
 
@@ -273,6 +492,85 @@ fn list_packages(args: ListPackagesArgs) -> ! {
     exit(0)
 }
 
+fn search_packages(args: SearchPackagesArgs) -> ! {
+    let mut opts = args.package.to_opts();
+    if args.refresh {
+        opts.index_ttl = Some(0);
+    }
+    let registry = HttpRegistry::new(opts);
+    let query = args.query.to_lowercase();
+
+    // Shows the latest version of each package.
+    let mut latest = BTreeMap::<_, &(PackageSpec, Option<EcoString>)>::new();
+    for pkg in registry.packages() {
+        let (spec, description) = pkg;
+        let matched = spec.name.to_lowercase().contains(&query)
+            || description
+                .as_ref()
+                .is_some_and(|d| d.to_lowercase().contains(&query));
+        if !matched {
+            continue;
+        }
+
+        let key = (spec.namespace.clone(), spec.name.clone());
+        let entry = latest.entry(key).or_insert(pkg);
+        if entry.0.version < spec.version {
+            *entry = pkg;
+        }
+    }
+
+    for (spec, description) in latest.into_values() {
+        println!("{spec}");
+        if let Some(description) = description {
+            println!("  {description}");
+        }
+    }
+
+    exit(0)
+}
+
+fn install_packages(args: InstallPackagesArgs) -> ! {
+    let registry = HttpRegistry::new(args.package.to_opts());
+
+    let specs = args.specs.iter().map(|spec| {
+        spec.parse::<PackageSpec>().unwrap_or_else(|err| {
+            clap::Error::raw(
+                clap::error::ErrorKind::InvalidValue,
+                format!("invalid package spec {spec:?}: {err}\n"),
+            )
+            .exit()
+        })
+    });
+
+    let mut failed = false;
+    for spec in specs.collect::<Vec<_>>() {
+        match registry.resolve(&spec) {
+            Ok(dir) => eprintln!("installed package: {spec} -> {}", unix_slash(&dir)),
+            Err(err) => {
+                eprintln!("failed to install package {spec}: {err}");
+                failed = true;
+            }
+        }
+    }
+
+    logical_exit(!failed)
+}
+
+fn vendor_packages(args: VendorPackagesArgs) -> ! {
+    let dir = args.dir.clone();
+    let specs = typst_ts_cli::package::vendor_packages(args).unwrap_or_else(|err| {
+        eprintln!("error: {err}");
+        logical_exit(false)
+    });
+
+    eprintln!(
+        "vendored {} packages, compile with `--package-path {} --offline` to use them",
+        specs.len(),
+        unix_slash(&dir)
+    );
+    exit(0)
+}
+
 fn link_packages(args: LinkPackagesArgs, should_delete: bool) -> ! {
     fn get_string(v: &toml::Value) -> &str {
         match v {
@@ -0,0 +1,147 @@
+use std::collections::BTreeSet;
+
+use reflexo_typst::package::{PackageRegistry, PackageSpec};
+use reflexo_typst::path::unix_slash;
+use reflexo_typst::{
+    CompileEnv, CompileReporter, Compiler, ConsoleDiagReporter, PureCompiler, TypstSystemWorld,
+};
+use typst::diag::{eco_format, StrResult};
+
+use crate::compile::create_driver;
+use crate::project::apply_target_once;
+use crate::utils::{copy_dir_all, make_absolute};
+use crate::VendorPackagesArgs;
+
+/// Copies the packages imported by the document, including the packages
+/// imported by packages, into `{dir}/{namespace}/{name}/{version}`.
+///
+/// The packages are discovered by compiling the document, so a document that
+/// fails to compile may import more packages than discovered. In that case,
+/// no package is vendored unless `allow_partial` is set.
+pub fn vendor_packages(mut args: VendorPackagesArgs) -> StrResult<Vec<PackageSpec>> {
+    apply_target_once(&mut args.compile);
+
+    let driver = create_driver(args.compile.clone());
+    let world = driver.snapshot();
+
+    let mut compiler = CompileReporter::<_, TypstSystemWorld>::new(PureCompiler::default());
+    compiler.set_generic_reporter(ConsoleDiagReporter::default());
+    if compiler
+        .compile(&world, &mut CompileEnv::default())
+        .is_err()
+    {
+        if !args.allow_partial {
+            return Err(
+                "the document failed to compile, some packages may not be discovered, \
+                 pass --allow-partial to vendor the discovered packages"
+                    .into(),
+            );
+        }
+        eprintln!("warning: the document failed to compile, some packages may not be vendored");
+    }
+
+    let ids = world
+        .source_db
+        .slots
+        .lock()
+        .keys()
+        .copied()
+        .collect::<Vec<_>>();
+    let specs = ids
+        .iter()
+        .filter_map(|id| id.package())
+        .map(ToString::to_string)
+        .collect::<BTreeSet<_>>();
+    let specs = specs
+        .iter()
+        .map(|spec| spec.parse::<PackageSpec>().unwrap());
+    let specs = specs.collect::<Vec<_>>();
+
+    let vendor_dir = make_absolute(&args.dir);
+    for spec in &specs {
+        let src = world
+            .registry
+            .resolve(spec)
+            .map_err(|err| eco_format!("failed to resolve package {spec}: {err}"))?;
+        let dst = vendor_dir
+            .join(spec.namespace.as_str())
+            .join(spec.name.as_str())
+            .join(spec.version.to_string());
+        if make_absolute(&src) == dst {
+            continue;
+        }
+
+        eprintln!("vendor package: {spec} -> {}", unix_slash(&dst));
+        let copy = || {
+            if dst.exists() {
+                std::fs::remove_dir_all(&dst)?;
+            }
+            copy_dir_all(&src, &dst)
+        };
+        copy().map_err(|err| eco_format!("failed to vendor package {spec}: {err}"))?;
+    }
+
+    Ok(specs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use clap::Parser;
+
+    use super::*;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn vendor(dir: &Path, main: &str, allow_partial: bool) -> StrResult<Vec<PackageSpec>> {
+        let pkg = dir.join("packages/preview/example/0.1.0");
+        write(
+            &pkg.join("typst.toml"),
+            "[package]\nname = \"example\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+        );
+        write(&pkg.join("lib.typ"), "#let x = 1");
+        write(&dir.join("main.typ"), main);
+
+        let dir = dir.to_str().unwrap();
+        let mut args = vec![
+            "vendor".to_owned(),
+            format!("--dir={dir}/vendor"),
+            format!("--workspace={dir}"),
+            format!("--entry={dir}/main.typ"),
+            format!("--package-path={dir}/packages"),
+            "--offline".to_owned(),
+        ];
+        if allow_partial {
+            args.push("--allow-partial".to_owned());
+        }
+        vendor_packages(VendorPackagesArgs::parse_from(args))
+    }
+
+    #[test]
+    fn test_vendor_packages() {
+        let dir = std::env::temp_dir().join(format!("typst-ts-vendor-{}", std::process::id()));
+        let vendored = "vendor/preview/example/0.1.0/lib.typ";
+
+        let main = "#import \"@preview/example:0.1.0\": x\n#x";
+        let specs = vendor(&dir.join("ok"), main, false).unwrap();
+        assert_eq!(specs.len(), 1);
+        assert_eq!(specs[0].to_string(), "@preview/example:0.1.0");
+        assert!(dir.join("ok").join(vendored).exists());
+
+        // the document fails to compile after importing the package
+        let main = "#import \"@preview/example:0.1.0\": x\n#panic(x)";
+        let err = vendor(&dir.join("partial"), main, false).unwrap_err();
+        assert!(err.contains("--allow-partial"), "{err}");
+        assert!(!dir.join("partial/vendor").exists());
+
+        let specs = vendor(&dir.join("partial"), main, true).unwrap();
+        assert_eq!(specs.len(), 1);
+        assert!(dir.join("partial").join(vendored).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
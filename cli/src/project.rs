@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use reflexo_typst::config::{ProjectConfig, TargetConfig};
+
+use crate::{CompileArgs, CompileOnceArgs};
+
+/// Applies the target selected by `--target` to the compile arguments.
+///
+/// Options given on the command line take precedence over the settings of
+/// the target.
+pub fn apply_target(args: &mut CompileArgs) {
+    let Some(target) = apply_target_once(&mut args.compile) else {
+        return;
+    };
+
+    if args.format.is_empty() {
+        args.format = target.formats;
+    }
+
+    if args.export.creation_timestamp.is_none() {
+        if let Some(timestamp) = target.creation_timestamp {
+            let timestamp = chrono::DateTime::from_timestamp(timestamp, 0);
+            let timestamp = timestamp.unwrap_or_else(|| {
+                exit_with_config_error(format!(
+                    "creation timestamp of target {:?} is out of range",
+                    args.compile.target.as_deref().unwrap_or_default(),
+                ))
+            });
+            args.export.creation_timestamp = Some(timestamp);
+        }
+    }
+}
+
+/// Applies the target selected by `--target` to the arguments shared by
+/// compile commands, and returns the target for applying the rest of
+/// settings.
+pub fn apply_target_once(args: &mut CompileOnceArgs) -> Option<TargetConfig> {
+    let name = args.target.as_deref()?;
+
+    let workspace = Path::new(&args.workspace);
+    let config_path = workspace.join(ProjectConfig::FILE_NAME);
+    let config = std::fs::read_to_string(&config_path).unwrap_or_else(|err| {
+        exit_with_config_error(format!(
+            "failed to read project config {}: {err}",
+            config_path.display()
+        ))
+    });
+    let mut config: ProjectConfig = toml::from_str(&config).unwrap_or_else(|err| {
+        exit_with_config_error(format!(
+            "failed to parse project config {}: {err}",
+            config_path.display()
+        ))
+    });
+
+    let Some(target) = config.targets.remove(name) else {
+        let available = config.targets.keys().cloned().collect::<Vec<_>>();
+        exit_with_config_error(format!(
+            "unknown target {name:?} in {}, available targets: [{}]",
+            config_path.display(),
+            available.join(", ")
+        ))
+    };
+
+    // Paths in the project config are relative to the workspace root.
+    if args.entry.is_empty() {
+        args.entry = workspace.join(&target.entry).to_string_lossy().into_owned();
+    }
+    if args.output.is_empty() {
+        if let Some(output) = &target.output {
+            args.output = workspace.join(output).to_string_lossy().into_owned();
+        }
+    }
+    args.font
+        .paths
+        .extend(target.font_paths.iter().map(|p| workspace.join(p)));
+
+    // Later pairs take precedence, so the inputs given on the command line
+    // override the inputs of the target.
+    let inputs = target.inputs.iter().map(|(k, v)| (k.clone(), v.clone()));
+    args.inputs = inputs.chain(std::mem::take(&mut args.inputs)).collect();
+
+    Some(target)
+}
+
+fn exit_with_config_error(msg: String) -> ! {
+    clap::Error::raw(clap::error::ErrorKind::InvalidValue, format!("{msg}\n")).exit()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+
+    fn workspace(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("typst-ts-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(ProjectConfig::FILE_NAME),
+            r#"
+[targets.book]
+entry = "book/main.typ"
+inputs = { edition = "print", lang = "en" }
+formats = ["pdf"]
+output = "dist/book"
+font-paths = ["assets/fonts"]
+creation-timestamp = 1704067200
+"#,
+        )
+        .unwrap();
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn compile_args(args: &[&str]) -> CompileArgs {
+        let mut args = CompileArgs::parse_from(["compile"].iter().chain(args));
+        apply_target(&mut args);
+        args
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        let pairs = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string()));
+        pairs.collect()
+    }
+
+    #[test]
+    fn test_apply_target() {
+        let ws = workspace("target");
+        let join = |path: &str| Path::new(&ws).join(path);
+
+        let args = compile_args(&["-w", &ws, "--target", "book"]);
+        assert_eq!(Path::new(&args.compile.entry), join("book/main.typ"));
+        assert_eq!(Path::new(&args.compile.output), join("dist/book"));
+        assert_eq!(args.compile.font.paths, [join("assets/fonts")]);
+        assert_eq!(
+            args.compile.inputs,
+            pairs(&[("edition", "print"), ("lang", "en")])
+        );
+        assert_eq!(args.format, ["pdf"]);
+        let timestamp = args.export.creation_timestamp.unwrap();
+        assert_eq!(timestamp.timestamp(), 1704067200);
+
+        // without a target, the project config is not read
+        let args = compile_args(&["-w", &ws, "-e", "main.typ"]);
+        assert_eq!(args.compile.entry, "main.typ");
+        assert!(args.format.is_empty() && args.compile.inputs.is_empty());
+
+        std::fs::remove_dir_all(&ws).unwrap();
+    }
+
+    #[test]
+    fn test_apply_target_overridden() {
+        let ws = workspace("target-overridden");
+
+        let args = compile_args(&[
+            "-w",
+            &ws,
+            "--target",
+            "book",
+            "-e",
+            "other.typ",
+            "-o",
+            "out",
+            "--font-path",
+            "fonts",
+            "--input",
+            "edition=web",
+            "--format",
+            "svg",
+            "--creation-timestamp",
+            "0",
+        ]);
+        assert_eq!(args.compile.entry, "other.typ");
+        assert_eq!(args.compile.output, "out");
+        // font paths are searched in addition to those of the target
+        assert_eq!(
+            args.compile.font.paths,
+            [
+                Path::new("fonts").to_owned(),
+                Path::new(&ws).join("assets/fonts")
+            ]
+        );
+        // later pairs take precedence
+        assert_eq!(
+            args.compile.inputs,
+            pairs(&[("edition", "print"), ("lang", "en"), ("edition", "web")])
+        );
+        assert_eq!(args.format, ["svg"]);
+        let timestamp = args.export.creation_timestamp.unwrap();
+        assert_eq!(timestamp.timestamp(), 0);
+
+        std::fs::remove_dir_all(&ws).unwrap();
+    }
+}
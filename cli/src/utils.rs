@@ -112,6 +112,21 @@ pub fn remove_symlink_dir(path: &Path) -> io::Result<()> {
     }
 }
 
+/// Copies a directory recursively, following symbolic links.
+pub fn copy_dir_all(src: &Path, dst: &Path) -> io::Result<()> {
+    for entry in walkdir::WalkDir::new(src).follow_links(true) {
+        let entry = entry?;
+        let rel = entry.path().strip_prefix(src).unwrap();
+        let target = dst.join(rel);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
 pub fn current_dir() -> PathBuf {
     std::env::current_dir().unwrap_or_exit()
 }
@@ -127,3 +142,184 @@ pub fn make_absolute_from(path: &Path, relative_to: impl FnOnce() -> PathBuf) ->
 pub fn make_absolute(path: &Path) -> PathBuf {
     make_absolute_from(path, current_dir)
 }
+
+/// Expands a glob pattern into the matched files, sorted by path.
+///
+/// Besides plain paths, it supports the wildcards `*` and `?`, which match
+/// within a path component, and `**`, which matches any number of path
+/// components.
+pub fn expand_glob(pattern: &str) -> io::Result<Vec<PathBuf>> {
+    let is_glob = |s: &str| s.contains(['*', '?']);
+    if !is_glob(pattern) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+
+    // Walks from the longest prefix without wildcards.
+    let pattern = Path::new(pattern);
+    let mut base = PathBuf::new();
+    let mut rest = vec![];
+    for component in pattern.components() {
+        let component = component.as_os_str().to_string_lossy();
+        if rest.is_empty() && !is_glob(&component) {
+            base.push(component.as_ref());
+        } else {
+            rest.push(component.into_owned());
+        }
+    }
+    let rest = rest.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let walk_base = if base.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        base.as_path()
+    };
+
+    let mut matched = vec![];
+    if !walk_base.exists() {
+        return Ok(matched);
+    }
+    for entry in walkdir::WalkDir::new(walk_base).follow_links(true) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let Ok(relative) = entry.path().strip_prefix(walk_base) else {
+            continue;
+        };
+        let components = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>();
+        let components = components.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        if match_components(&rest, &components) {
+            matched.push(base.join(relative));
+        }
+    }
+
+    matched.sort();
+    Ok(matched)
+}
+
+fn match_components(pattern: &[&str], path: &[&str]) -> bool {
+    match_sequence(
+        pattern,
+        path,
+        |p| *p == "**",
+        |p, s| {
+            let (p, s) = (p.chars().collect::<Vec<_>>(), s.chars().collect::<Vec<_>>());
+            match_sequence(&p, &s, |c| *c == '*', |p, c| *p == '?' || p == c)
+        },
+    )
+}
+
+/// Matches `items` against `pattern`, in which a star matches any number of
+/// items and any other pattern matches exactly one item.
+///
+/// It only backtracks to the last star, so it takes at most
+/// `O(pattern.len() * items.len())` steps.
+fn match_sequence<P, T>(
+    pattern: &[P],
+    items: &[T],
+    is_star: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // The position of the last star and the position of the items it matches
+    // up to.
+    let mut star = None;
+    while i < items.len() {
+        if p < pattern.len() && is_star(&pattern[p]) {
+            star = Some((p, i));
+            p += 1;
+        } else if p < pattern.len() && matches(&pattern[p], &items[i]) {
+            p += 1;
+            i += 1;
+        } else if let Some((star_p, star_i)) = star {
+            // Lets the last star match one more item.
+            star = Some((star_p, star_i + 1));
+            p = star_p + 1;
+            i = star_i + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(is_star)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        let pattern = pattern.split('/').collect::<Vec<_>>();
+        let path = path.split('/').collect::<Vec<_>>();
+        match_components(&pattern, &path)
+    }
+
+    #[test]
+    fn test_match_star() {
+        assert!(matches("*.typ", "main.typ"));
+        assert!(matches("*.typ", ".typ"));
+        assert!(matches("m*n.typ", "main.typ"));
+        assert!(matches("*", "main.typ"));
+        assert!(!matches("*.typ", "main.pdf"));
+        assert!(!matches("*.typ", "docs/main.typ"));
+        assert!(matches("docs/*.typ", "docs/main.typ"));
+    }
+
+    #[test]
+    fn test_match_question_mark() {
+        assert!(matches("ch?.typ", "ch1.typ"));
+        assert!(!matches("ch?.typ", "ch.typ"));
+        assert!(!matches("ch?.typ", "ch10.typ"));
+        assert!(matches("ch??.typ", "ch10.typ"));
+    }
+
+    #[test]
+    fn test_match_globstar() {
+        assert!(matches("**/*.typ", "main.typ"));
+        assert!(matches("**/*.typ", "docs/a/main.typ"));
+        assert!(matches("docs/**/main.typ", "docs/main.typ"));
+        assert!(matches("docs/**/main.typ", "docs/a/b/main.typ"));
+        assert!(!matches("docs/**/main.typ", "src/a/main.typ"));
+        assert!(!matches("docs/**/main.typ", "docs/a/lib.typ"));
+        assert!(matches("**", "docs/a/main.typ"));
+    }
+
+    #[test]
+    fn test_match_backtracking() {
+        // Exponential with naive backtracking.
+        let name = "a".repeat(64);
+        assert!(!matches(&format!("{}b", "*a".repeat(32)), &name));
+        let path = ["a"; 64].join("/");
+        assert!(!matches(&format!("{}/b", ["**/a"; 32].join("/")), &path));
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = std::env::temp_dir().join(format!("typst-ts-glob-{}", std::process::id()));
+        for file in ["main.typ", "a/x.typ", "a/b/y.typ", "a/b/z.md"] {
+            let path = dir.join(file);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let expand = |pattern: &str| {
+            let matched = expand_glob(&dir.join(pattern).to_string_lossy()).unwrap();
+            let matched = matched.iter().map(|p| p.strip_prefix(&dir).unwrap());
+            matched
+                .map(|p| p.to_string_lossy().replace('\\', "/"))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(expand("*.typ"), ["main.typ"]);
+        assert_eq!(expand("a/?.typ"), ["a/x.typ"]);
+        assert_eq!(expand("**/*.typ"), ["a/b/y.typ", "a/x.typ", "main.typ"]);
+        assert_eq!(expand("a/**/*.md"), ["a/b/z.md"]);
+        assert!(expand("**/*.pdf").is_empty());
+        assert!(expand("missing/*.typ").is_empty());
+        // Plain paths are returned as is.
+        assert_eq!(expand("missing.typ"), ["missing.typ"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 //! Lowering Typst Document into SvgItem.
 
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::ops::DerefMut;
 use std::sync::Arc;
 
@@ -96,6 +96,22 @@ impl<const ENABLE_REF_CNT: bool> TGlyph2VecPass<ENABLE_REF_CNT> {
         (fonts, glyphs)
     }
 
+    /// Gets the ids of the glyphs lowered so far, grouped by their fonts in
+    /// the order the fonts are built.
+    pub fn used_glyphs(&self) -> Vec<(Font, BTreeSet<u16>)> {
+        let mut fonts = BTreeMap::new();
+        for (glyph, (glyph_ref, font_ref)) in self.glyph_defs.clone() {
+            let GlyphItem::Raw(font, _) = glyph else {
+                continue;
+            };
+            let (_, glyphs) = fonts
+                .entry(font_ref.idx)
+                .or_insert_with(|| (font, BTreeSet::new()));
+            glyphs.insert(glyph_ref.glyph_idx as u16);
+        }
+        fonts.into_values().collect()
+    }
+
     pub fn build_font(&self, font: &Font) -> FontRef {
         if let Some(id) = self.font_mapping.get(font) {
             return *id;
@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use tiny_skia as sk;
 
-use reflexo::{hash::Fingerprint, vector::ir::*};
+use reflexo::{error::prelude::*, hash::Fingerprint, vector::ir::*};
 
 #[derive(Default)]
 pub struct Vec2BBoxPass {
@@ -13,54 +13,116 @@ impl Vec2BBoxPass {
     /// Calculate the bounding box of a vector item with a given transform.
     /// The transform is required to calculate the accurate bounding box for
     /// irregular shapes.
-    pub fn bbox_of(&mut self, module: &Module, v: Fingerprint, ts: Transform) -> Option<Rect> {
+    ///
+    /// Returns `Ok(None)` if the item paints nothing, e.g. a link, and an
+    /// error if the item or a font it uses is missing in the module.
+    pub fn bbox_of(
+        &mut self,
+        module: &Module,
+        v: Fingerprint,
+        ts: Transform,
+    ) -> ZResult<Option<Rect>> {
         if let Some(bbox) = self.bbox_caches.get(&(v, ts)) {
-            return *bbox;
+            return Ok(*bbox);
         }
 
-        let bbox = self.bbox_of_(module, v, ts);
-        println!("bbox_of({v:?}, {ts:?}) = {bbox:?}");
+        let bbox = self.bbox_of_(module, v, ts)?;
         self.bbox_caches.insert((v, ts), bbox);
-        bbox
+        Ok(bbox)
     }
 
-    fn bbox_of_(&mut self, module: &Module, v: Fingerprint, ts: Transform) -> Option<Rect> {
-        let item = module.get_item(&v).unwrap();
-        match item {
-            VecItem::Item(item) => self.bbox_of(module, item.1, item.0.clone().into()),
+    /// Calculate the tight bounding box of the content of a page, cropped by
+    /// the page. The areas of links are not content, so they are excluded.
+    pub fn page_bbox(&mut self, module: &Module, page: &Page) -> ZResult<Option<Rect>> {
+        let bbox = self.bbox_of(module, page.content, Transform::identity())?;
+        let page = Rect {
+            lo: Point::default(),
+            hi: page.size,
+        };
+        Ok(bbox
+            .map(|bbox| bbox.intersect(&page))
+            .filter(|e| !e.is_empty()))
+    }
+
+    fn bbox_of_(
+        &mut self,
+        module: &Module,
+        v: Fingerprint,
+        ts: Transform,
+    ) -> ZResult<Option<Rect>> {
+        let item = module
+            .get_item(&v)
+            .ok_or_else(|| error_once!("Vec2BBoxPass.ItemNotFound", item: format!("{v:?}")))?;
+        Ok(match item {
+            VecItem::Item(TransformedRef(TransformItem::Clip(clip), item)) => {
+                let Some(bbox) = self.bbox_of(module, *item, ts)? else {
+                    return Ok(None);
+                };
+                // keeps the item unclipped if the clip path cannot be parsed.
+                match Self::simple_path_bbox(&clip.d, ts.into()) {
+                    Some(clip) => Some(bbox.intersect(&clip)).filter(|e| !e.is_empty()),
+                    None => Some(bbox),
+                }
+            }
+            VecItem::Item(TransformedRef(transform, item)) => {
+                self.bbox_of(module, *item, ts.pre_concat(transform.clone().into()))?
+            }
             VecItem::Group(g) => {
-                let mut r = Rect::default();
+                let mut r: Option<Rect> = None;
                 for (p, f) in g.0.iter() {
-                    let sub_bbox = self.bbox_of(module, *f, ts);
+                    let sub_bbox = self.bbox_of(module, *f, ts.pre_translate(p.x.0, p.y.0))?;
                     if let Some(sub_bbox) = sub_bbox {
-                        union(&mut r, *p, sub_bbox);
+                        r = Some(r.map_or(sub_bbox, |r| r.union(&sub_bbox)));
                     }
                 }
-                Some(r)
+                r
+            }
+            VecItem::Image(ImageItem { size, .. }) | VecItem::Html(HtmlItem { size, .. }) => {
+                self.rect(*size, ts)
             }
-            VecItem::Image(ImageItem { size, .. })
-            | VecItem::Link(LinkItem { size, .. })
-            | VecItem::Html(HtmlItem { size, .. }) => self.rect(*size, ts),
-            // todo: I'm writing this in my leg
             VecItem::Text(t) => {
-                let width = t.width();
-                let height = t.shape.size.0;
-                tiny_skia_path::Rect::from_xywh(0.0, 0.0, width.0, height).map(|e| e.into())
+                let font = module.get_font(&t.shape.font).ok_or_else(
+                    || error_once!("Vec2BBoxPass.FontNotFound", font: t.shape.font.idx),
+                )?;
+                self.text(t, font, ts)
             }
             VecItem::Path(p) => self.path(p, ts),
-            VecItem::ContentHint(..)
-            | VecItem::ColorTransform(..)
-            | VecItem::Pattern(..)
-            | VecItem::Gradient(..)
-            | VecItem::Color32(..)
-            | VecItem::None => None,
-        }
+            VecItem::ColorTransform(c) => {
+                self.bbox_of(module, c.item, ts.pre_concat(c.transform))?
+            }
+            // a pattern paints its tiles, and a gradient paints the unit box of
+            // the bounding box of the filled item.
+            VecItem::Pattern(p) => self.rect(p.size, ts),
+            VecItem::Gradient(..) => self.rect(Size::new(Scalar(1.), Scalar(1.)), ts),
+            VecItem::Link(..) | VecItem::ContentHint(..) | VecItem::Color32(..) | VecItem::None => {
+                None
+            }
+        })
     }
 
     pub fn path(&mut self, p: &PathItem, ts: Transform) -> Option<Rect> {
         Self::path_bbox(p, ts.into())
     }
 
+    /// Calculate the bounding box of a text, which spans from the ascender to
+    /// the descender of its font vertically.
+    pub fn text(&self, t: &TextItem, font: &FontItem, ts: Transform) -> Option<Rect> {
+        let size = t.shape.size.0;
+        let stroke = t.shape.styles.iter().find_map(|s| match s {
+            PathStyle::StrokeWidth(w) => Some(w.0 / 2.),
+            _ => None,
+        });
+        let stroke = stroke.unwrap_or_default();
+
+        let r = tiny_skia_path::Rect::from_ltrb(
+            -stroke,
+            -font.ascender.0 * size - stroke,
+            t.width().0 + stroke,
+            -font.descender.0 * size + stroke,
+        );
+        r.and_then(|e| e.transform(ts.into())).map(|e| e.into())
+    }
+
     fn rect(&self, size: Axes<Scalar>, ts: Transform) -> Option<Rect> {
         let r = tiny_skia_path::Rect::from_xywh(0.0, 0.0, size.x.0, size.y.0);
         r.and_then(|e| e.transform(ts.into())).map(|e| e.into())
@@ -79,15 +141,17 @@ impl Vec2BBoxPass {
             .and_then(|e| e.compute_tight_bounds())
             .and_then(|e| {
                 let Some(stroke) = p.styles.iter().find_map(|s| match s {
-                    PathStyle::StrokeWidth(w) => Some(w.0),
+                    PathStyle::StrokeWidth(w) => Some(w.0 / 2.),
                     _ => None,
                 }) else {
                     return Some(e);
                 };
+                // A stroke of radius `stroke` is transformed into an ellipse
+                // whose extents are scaled by the rows of the transform.
                 let sk::Transform { sx, sy, kx, ky, .. } = ts;
-                let stroke_x = (stroke * (sx + ky)).abs();
-                let stroke_y = (stroke * (sy + kx)).abs();
-                // extend the bounding box by the stroke width
+                let stroke_x = stroke * sx.hypot(kx);
+                let stroke_y = stroke * ky.hypot(sy);
+                // extend the bounding box by half the stroke width
                 let x = e.x() - stroke_x;
                 let y = e.y() - stroke_y;
                 let w = e.width() + stroke_x * 2.0;
@@ -98,10 +162,6 @@ impl Vec2BBoxPass {
     }
 }
 
-fn union(r: &mut Rect, p: Axes<Scalar>, sub_bbox: Rect) {
-    *r = r.union(&sub_bbox.translate(p))
-}
-
 fn convert_path(path_data: &str) -> Option<tiny_skia_path::Path> {
     let mut builder = tiny_skia_path::PathBuilder::new();
     for segment in svgtypes::SimplifyingPathParser::from(path_data) {
@@ -159,4 +219,218 @@ mod tests {
 
         assert!(Vec2BBoxPass::path_bbox(&p, ts).is_some());
     }
+
+    fn fingerprint(v: u64) -> Fingerprint {
+        Fingerprint::from_pair(v, 0)
+    }
+
+    fn rect_path(w: f32, h: f32) -> VecItem {
+        VecItem::Path(PathItem {
+            d: format!("M 0 0 L {w} 0 L {w} {h} L 0 {h} Z").into(),
+            size: None,
+            styles: vec![],
+        })
+    }
+
+    #[test]
+    fn test_stroked_path_bbox() {
+        let VecItem::Path(mut p) = rect_path(10., 10.) else {
+            unreachable!()
+        };
+        p.styles.push(PathStyle::StrokeWidth(Scalar(2.)));
+
+        let bbox = Vec2BBoxPass::path_bbox(&p, sk::Transform::identity());
+        assert_rect(bbox, (-1., -1.), (11., 11.));
+
+        let bbox = Vec2BBoxPass::path_bbox(&p, sk::Transform::from_scale(2., 3.));
+        assert_rect(bbox, (-2., -3.), (22., 33.));
+
+        // The stroke is not lost when the terms of the transform cancel out.
+        let d = 50f32.sqrt();
+        let bbox = Vec2BBoxPass::path_bbox(&p, sk::Transform::from_rotate(135.));
+        assert_rect(bbox, (-2. * d - 1., -d - 1.), (1., d + 1.));
+    }
+
+    fn assert_rect(r: Option<Rect>, lo: (f32, f32), hi: (f32, f32)) {
+        let r = r.expect("bbox");
+        let close = |a: Scalar, b: f32| (a.0 - b).abs() < 1e-3;
+        assert!(
+            close(r.lo.x, lo.0)
+                && close(r.lo.y, lo.1)
+                && close(r.hi.x, hi.0)
+                && close(r.hi.y, hi.1),
+            "{r:?} != {lo:?}..{hi:?}"
+        );
+    }
+
+    #[test]
+    fn test_group_bbox() {
+        let mut module = Module::default();
+        module.items.insert(fingerprint(1), rect_path(10., 10.));
+        module.items.insert(
+            fingerprint(2),
+            VecItem::Group(GroupRef(
+                vec![(Point::new(Scalar(5.), Scalar(0.)), fingerprint(1))].into(),
+            )),
+        );
+
+        let ts = Transform::from_scale(Scalar(2.), Scalar(2.));
+        let bbox = Vec2BBoxPass::default().bbox_of(&module, fingerprint(2), ts);
+        assert_rect(bbox.unwrap(), (10., 0.), (30., 20.));
+    }
+
+    #[test]
+    fn test_text_bbox() {
+        let mut module = Module::default();
+        module.fonts.push(FontItem {
+            fingerprint: fingerprint(0),
+            family: "Test".into(),
+            hash: 0,
+            cap_height: Scalar(0.7),
+            ascender: Scalar(0.8),
+            descender: Scalar(-0.2),
+            units_per_em: Scalar(1000.),
+            vertical: false,
+            glyphs: vec![],
+            glyph_cov: Default::default(),
+        });
+        module.items.insert(
+            fingerprint(1),
+            VecItem::Text(TextItem {
+                shape: TextShape {
+                    font: FontRef { hash: 0, idx: 0 },
+                    dir: "ltr".into(),
+                    size: Scalar(10.),
+                    styles: vec![],
+                }
+                .into(),
+                content: TextItemContent {
+                    content: "ab".into(),
+                    glyphs: vec![(Scalar(0.), Scalar(6.), 1), (Scalar(0.), Scalar(4.), 2)].into(),
+                }
+                .into(),
+            }),
+        );
+        module.items.insert(
+            fingerprint(2),
+            VecItem::Item(TransformedRef(
+                TransformItem::Translate(Axes::new(Scalar(5.), Scalar(20.)).into()),
+                fingerprint(1),
+            )),
+        );
+
+        let bbox = Vec2BBoxPass::default().bbox_of(&module, fingerprint(2), Transform::identity());
+        assert_rect(bbox.unwrap(), (5., 12.), (15., 22.));
+    }
+
+    #[test]
+    fn test_clip_bbox() {
+        let mut module = Module::default();
+        module.items.insert(fingerprint(1), rect_path(100., 100.));
+        let clip = PathItem {
+            d: "M 50 50 L 150 50 L 150 150 L 50 150 Z".into(),
+            size: None,
+            styles: vec![],
+        };
+        module.items.insert(
+            fingerprint(2),
+            VecItem::Item(TransformedRef(
+                TransformItem::Clip(clip.into()),
+                fingerprint(1),
+            )),
+        );
+
+        let mut pass = Vec2BBoxPass::default();
+        let bbox = pass.bbox_of(&module, fingerprint(2), Transform::identity());
+        assert_rect(bbox.unwrap(), (50., 50.), (100., 100.));
+
+        let page = Page {
+            content: fingerprint(2),
+            size: Size::new(Scalar(80.), Scalar(200.)),
+        };
+        assert_rect(
+            pass.page_bbox(&module, &page).unwrap(),
+            (50., 50.),
+            (80., 100.),
+        );
+    }
+
+    #[test]
+    fn test_unparsed_clip_bbox() {
+        let mut module = Module::default();
+        module.items.insert(fingerprint(1), rect_path(100., 100.));
+        let clip = PathItem {
+            d: "not a path".into(),
+            size: None,
+            styles: vec![],
+        };
+        module.items.insert(
+            fingerprint(2),
+            VecItem::Item(TransformedRef(
+                TransformItem::Clip(clip.into()),
+                fingerprint(1),
+            )),
+        );
+
+        let bbox = Vec2BBoxPass::default().bbox_of(&module, fingerprint(2), Transform::identity());
+        assert_rect(bbox.unwrap(), (0., 0.), (100., 100.));
+    }
+
+    #[test]
+    fn test_link_bbox() {
+        let mut module = Module::default();
+        module.items.insert(fingerprint(1), rect_path(10., 10.));
+        module.items.insert(
+            fingerprint(2),
+            VecItem::Link(LinkItem {
+                href: "https://example.com".into(),
+                size: Size::new(Scalar(50.), Scalar(50.)),
+            }),
+        );
+        module.items.insert(
+            fingerprint(3),
+            VecItem::Group(GroupRef(
+                vec![
+                    (Point::new(Scalar(5.), Scalar(5.)), fingerprint(1)),
+                    (Point::new(Scalar(20.), Scalar(20.)), fingerprint(2)),
+                ]
+                .into(),
+            )),
+        );
+        module.items.insert(
+            fingerprint(4),
+            VecItem::Group(GroupRef(vec![(Point::default(), fingerprint(2))].into())),
+        );
+
+        let size = Size::new(Scalar(100.), Scalar(100.));
+        let mut pass = Vec2BBoxPass::default();
+        let page = Page {
+            content: fingerprint(3),
+            size,
+        };
+        assert_rect(
+            pass.page_bbox(&module, &page).unwrap(),
+            (5., 5.),
+            (15., 15.),
+        );
+
+        // a page of only links is blank.
+        let page = Page {
+            content: fingerprint(4),
+            size,
+        };
+        assert_eq!(pass.page_bbox(&module, &page).unwrap(), None);
+    }
+
+    #[test]
+    fn test_missing_item() {
+        let mut module = Module::default();
+        module.items.insert(
+            fingerprint(1),
+            VecItem::Group(GroupRef(vec![(Point::default(), fingerprint(2))].into())),
+        );
+
+        let bbox = Vec2BBoxPass::default().bbox_of(&module, fingerprint(1), Transform::identity());
+        assert!(bbox.is_err());
+    }
 }
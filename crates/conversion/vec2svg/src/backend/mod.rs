@@ -4,6 +4,7 @@ mod text;
 
 pub use glyph::SvgGlyphBuilder;
 
+use std::fmt::Write;
 use std::sync::Arc;
 
 use base64::Engine;
@@ -193,6 +194,45 @@ impl SvgTextBuilder {
         )))
     }
 
+    /// Render a text as a `<text>` element in a web font, whose characters
+    /// are placed at the offsets in font units. The invisible characters are
+    /// only for selection.
+    pub fn render_web_text(&mut self, family: &str, chars: &[(char, f32, bool)], upem: Scalar) {
+        let mut x = String::new();
+        let mut content = String::new();
+        let mut visible = true;
+        for (c, offset, is_visible) in chars {
+            if !x.is_empty() {
+                x.push(' ');
+            }
+            let _ = write!(x, "{:.2}", offset / 16.);
+
+            if *is_visible != visible {
+                content.push_str(match is_visible {
+                    false => r#"<tspan fill-opacity="0" stroke-opacity="0">"#,
+                    true => "</tspan>",
+                });
+                visible = *is_visible;
+            }
+            content.push_str(&escape::escape_str::<PcDataEscapes>(
+                c.encode_utf8(&mut [0; 4]),
+            ));
+        }
+        if !visible {
+            content.push_str("</tspan>");
+        }
+
+        // same as the text semantics, the text is scaled up to avoid the limit
+        // of font size in browsers.
+        self.content.push(SvgText::Plain(format!(
+            r#"<text transform="scale(16,-16)" x="{}" font-family="{}" font-size="{}" xml:space="preserve">{}</text>"#,
+            x,
+            family,
+            upem.0 / 16.,
+            content
+        )))
+    }
+
     fn render_paint<C: NotifyPaint>(
         ctx: &mut C,
         color: ImmutStr,
@@ -8,13 +8,14 @@ use reflexo::{
     hash::{Fingerprint, FingerprintBuilder},
     vector::{
         ir::{
-            self, FlatGlyphItem, FontIndice, FontRef, GroupRef, ImmutStr, Module, PathItem, Scalar,
-            TextItem, Transform, VecItem,
+            self, FlatGlyphItem, FontIndice, FontItem, FontRef, GlyphRef, GroupRef, ImmutStr,
+            Module, PathItem, Scalar, TextItem, Transform, VecItem,
         },
         vm::{GroupContext, IncrRenderVm, RenderVm},
     },
 };
 
+use super::webfont::{WebFontUsage, WebFonts, WebGlyph, WebText};
 use crate::{
     backend::{BuildClipPath, DynExportFeature, NotifyPaint, SvgText, SvgTextBuilder, SvgTextNode},
     ExportFeature,
@@ -53,18 +54,27 @@ pub struct RenderContext<'m, 't, Feat: ExportFeature> {
     /// Stores the patterns used in the document.
     pub(crate) patterns: &'t mut PaintFillMap,
 
-    /// See [`ExportFeature`].
+    /// See [`crate::SvgExportOptions`].
     pub should_render_text_element: bool,
-    /// See [`ExportFeature`].
+    /// See [`crate::SvgExportOptions`].
     pub should_attach_debug_info: bool,
-    /// See [`ExportFeature`].
+    /// See [`crate::SvgExportOptions`].
     pub use_stable_glyph_id: bool,
-    /// See [`ExportFeature`].
+    /// See [`crate::SvgExportOptions`].
     pub should_rasterize_text: bool,
+    /// See [`crate::SvgExportOptions`].
+    pub should_aware_html_entity: bool,
+
+    /// The fonts to render texts in, if web fonts are enabled.
+    pub(crate) web_fonts: Option<&'t WebFonts>,
+    /// Stores the glyphs rendered in web fonts or as outlines.
+    pub(crate) web_font_usage: &'t mut WebFontUsage,
 
     pub _feat_phantom: std::marker::PhantomData<Feat>,
 }
 
+/// The options take effect only if the features are enabled by `Feat`, so that
+/// the features disabled at compile time cost nothing.
 impl<'m, 't, Feat: ExportFeature> DynExportFeature for RenderContext<'m, 't, Feat> {
     #[inline]
     fn should_render_text_element(&self) -> bool {
@@ -88,7 +98,7 @@ impl<'m, 't, Feat: ExportFeature> DynExportFeature for RenderContext<'m, 't, Fea
 
     #[inline]
     fn should_aware_html_entity(&self) -> bool {
-        Feat::AWARE_HTML_ENTITY
+        Feat::AWARE_HTML_ENTITY && self.should_aware_html_entity
     }
 }
 
@@ -305,6 +315,26 @@ impl<'m, 't, Feat: ExportFeature> RenderContext<'m, 't, Feat> {
 
         group_ctx = text.shape.add_transform(self, group_ctx, upem);
 
+        if let Some(web_fonts) = self.web_fonts {
+            // a text in web font is selectable by itself
+            let web_text = match (&group_ctx.text_fill, &group_ctx.text_stroke) {
+                (None, None) => web_fonts.layout_text(text, font),
+                _ => None,
+            };
+            if let Some(web_text) = web_text {
+                self.render_web_text(&mut group_ctx, text, font, upem, web_text);
+                return group_ctx;
+            }
+
+            let glyphs = text.content.glyphs.iter();
+            self.web_font_usage
+                .outlines
+                .extend(glyphs.map(|(_, _, glyph_idx)| GlyphRef {
+                    font_hash: font.hash,
+                    glyph_idx: *glyph_idx,
+                }));
+        }
+
         let width = match (&group_ctx.text_fill, &group_ctx.text_stroke) {
             (fill, Some(stroke)) => {
                 let mut width = 0f32;
@@ -385,4 +415,50 @@ impl<'m, 't, Feat: ExportFeature> RenderContext<'m, 't, Feat> {
 
         group_ctx
     }
+
+    /// Render a text as a `<text>` element in its web font, along with the
+    /// glyphs rendered as outlines.
+    fn render_web_text(
+        &mut self,
+        group_ctx: &mut SvgTextBuilder,
+        text: &TextItem,
+        font: &FontItem,
+        upem: Scalar,
+        web_text: WebText,
+    ) {
+        let mut width = 0f32;
+        let positions = text.render_glyphs(upem, &mut width).collect::<Vec<_>>();
+        let end = width * upem.0 / text.shape.size.0;
+
+        // (character, offset, whether it is visible)
+        let mut chars = vec![];
+        for (i, glyph) in web_text.glyphs.into_iter().enumerate() {
+            let (x, glyph_idx) = positions[i];
+            match glyph {
+                WebGlyph::Char(c) => chars.push((c, x.0, true)),
+                WebGlyph::Outline(content) => {
+                    group_ctx.render_glyph(self, x, font, glyph_idx);
+                    self.web_font_usage.outlines.insert(GlyphRef {
+                        font_hash: font.hash,
+                        glyph_idx,
+                    });
+
+                    // spread the characters over the advance of the glyph
+                    let next = positions.get(i + 1).map_or(end, |(x, _)| x.0);
+                    let n = content.chars().count() as f32;
+                    chars.extend(
+                        content
+                            .chars()
+                            .enumerate()
+                            .map(|(j, c)| (c, x.0 + (next - x.0) * j as f32 / n, false)),
+                    );
+                }
+            }
+        }
+
+        let used = self.web_font_usage.text.entry(font.hash).or_default();
+        used.extend(web_text.used);
+
+        group_ctx.render_web_text(&self.web_font_usage.family(font.hash), &chars, upem);
+    }
 }
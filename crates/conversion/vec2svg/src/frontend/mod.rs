@@ -2,9 +2,11 @@ pub(crate) mod context;
 pub(crate) mod dynamic_layout;
 pub(crate) mod flat;
 pub(crate) mod incremental;
+pub(crate) mod webfont;
 
 pub use dynamic_layout::DynamicLayoutSvgExporter;
 pub use incremental::{IncrSvgDocClient, IncrSvgDocServer, IncrementalRenderContext};
+pub use webfont::WebFonts;
 
 use std::{collections::HashSet, f32::consts::TAU, fmt::Write, sync::Arc};
 
@@ -24,9 +26,10 @@ use typst::{
 
 use crate::{
     backend::{SvgGlyphBuilder, SvgText, SvgTextNode},
-    ExportFeature, SvgDataSelection,
+    ExportFeature, SvgDataSelection, SvgExportOptions,
 };
 use context::{PaintFillMap, RenderContext, StyleDefMap};
+use webfont::WebFontUsage;
 
 pub struct SvgExporter<Feat: ExportFeature> {
     pub _feat_phantom: std::marker::PhantomData<Feat>,
@@ -312,18 +315,95 @@ impl<Feat: ExportFeature> SvgExporter<Feat> {
         module: &Module,
         pages: &[Page],
         parts: Option<SvgDataSelection>,
+    ) -> Vec<SvgText> {
+        Self::render_with_options(
+            module,
+            pages,
+            parts,
+            SvgExportOptions::from_feature::<Feat>(),
+        )
+    }
+
+    /// Render pages into the entire SVG with the given options, which take
+    /// effect only where the features of the exporter are enabled.
+    pub fn render_with_options(
+        module: &Module,
+        pages: &[Page],
+        parts: Option<SvgDataSelection>,
+        options: SvgExportOptions,
+    ) -> Vec<SvgText> {
+        Self::render_impl(module, pages, parts, options, false, None)
+    }
+
+    /// Render each page into a standalone SVG with the given options.
+    ///
+    /// Unlike [`Self::render_with_options`], each SVG only carries the glyph
+    /// definitions, gradients and patterns used by its page.
+    pub fn render_pages_with_options(
+        module: &Module,
+        pages: &[Page],
+        options: SvgExportOptions,
+    ) -> Vec<Vec<SvgText>> {
+        Self::render_pages_impl(module, pages, options, None)
+    }
+
+    pub(crate) fn render_pages_impl(
+        module: &Module,
+        pages: &[Page],
+        options: SvgExportOptions,
+        web_fonts: Option<&WebFonts>,
+    ) -> Vec<Vec<SvgText>> {
+        pages
+            .iter()
+            .map(|page| {
+                let page = std::slice::from_ref(page);
+                Self::render_impl(module, page, None, options, true, web_fonts)
+            })
+            .collect()
+    }
+
+    /// Render pages, in which the texts are rendered in the web fonts if
+    /// given and enabled by [`SvgExportOptions::embed_web_fonts`].
+    pub(crate) fn render_impl(
+        module: &Module,
+        pages: &[Page],
+        parts: Option<SvgDataSelection>,
+        options: SvgExportOptions,
+        used_glyphs_only: bool,
+        web_fonts: Option<&WebFonts>,
     ) -> Vec<SvgText> {
         if !module.glyphs.is_empty() {
             panic!("Glyphs should be loaded before rendering.");
         }
 
-        let mut t = SvgTask::<Feat>::default();
+        let mut t = SvgTask::<Feat>::new(options);
+        t.web_fonts = web_fonts;
+        t.web_font_usage.scope =
+            reflexo::hash::hash32(&pages.iter().map(|p| p.content).collect::<Vec<_>>());
+        let web_fonts = t.web_fonts.filter(|_| options.embed_web_fonts);
+
         let mut svg_body = vec![];
         t.render(module, pages, &mut svg_body);
         let patterns = t.render_patterns(module);
 
         // note in order!: pattern may use glyphs
-        let glyphs = t.render_glyphs(module.glyphs_all());
+        let glyphs = if web_fonts.is_some() {
+            // the glyphs of texts in web fonts are not referenced
+            let outlines = std::mem::take(&mut t.web_font_usage.outlines);
+            t.render_glyphs(module.glyphs_all().filter(|(id, _)| outlines.contains(id)))
+        } else if used_glyphs_only {
+            let mut used = UsedGlyphs::new(module);
+            for page in pages {
+                used.visit(&page.content);
+            }
+            for (id, ..) in &patterns {
+                used.visit(id);
+            }
+            let used = used.glyphs;
+            t.render_glyphs(module.glyphs_all().filter(|(id, _)| used.contains(id)))
+        } else {
+            t.render_glyphs(module.glyphs_all())
+        };
 
         let gradients = t
             .gradients
@@ -349,7 +429,7 @@ impl<Feat: ExportFeature> SvgExporter<Feat> {
             // base style
         ];
 
-        if Feat::WITH_BUILTIN_CSS && with_css {
+        if Feat::WITH_BUILTIN_CSS && options.with_builtin_css && with_css {
             svg.push(r#"<style type="text/css">"#.into());
             svg.push(include_str!("./typst.svg.css").into());
             svg.push("</style>".into());
@@ -364,6 +444,9 @@ impl<Feat: ExportFeature> SvgExporter<Feat> {
             Self::gradients(gradients, &mut svg);
             Self::patterns(patterns.into_iter(), &mut svg);
             svg.push("</defs>".into());
+            if let Some(web_fonts) = web_fonts {
+                web_fonts.render_font_faces(&t.web_font_usage, &mut svg);
+            }
             Self::style_defs(t.style_defs, &mut svg);
         }
 
@@ -372,7 +455,7 @@ impl<Feat: ExportFeature> SvgExporter<Feat> {
             svg.append(&mut svg_body);
         }
 
-        if Feat::WITH_RESPONSIVE_JS && with_js {
+        if Feat::WITH_RESPONSIVE_JS && options.with_responsive_js && with_js {
             // attach the javascript for animations
             svg.push(r#"<script type="text/javascript">"#.into());
             svg.push(include_str!("./typst.svg.js").into());
@@ -386,6 +469,53 @@ impl<Feat: ExportFeature> SvgExporter<Feat> {
     }
 }
 
+/// Collects the glyphs used by items, following groups, transforms and the
+/// frames of patterns.
+struct UsedGlyphs<'m> {
+    module: &'m Module,
+    visited: HashSet<Fingerprint>,
+    glyphs: HashSet<GlyphRef>,
+}
+
+impl<'m> UsedGlyphs<'m> {
+    fn new(module: &'m Module) -> Self {
+        Self {
+            module,
+            visited: HashSet::new(),
+            glyphs: HashSet::new(),
+        }
+    }
+
+    fn visit(&mut self, id: &Fingerprint) {
+        if !self.visited.insert(*id) {
+            return;
+        }
+
+        match self.module.get_item(id) {
+            Some(VecItem::Group(group)) => {
+                for (_, item) in group.0.iter() {
+                    self.visit(item);
+                }
+            }
+            Some(VecItem::Item(transformed)) => self.visit(&transformed.1),
+            Some(VecItem::Pattern(pattern)) => self.visit(&pattern.frame),
+            Some(VecItem::Text(text)) => {
+                let font_hash = text.shape.font.hash;
+                self.glyphs.extend(
+                    text.content
+                        .glyphs
+                        .iter()
+                        .map(|(_, _, glyph_idx)| GlyphRef {
+                            font_hash,
+                            glyph_idx: *glyph_idx,
+                        }),
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
 /// The task context for exporting svg.
 /// It is also as a namespace for all the functions used in the task.
 pub struct SvgTask<'a, Feat: ExportFeature> {
@@ -399,12 +529,28 @@ pub struct SvgTask<'a, Feat: ExportFeature> {
     /// Stores the patterns used in the document.
     pub patterns: PaintFillMap,
 
+    /// The options of the task, see [`SvgExportOptions`].
+    pub options: SvgExportOptions,
+    /// The fonts to embed, which take effect only if
+    /// [`SvgExportOptions::embed_web_fonts`] is enabled.
+    pub web_fonts: Option<&'a WebFonts>,
+    /// Stores the glyphs rendered in web fonts or as outlines.
+    pub(crate) web_font_usage: WebFontUsage,
+
     _feat_phantom: std::marker::PhantomData<&'a Feat>,
 }
 
 /// Unfortunately, `Default` derive does not work for generic structs.
 impl<Feat: ExportFeature> Default for SvgTask<'_, Feat> {
     fn default() -> Self {
+        Self::new(SvgExportOptions::from_feature::<Feat>())
+    }
+}
+
+impl<Feat: ExportFeature> SvgTask<'_, Feat> {
+    /// Create a task with the given options, which take effect only where the
+    /// features of the task are enabled.
+    pub fn new(options: SvgExportOptions) -> Self {
         Self {
             fingerprint_builder: FingerprintBuilder::default(),
 
@@ -412,12 +558,14 @@ impl<Feat: ExportFeature> Default for SvgTask<'_, Feat> {
             gradients: PaintFillMap::default(),
             patterns: PaintFillMap::default(),
 
+            options,
+            web_fonts: None,
+            web_font_usage: WebFontUsage::default(),
+
             _feat_phantom: std::marker::PhantomData,
         }
     }
-}
 
-impl<Feat: ExportFeature> SvgTask<'_, Feat> {
     /// Return integral page size for showing document.
     pub(crate) fn page_size(sz: Size) -> Axes<u32> {
         let (width_px, height_px) = {
@@ -444,10 +592,14 @@ impl<Feat: ExportFeature> SvgTask<'_, Feat> {
             gradients: &mut self.gradients,
             patterns: &mut self.patterns,
 
-            should_attach_debug_info: Feat::SHOULD_ATTACH_DEBUG_INFO,
-            should_render_text_element: true,
-            use_stable_glyph_id: true,
-            should_rasterize_text: true,
+            should_attach_debug_info: self.options.attach_debug_info,
+            should_render_text_element: self.options.render_text_element,
+            use_stable_glyph_id: self.options.use_stable_glyph_id,
+            should_rasterize_text: self.options.rasterize_text,
+            should_aware_html_entity: self.options.aware_html_entity,
+
+            web_fonts: self.web_fonts.filter(|_| self.options.embed_web_fonts),
+            web_font_usage: &mut self.web_font_usage,
 
             _feat_phantom: Default::default(),
         }
@@ -653,3 +805,214 @@ impl std::fmt::Display for RatioRepr {
         write!(f, "{:.3}%", self.0 * 100.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use ir::{
+        FontItem, FontRef, GroupRef, OutlineGlyphItem, PathItem, PathStyle, PatternItem, TextItem,
+        TextItemContent, TextShape, TransformItem, TransformedRef,
+    };
+
+    use super::*;
+    use crate::{backend::generate_text, DynamicExportFeature, SvgExportFeature};
+
+    const FONT_HASH: u32 = 7;
+
+    fn glyph(glyph_idx: u32) -> GlyphRef {
+        GlyphRef {
+            font_hash: FONT_HASH,
+            glyph_idx,
+        }
+    }
+
+    fn text(glyph_idx: u32) -> VecItem {
+        VecItem::Text(TextItem {
+            shape: Arc::new(TextShape {
+                font: FontRef {
+                    hash: FONT_HASH,
+                    idx: 0,
+                },
+                dir: "ltr".into(),
+                size: Scalar(10.),
+                styles: vec![],
+            }),
+            content: Arc::new(TextItemContent {
+                content: "A".into(),
+                glyphs: Arc::from([(Scalar(0.), Scalar(5.), glyph_idx)]),
+            }),
+        })
+    }
+
+    fn group(items: &[Fingerprint]) -> VecItem {
+        let items = items
+            .iter()
+            .map(|id| (Axes::new(Scalar(0.), Scalar(0.)), *id));
+        VecItem::Group(GroupRef(items.collect()))
+    }
+
+    /// Builds a module of two pages. The first page uses glyph 1, directly
+    /// and by a transform. The second page uses glyph 2, and glyph 3 by a
+    /// pattern filling a path.
+    fn two_pages() -> (Module, Vec<Page>, Fingerprint) {
+        let id = Fingerprint::from_u128;
+        let (t1, t2, t3, translated, pattern_frame, pattern, path, page1, page2) = (
+            id(1),
+            id(2),
+            id(3),
+            id(4),
+            id(5),
+            id(6),
+            id(7),
+            id(8),
+            id(9),
+        );
+
+        let size = Axes::new(Scalar(10.), Scalar(10.));
+        let translate = TransformItem::Translate(Arc::new(size));
+        let mut items = BTreeMap::new();
+        items.insert(t1, text(1));
+        items.insert(t2, text(2));
+        items.insert(t3, text(3));
+        items.insert(translated, VecItem::Item(TransformedRef(translate, t1)));
+        items.insert(page1, group(&[t1, translated]));
+        items.insert(pattern_frame, group(&[t3]));
+        items.insert(
+            pattern,
+            VecItem::Pattern(Arc::new(PatternItem {
+                frame: pattern_frame,
+                size,
+                spacing: Axes::new(Scalar(0.), Scalar(0.)),
+            })),
+        );
+        items.insert(
+            path,
+            VecItem::Path(PathItem {
+                d: "M 0 0 L 10 0 L 10 10 Z".into(),
+                size: Some(size),
+                styles: vec![PathStyle::Fill(
+                    format!("@p{}", pattern.as_svg_id("")).into(),
+                )],
+            }),
+        );
+        items.insert(page2, group(&[t2, path]));
+
+        let mut module = Module {
+            fonts: vec![FontItem {
+                fingerprint: id(10),
+                family: "Test".into(),
+                hash: FONT_HASH,
+                cap_height: Scalar(700.),
+                ascender: Scalar(800.),
+                descender: Scalar(-200.),
+                units_per_em: Scalar(1000.),
+                vertical: false,
+                glyphs: vec![],
+                glyph_cov: Default::default(),
+            }],
+            glyphs: (1..=3)
+                .map(|idx| {
+                    let outline = OutlineGlyphItem {
+                        ts: None,
+                        d: format!("M 0 0 L {idx} {idx} Z").into(),
+                        ligature_len: 0,
+                    };
+                    (glyph(idx), FlatGlyphItem::Outline(Arc::new(outline)))
+                })
+                .collect(),
+            items,
+        };
+        module.prepare_glyphs();
+
+        let pages = [page1, page2].map(|content| Page {
+            content,
+            size: Axes::new(Scalar(100.), Scalar(100.)),
+        });
+        (module, pages.to_vec(), pattern)
+    }
+
+    #[test]
+    fn test_used_glyphs() {
+        let (module, pages, pattern) = two_pages();
+
+        let mut used = UsedGlyphs::new(&module);
+        used.visit(&pages[0].content);
+        // Visiting an item twice is a no-op.
+        used.visit(&pages[0].content);
+        assert_eq!(used.glyphs, HashSet::from([glyph(1)]));
+
+        // The glyphs in patterns are not collected from paths filled by them,
+        // but from the patterns.
+        let mut used = UsedGlyphs::new(&module);
+        used.visit(&pages[1].content);
+        assert_eq!(used.glyphs, HashSet::from([glyph(2)]));
+        used.visit(&pattern);
+        assert_eq!(used.glyphs, HashSet::from([glyph(2), glyph(3)]));
+
+        let mut used = UsedGlyphs::new(&module);
+        used.visit(&Fingerprint::from_u128(100));
+        assert!(used.glyphs.is_empty());
+    }
+
+    #[test]
+    fn test_render_pages() {
+        let (module, pages, pattern) = two_pages();
+        let options = SvgExportOptions::from_feature::<SvgExportFeature>();
+        let svgs =
+            SvgExporter::<SvgExportFeature>::render_pages_with_options(&module, &pages, options);
+        let svgs = svgs.into_iter().map(generate_text).collect::<Vec<_>>();
+        assert_eq!(svgs.len(), 2);
+
+        let glyph_def = |idx| format!(r#"id="{}""#, glyph(idx).as_svg_id("g"));
+        let pattern_def = format!(r#"<pattern id="{}""#, pattern.as_svg_id("g"));
+
+        assert!(svgs[0].contains(&glyph_def(1)));
+        assert!(!svgs[0].contains(&glyph_def(2)));
+        assert!(!svgs[0].contains(&glyph_def(3)));
+        assert!(!svgs[0].contains(&pattern_def));
+
+        assert!(!svgs[1].contains(&glyph_def(1)));
+        assert!(svgs[1].contains(&glyph_def(2)));
+        assert!(svgs[1].contains(&glyph_def(3)));
+        assert!(svgs[1].contains(&pattern_def));
+
+        // The stacked SVG carries all definitions.
+        let svg =
+            SvgExporter::<SvgExportFeature>::render_with_options(&module, &pages, None, options);
+        let svg = generate_text(svg);
+        for idx in 1..=3 {
+            assert!(svg.contains(&glyph_def(idx)));
+        }
+        assert!(svg.contains(&pattern_def));
+    }
+
+    #[test]
+    fn test_options_masked_by_features() {
+        let (module, pages, _) = two_pages();
+        let options = SvgExportOptions {
+            with_responsive_js: true,
+            ..SvgExportOptions::from_feature::<SvgExportFeature>()
+        };
+        let script = r#"<script type="text/javascript">"#;
+
+        // The features disabled at compile time are not enabled by options.
+        let svg =
+            SvgExporter::<SvgExportFeature>::render_with_options(&module, &pages, None, options);
+        assert!(!generate_text(svg).contains(script));
+
+        let svg = SvgExporter::<DynamicExportFeature>::render_with_options(
+            &module, &pages, None, options,
+        );
+        assert!(generate_text(svg).contains(script));
+
+        let options = SvgExportOptions {
+            with_responsive_js: false,
+            ..options
+        };
+        let svg = SvgExporter::<DynamicExportFeature>::render_with_options(
+            &module, &pages, None, options,
+        );
+        assert!(!generate_text(svg).contains(script));
+    }
+}
@@ -0,0 +1,510 @@
+//! Web fonts embedded into SVG.
+//!
+//! A text whose glyphs are all mapped from its characters by the `cmap` of
+//! its font is rendered as a `<text>` element, so that it is selectable and
+//! accessible to screen readers. The fonts are subset to the glyphs of such
+//! texts and embedded as WOFF2 data URIs in `@font-face` rules. Other texts
+//! are still rendered as glyph outlines.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Write;
+
+use base64::Engine;
+use reflexo::vector::ir::{FlatGlyphItem, FontItem, GlyphRef, TextItem};
+use typst::layout::{Frame, FrameItem};
+use typst::model::Document as TypstDocument;
+use typst::text::Font;
+
+use crate::backend::SvgText;
+
+/// The fonts of a document to embed as web fonts, indexed by the hashes of
+/// the fonts in the vector IR.
+#[derive(Debug, Default, Clone)]
+pub struct WebFonts {
+    fonts: HashMap<u32, Font>,
+}
+
+impl WebFonts {
+    /// Collects the fonts used by a document.
+    pub fn from_document(doc: &TypstDocument) -> Self {
+        let mut fonts = HashMap::new();
+        let mut conflicts = HashSet::new();
+        for page in &doc.pages {
+            collect_fonts(&page.frame, &mut fonts, &mut conflicts);
+        }
+
+        // the vector IR renames a font whose hash conflicts with another font,
+        // so such fonts are left rendered as outlines.
+        for hash in conflicts {
+            fonts.remove(&hash);
+        }
+
+        Self { fonts }
+    }
+
+    /// Lays out a text in its web font, in which each glyph mapped from the
+    /// next character by the font is rendered by the character, and the
+    /// others, e.g. ligatures and emojis, are rendered as outlines.
+    ///
+    /// Returns `None` if the text is not laid out from left to right, or the
+    /// glyphs cannot be aligned with the characters.
+    pub(crate) fn layout_text(&self, text: &TextItem, font: &FontItem) -> Option<WebText> {
+        let web_font = self.fonts.get(&font.hash)?;
+        if text.shape.dir.as_ref() != "ltr" {
+            return None;
+        }
+
+        let ttf = web_font.ttf();
+        let char_glyph = |c: char| ttf.glyph_index(c).map(|g| g.0 as u32).filter(|g| *g != 0);
+        let chars = text.content.content.chars().collect::<Vec<_>>();
+        let glyphs = &text.content.glyphs;
+
+        let mut web_text = WebText::default();
+        let mut pos = 0;
+        for (i, (_, _, glyph)) in glyphs.iter().enumerate() {
+            // image glyphs, e.g. emojis, are not kept by the subset font.
+            let is_image = matches!(
+                font.get_glyph(*glyph).map(AsRef::as_ref),
+                Some(FlatGlyphItem::Image(..))
+            );
+            let c = *chars.get(pos)?;
+            if !is_image && char_glyph(c) == Some(*glyph) {
+                web_text.glyphs.push(WebGlyph::Char(c));
+                web_text.used.push(*glyph as u16);
+                pos += 1;
+                continue;
+            }
+
+            // the glyph takes the characters until the one of the next glyph.
+            let end = match glyphs.get(i + 1) {
+                Some((_, _, next)) => {
+                    pos + 1
+                        + chars[pos + 1..]
+                            .iter()
+                            .position(|c| char_glyph(*c) == Some(*next))?
+                }
+                None => chars.len(),
+            };
+            let chars = &chars[pos..end];
+            web_text
+                .used
+                .extend(chars.iter().flat_map(|c| char_glyph(*c)).map(|g| g as u16));
+            web_text
+                .glyphs
+                .push(WebGlyph::Outline(chars.iter().collect()));
+            pos = end;
+        }
+
+        (pos == chars.len()).then_some(web_text)
+    }
+
+    /// Renders the `@font-face` rules of the fonts subset to the used glyphs.
+    pub(crate) fn render_font_faces(&self, usage: &WebFontUsage, svg: &mut Vec<SvgText>) {
+        if usage.text.is_empty() {
+            return;
+        }
+
+        svg.push(r#"<style type="text/css">"#.into());
+        for (hash, glyphs) in &usage.text {
+            let Some(font) = self.fonts.get(hash) else {
+                continue;
+            };
+
+            let Some(woff2) = subset_font(font, glyphs).and_then(|data| encode_woff2(&data)) else {
+                log::warn!("failed to embed font {} as a web font", font.info().family);
+                continue;
+            };
+
+            svg.push(SvgText::Plain(format!(
+                r#"@font-face{{font-family:"{}";src:url(data:font/woff2;base64,{}) format("woff2")}}"#,
+                usage.family(*hash),
+                base64::engine::general_purpose::STANDARD.encode(woff2),
+            )));
+        }
+        svg.push("</style>".into());
+    }
+}
+
+/// A text laid out in its web font, see [`WebFonts::layout_text`].
+#[derive(Debug, Default)]
+pub(crate) struct WebText {
+    /// How each glyph of the text is rendered.
+    pub glyphs: Vec<WebGlyph>,
+    /// The glyphs of the characters in the web font.
+    pub used: Vec<u16>,
+}
+
+/// How a glyph of a text is rendered in web fonts.
+#[derive(Debug)]
+pub(crate) enum WebGlyph {
+    /// The glyph is rendered by the character in the web font.
+    Char(char),
+    /// The glyph is rendered as an outline, and its characters are rendered
+    /// invisibly to keep the text selectable.
+    Outline(String),
+}
+
+/// The glyphs rendered by a task, either as texts in web fonts or as
+/// outlines.
+#[derive(Debug, Default)]
+pub(crate) struct WebFontUsage {
+    /// Distinguishes the font families of SVGs rendered from different pages,
+    /// which can be inlined into the same HTML.
+    pub scope: u32,
+    /// The glyphs rendered as texts, by the hashes of fonts.
+    pub text: BTreeMap<u32, BTreeSet<u16>>,
+    /// The glyphs rendered as outlines.
+    pub outlines: HashSet<GlyphRef>,
+}
+
+impl WebFontUsage {
+    /// Gets the family name of the web font of a font.
+    pub fn family(&self, font_hash: u32) -> String {
+        format!("tw-{font_hash:x}-{:x}", self.scope)
+    }
+}
+
+fn collect_fonts(frame: &Frame, fonts: &mut HashMap<u32, Font>, conflicts: &mut HashSet<u32>) {
+    for (_, item) in frame.items() {
+        match item {
+            FrameItem::Group(group) => collect_fonts(&group.frame, fonts, conflicts),
+            FrameItem::Text(text) => {
+                let hash = reflexo::hash::hash32(&text.font);
+                let font = fonts.entry(hash).or_insert_with(|| text.font.clone());
+                if *font != text.font {
+                    conflicts.insert(hash);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Subsets a font to the glyphs, keeping glyph ids and the `cmap` table.
+///
+/// Falls back to the whole font if it cannot be subset and is not in a font
+/// collection.
+fn subset_font(font: &Font, glyphs: &BTreeSet<u16>) -> Option<Vec<u8>> {
+    let glyphs = glyphs.iter().copied().collect::<Vec<_>>();
+    match subsetter::subset(font.data(), font.index(), subsetter::Profile::pdf(&glyphs)) {
+        Ok(data) => Some(data),
+        Err(_) if is_sfnt(font.data()) => Some(font.data().to_vec()),
+        Err(_) => None,
+    }
+}
+
+/// Checks whether the data is a single TrueType or OpenType font, rather
+/// than a font collection.
+fn is_sfnt(data: &[u8]) -> bool {
+    matches!(
+        data.get(0..4),
+        Some(b"\x00\x01\x00\x00" | b"OTTO" | b"true")
+    )
+}
+
+/// The tags of tables which are encoded by their indices in WOFF2.
+const WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// Encodes a font in the WOFF2 format, without transforming tables.
+///
+/// Returns `None` if the font is malformed.
+fn encode_woff2(sfnt: &[u8]) -> Option<Vec<u8>> {
+    let flavor = read_u32(sfnt, 0)?;
+    let num_tables = read_u16(sfnt, 4)?;
+    let mut tables = (0..num_tables as usize)
+        .map(|i| {
+            let record = 12 + 16 * i;
+            let tag: [u8; 4] = sfnt.get(record..record + 4)?.try_into().ok()?;
+            let offset = read_u32(sfnt, record + 8)? as usize;
+            let len = read_u32(sfnt, record + 12)? as usize;
+            Some((tag, sfnt.get(offset..offset.checked_add(len)?)?))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    tables.sort_by_key(|(tag, _)| *tag);
+    // the `loca` table must follow the `glyf` table.
+    let position =
+        |tables: &[([u8; 4], &[u8])], tag: &[u8; 4]| tables.iter().position(|(t, _)| t == tag);
+    if let Some(loca) = position(&tables, b"loca") {
+        let loca = tables.remove(loca);
+        match position(&tables, b"glyf") {
+            Some(glyf) => tables.insert(glyf + 1, loca),
+            None => tables.push(loca),
+        }
+    }
+
+    let mut directory = vec![];
+    let mut data = vec![];
+    let mut sfnt_size = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        // the null transform is version 3 for `glyf` and `loca`, and 0 for
+        // the others.
+        let transform = match tag {
+            b"glyf" | b"loca" => 3 << 6,
+            _ => 0,
+        };
+        match WOFF2_KNOWN_TAGS.iter().position(|known| *known == tag) {
+            Some(idx) => directory.push(transform | idx as u8),
+            None => {
+                directory.push(transform | 63);
+                directory.extend(tag);
+            }
+        }
+        write_base128(&mut directory, table.len() as u32);
+
+        data.extend_from_slice(table);
+        sfnt_size += table.len().next_multiple_of(4);
+    }
+
+    let mut compressed = vec![];
+    {
+        let mut w = brotli::CompressorWriter::new(&mut compressed, 4096, 11, 22);
+        w.write_all(&data).ok()?;
+    }
+
+    let length = 48 + directory.len() + compressed.len();
+    let mut out = Vec::with_capacity(length);
+    out.extend(b"wOF2");
+    out.extend(flavor.to_be_bytes());
+    out.extend((length as u32).to_be_bytes());
+    out.extend(num_tables.to_be_bytes());
+    out.extend(0u16.to_be_bytes());
+    out.extend((sfnt_size as u32).to_be_bytes());
+    out.extend((compressed.len() as u32).to_be_bytes());
+    // version 1.0, without metadata and private data
+    out.extend(1u16.to_be_bytes());
+    out.extend(0u16.to_be_bytes());
+    out.extend([0; 20]);
+    out.extend(directory);
+    out.extend(compressed);
+    Some(out)
+}
+
+/// Writes a `UIntBase128` number.
+fn write_base128(out: &mut Vec<u8>, v: u32) {
+    let len = (1..5).find(|i| v >> (7 * i) == 0).unwrap_or(5);
+    for i in (0..len).rev() {
+        let byte = ((v >> (7 * i)) & 0x7F) as u8;
+        out.push(if i == 0 { byte } else { byte | 0x80 });
+    }
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::sync::Arc;
+
+    use reflexo::test_utils::{test_font, triangle, write_font};
+    use reflexo::vector::ir::{FontRef, Scalar, TextItemContent, TextShape};
+    use typst::foundations::Bytes;
+
+    use super::*;
+
+    const FONT_HASH: u32 = 7;
+
+    /// Builds a font in which `A`, `B`, `C` and `D` are mapped to the glyphs 1
+    /// to 4, and the glyph 5 is a ligature. The glyphs are right triangles
+    /// with legs of growing sizes, except the empty glyph 0.
+    fn ligature_font() -> Vec<u8> {
+        let glyphs = (0..6).map(|i| if i == 0 { vec![] } else { triangle(i * 100) });
+        test_font(&glyphs.collect::<Vec<_>>())
+    }
+
+    fn read_base128(data: &[u8], pos: &mut usize) -> u32 {
+        let mut v = 0;
+        loop {
+            let byte = data[*pos];
+            *pos += 1;
+            v = (v << 7) | (byte & 0x7F) as u32;
+            if byte & 0x80 == 0 {
+                return v;
+            }
+        }
+    }
+
+    /// Decodes a WOFF2 font without transformed tables into a font.
+    fn decode_woff2(woff2: &[u8]) -> Vec<u8> {
+        assert_eq!(&woff2[0..4], b"wOF2");
+        assert_eq!(read_u32(woff2, 8), Some(woff2.len() as u32));
+        let num_tables = read_u16(woff2, 12).unwrap();
+        let compressed_len = read_u32(woff2, 20).unwrap() as usize;
+
+        let mut pos = 48;
+        let mut directory = vec![];
+        for _ in 0..num_tables {
+            let flags = woff2[pos];
+            pos += 1;
+            // only null transforms are written.
+            let null_transform = match flags & 0x3F {
+                10 | 11 => 3,
+                _ => 0,
+            };
+            assert_eq!(flags >> 6, null_transform);
+            let tag = match flags & 0x3F {
+                63 => {
+                    pos += 4;
+                    woff2[pos - 4..pos].try_into().unwrap()
+                }
+                idx => *WOFF2_KNOWN_TAGS[idx as usize],
+            };
+            directory.push((tag, read_base128(woff2, &mut pos) as usize));
+        }
+        assert_eq!(pos + compressed_len, woff2.len());
+
+        let mut data = vec![];
+        brotli::Decompressor::new(&woff2[pos..], 4096)
+            .read_to_end(&mut data)
+            .unwrap();
+
+        let mut offset = 0;
+        let tables = directory
+            .into_iter()
+            .map(|(tag, len)| {
+                offset += len;
+                (tag, data[offset - len..offset].to_vec())
+            })
+            .collect();
+        assert_eq!(offset, data.len());
+        write_font(tables)
+    }
+
+    #[test]
+    fn test_encode_woff2() {
+        let sfnt = ligature_font();
+        let woff2 = encode_woff2(&sfnt).unwrap();
+        assert_eq!(read_u32(&woff2, 4), Some(0x00010000));
+        assert_eq!(read_u16(&woff2, 12), Some(8));
+        // the size of the font, in which tables are padded.
+        assert_eq!(read_u32(&woff2, 16), Some(sfnt.len() as u32));
+
+        let decoded = decode_woff2(&woff2);
+        assert_eq!(decoded, sfnt);
+
+        let font = Font::new(Bytes::from(decoded), 0).unwrap();
+        assert_eq!(font.info().family, "Test");
+        let ttf = font.ttf();
+        assert_eq!(ttf.number_of_glyphs(), 6);
+        let a = ttf.glyph_index('A').unwrap();
+        assert_eq!(a.0, 1);
+        let bbox = ttf.glyph_bounding_box(a).unwrap();
+        assert_eq!((bbox.x_max, bbox.y_max), (100, 100));
+        assert_eq!(ttf.glyph_index('D').map(|g| g.0), Some(4));
+
+        assert_eq!(encode_woff2(b"not a font"), None);
+        assert_eq!(encode_woff2(&sfnt[..100]), None);
+    }
+
+    #[test]
+    fn test_subset_font() {
+        let font = Font::new(Bytes::from(ligature_font()), 0).unwrap();
+        let glyphs = BTreeSet::from([1, 2]);
+        let subset = subset_font(&font, &glyphs).unwrap();
+        let woff2 = encode_woff2(&subset).unwrap();
+        let subset = Font::new(Bytes::from(decode_woff2(&woff2)), 0).unwrap();
+        // glyph ids are kept.
+        assert_eq!(subset.ttf().glyph_index('B').map(|g| g.0), Some(2));
+
+        assert!(is_sfnt(&ligature_font()));
+        assert!(!is_sfnt(b"ttcf\x00\x01\x00\x00"));
+        assert!(!is_sfnt(b""));
+    }
+
+    fn layout(dir: &str, content: &str, glyphs: &[u32]) -> Option<Vec<String>> {
+        let font = Font::new(Bytes::from(ligature_font()), 0).unwrap();
+        let web_fonts = WebFonts {
+            fonts: HashMap::from([(FONT_HASH, font)]),
+        };
+        let font = FontItem {
+            fingerprint: reflexo::hash::Fingerprint::from_u128(1),
+            family: "Test".into(),
+            hash: FONT_HASH,
+            cap_height: Scalar(700.),
+            ascender: Scalar(800.),
+            descender: Scalar(-200.),
+            units_per_em: Scalar(1000.),
+            vertical: false,
+            glyphs: vec![],
+            glyph_cov: Default::default(),
+        };
+        let text = TextItem {
+            shape: Arc::new(TextShape {
+                font: FontRef {
+                    hash: FONT_HASH,
+                    idx: 0,
+                },
+                dir: dir.into(),
+                size: Scalar(10.),
+                styles: vec![],
+            }),
+            content: Arc::new(TextItemContent {
+                content: content.into(),
+                glyphs: glyphs
+                    .iter()
+                    .map(|glyph| (Scalar(0.), Scalar(5.), *glyph))
+                    .collect(),
+            }),
+        };
+
+        let web_text = web_fonts.layout_text(&text, &font)?;
+        let glyphs = web_text.glyphs.iter().map(|glyph| match glyph {
+            WebGlyph::Char(c) => c.to_string(),
+            WebGlyph::Outline(chars) => format!("[{chars}]"),
+        });
+        let mut glyphs = glyphs.collect::<Vec<_>>();
+        glyphs.push(format!("{:?}", web_text.used));
+        Some(glyphs)
+    }
+
+    #[test]
+    fn test_layout_text() {
+        assert_eq!(layout("ltr", "AB", &[1, 2]).unwrap(), ["A", "B", "[1, 2]"]);
+
+        // a ligature takes the characters until the one of the next glyph,
+        // which are used to keep the text selectable.
+        assert_eq!(
+            layout("ltr", "ABCD", &[1, 5, 4]).unwrap(),
+            ["A", "[BC]", "D", "[1, 2, 3, 4]"]
+        );
+        assert_eq!(
+            layout("ltr", "ABC", &[1, 5]).unwrap(),
+            ["A", "[BC]", "[1, 2, 3]"]
+        );
+        assert_eq!(layout("ltr", "AB", &[5]).unwrap(), ["[AB]", "[1, 2]"]);
+
+        // an unmapped character is rendered by the glyph of the font, e.g.
+        // `.notdef`, and is not used in the web font.
+        assert_eq!(
+            layout("ltr", "AZB", &[1, 0, 2]).unwrap(),
+            ["A", "[Z]", "B", "[1, 2]"]
+        );
+        assert_eq!(layout("ltr", "Z", &[0]).unwrap(), ["[Z]", "[]"]);
+
+        // a glyph mapped from a character, but not from the one it is aligned
+        // with, is rendered as an outline.
+        assert_eq!(
+            layout("ltr", "AB", &[2, 2]).unwrap(),
+            ["[A]", "B", "[1, 2]"]
+        );
+
+        // glyphs that cannot be aligned with the characters.
+        assert_eq!(layout("ltr", "AB", &[1, 2, 3]), None);
+        assert_eq!(layout("ltr", "ABC", &[1, 2]), None);
+        assert_eq!(layout("ltr", "AB", &[5, 3]), None);
+        assert_eq!(layout("rtl", "AB", &[1, 2]), None);
+    }
+}
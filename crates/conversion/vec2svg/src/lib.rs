@@ -3,6 +3,8 @@
 // todo: https://github.com/typst/typst/pull/2610
 // color export
 
+use reflexo::error::prelude::*;
+use reflexo_vec2bbox::Vec2BBoxPass;
 use typst::model::Document as TypstDocument;
 
 /// re-export the core types.
@@ -23,10 +25,11 @@ pub(crate) mod frontend;
 pub use frontend::{
     DynamicLayoutSvgExporter, IncrSvgDocClient, IncrSvgDocServer, IncrementalRenderContext,
 };
-pub use frontend::{SvgExporter, SvgTask};
+pub use frontend::{SvgExporter, SvgTask, WebFonts};
 
 /// Useful transform for SVG Items.
 pub(crate) mod transform;
+pub use transform::MinifyReport;
 
 #[derive(Default)]
 pub struct SvgDataSelection {
@@ -72,6 +75,133 @@ pub trait ExportFeature {
     const AWARE_HTML_ENTITY: bool;
 }
 
+/// The options of SVG export, which are configurable at runtime.
+///
+/// By default, the options of [`SvgExporter`] and [`SvgTask`] are given by
+/// their [`ExportFeature`], see [`SvgExportOptions::from_feature`]. The
+/// options only take effect where the features are enabled, so that the
+/// functions taking options render with [`DynamicExportFeature`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SvgExportOptions {
+    /// Whether to attach debug info to svg elements.
+    pub attach_debug_info: bool,
+
+    /// Whether to render text element.
+    /// The text elements is selectable and searchable.
+    pub render_text_element: bool,
+
+    /// Whether to use stable glyph id.
+    /// See [`ExportFeature::USE_STABLE_GLYPH_ID`].
+    pub use_stable_glyph_id: bool,
+
+    /// Whether to rasterize text, which takes effect only if the
+    /// `aggresive-browser-rasterization` feature is enabled.
+    pub rasterize_text: bool,
+
+    /// Whether to include builtin css.
+    pub with_builtin_css: bool,
+
+    /// Whether to include js for interactive and responsive actions.
+    pub with_responsive_js: bool,
+
+    /// Also escape html entity.
+    pub aware_html_entity: bool,
+
+    /// Whether to render texts as `<text>` elements in the used fonts, which
+    /// are subset and embedded as WOFF2 web fonts. Texts that cannot be
+    /// represented in their fonts are still rendered as glyph outlines.
+    ///
+    /// It takes effect only if the fonts are given, e.g. when rendering a
+    /// [`TypstDocument`] or by [`SvgTask::web_fonts`].
+    pub embed_web_fonts: bool,
+
+    /// Whether to minify SVG more strictly, which deduplicates identical clip
+    /// paths and gradients, drops empty groups and merges the presentation
+    /// attributes shared by elements into CSS classes.
+    pub strict_minify: bool,
+
+    /// The number of decimal places kept in path data and translations, or
+    /// `None` to keep the numbers as they are.
+    pub numeric_precision: Option<u8>,
+}
+
+impl SvgExportOptions {
+    /// Gets the options given by the features.
+    pub const fn from_feature<Feat: ExportFeature>() -> Self {
+        Self {
+            attach_debug_info: Feat::SHOULD_ATTACH_DEBUG_INFO,
+            render_text_element: Feat::SHOULD_RENDER_TEXT_ELEMENT,
+            use_stable_glyph_id: Feat::USE_STABLE_GLYPH_ID,
+            rasterize_text: Feat::SHOULD_RASTERIZE_TEXT,
+            with_builtin_css: Feat::WITH_BUILTIN_CSS,
+            with_responsive_js: Feat::WITH_RESPONSIVE_JS,
+            aware_html_entity: Feat::AWARE_HTML_ENTITY,
+            embed_web_fonts: false,
+            strict_minify: false,
+            numeric_precision: None,
+        }
+    }
+
+    /// Overrides the options by the fields set in the overrides.
+    pub fn merge(self, overrides: &SvgExportOverrides) -> Self {
+        Self {
+            attach_debug_info: overrides
+                .attach_debug_info
+                .unwrap_or(self.attach_debug_info),
+            render_text_element: overrides
+                .render_text_element
+                .unwrap_or(self.render_text_element),
+            use_stable_glyph_id: overrides
+                .use_stable_glyph_id
+                .unwrap_or(self.use_stable_glyph_id),
+            rasterize_text: overrides.rasterize_text.unwrap_or(self.rasterize_text),
+            with_builtin_css: overrides.with_builtin_css.unwrap_or(self.with_builtin_css),
+            with_responsive_js: overrides
+                .with_responsive_js
+                .unwrap_or(self.with_responsive_js),
+            aware_html_entity: overrides
+                .aware_html_entity
+                .unwrap_or(self.aware_html_entity),
+            embed_web_fonts: overrides.embed_web_fonts.unwrap_or(self.embed_web_fonts),
+            strict_minify: overrides.strict_minify.unwrap_or(self.strict_minify),
+            numeric_precision: overrides.numeric_precision.or(self.numeric_precision),
+        }
+    }
+}
+
+impl Default for SvgExportOptions {
+    fn default() -> Self {
+        Self::from_feature::<DefaultExportFeature>()
+    }
+}
+
+/// The overrides of [`SvgExportOptions`], e.g. given by users, whose unset
+/// fields keep the options they are merged into. See
+/// [`SvgExportOptions::merge`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SvgExportOverrides {
+    /// See [`SvgExportOptions::attach_debug_info`].
+    pub attach_debug_info: Option<bool>,
+    /// See [`SvgExportOptions::render_text_element`].
+    pub render_text_element: Option<bool>,
+    /// See [`SvgExportOptions::use_stable_glyph_id`].
+    pub use_stable_glyph_id: Option<bool>,
+    /// See [`SvgExportOptions::rasterize_text`].
+    pub rasterize_text: Option<bool>,
+    /// See [`SvgExportOptions::with_builtin_css`].
+    pub with_builtin_css: Option<bool>,
+    /// See [`SvgExportOptions::with_responsive_js`].
+    pub with_responsive_js: Option<bool>,
+    /// See [`SvgExportOptions::aware_html_entity`].
+    pub aware_html_entity: Option<bool>,
+    /// See [`SvgExportOptions::embed_web_fonts`].
+    pub embed_web_fonts: Option<bool>,
+    /// See [`SvgExportOptions::strict_minify`].
+    pub strict_minify: Option<bool>,
+    /// See [`SvgExportOptions::numeric_precision`].
+    pub numeric_precision: Option<u8>,
+}
+
 /// The default feature set which is used for exporting full-fledged svg.
 pub struct DefaultExportFeature;
 pub type DefaultSvgTask = SvgTask<'static, DefaultExportFeature>;
@@ -104,11 +234,58 @@ impl ExportFeature for SvgExportFeature {
     const AWARE_HTML_ENTITY: bool = false;
 }
 
+/// The feature set which enables every feature but tracing and inlined svg,
+/// so that the rendering is decided by [`SvgExportOptions`] at runtime.
+pub struct DynamicExportFeature;
+
+impl ExportFeature for DynamicExportFeature {
+    const ENABLE_INLINED_SVG: bool = false;
+    const ENABLE_TRACING: bool = false;
+    const SHOULD_ATTACH_DEBUG_INFO: bool = true;
+    const SHOULD_RENDER_TEXT_ELEMENT: bool = true;
+    const USE_STABLE_GLYPH_ID: bool = true;
+    const SHOULD_RASTERIZE_TEXT: bool = cfg!(feature = "aggresive-browser-rasterization");
+    const WITH_BUILTIN_CSS: bool = true;
+    const WITH_RESPONSIVE_JS: bool = true;
+    const AWARE_HTML_ENTITY: bool = true;
+}
+
 /// Render SVG wrapped with html for [`TypstDocument`].
 pub fn render_svg_html<Feat: ExportFeature>(output: &TypstDocument) -> String {
+    render_svg_html_impl::<Feat>(output, SvgExportOptions::from_feature::<Feat>()).0
+}
+
+/// Render SVG wrapped with html for [`TypstDocument`] with the given options.
+pub fn render_svg_html_with_options(output: &TypstDocument, options: SvgExportOptions) -> String {
+    render_svg_html_with_report(output, options).0
+}
+
+/// Render SVG wrapped with html for [`TypstDocument`] with the given options,
+/// also returning the report of the stricter minification if configured.
+pub fn render_svg_html_with_report(
+    output: &TypstDocument,
+    options: SvgExportOptions,
+) -> (String, Option<MinifyReport>) {
+    render_svg_html_impl::<DynamicExportFeature>(output, options)
+}
+
+fn render_svg_html_impl<Feat: ExportFeature>(
+    output: &TypstDocument,
+    options: SvgExportOptions,
+) -> (String, Option<MinifyReport>) {
     let mut doc = SvgExporter::<Feat>::svg_doc(output);
     doc.module.prepare_glyphs();
-    let mut svg = SvgExporter::<Feat>::render(&doc.module, &doc.pages, None);
+    let web_fonts = options
+        .embed_web_fonts
+        .then(|| WebFonts::from_document(output));
+    let mut svg = SvgExporter::<Feat>::render_impl(
+        &doc.module,
+        &doc.pages,
+        None,
+        options,
+        false,
+        web_fonts.as_ref(),
+    );
 
     // wrap SVG with html
     let mut html: Vec<SvgText> = Vec::with_capacity(svg.len() + 3);
@@ -123,14 +300,109 @@ pub fn render_svg_html<Feat: ExportFeature>(output: &TypstDocument) -> String {
     html.push(r#"</title></head><body>"#.into());
     html.append(&mut svg);
     html.push(r#"</body></html>"#.into());
-    generate_text(transform::minify(html))
+    let (html, report) = transform::minify_with_options(html, &options);
+    (generate_text(html), report)
 }
 
 /// Render SVG for [`TypstDocument`].
 pub fn render_svg(output: &TypstDocument) -> String {
-    type UsingExporter = SvgExporter<SvgExportFeature>;
-    let mut doc = UsingExporter::svg_doc(output);
+    let options = SvgExportOptions::from_feature::<SvgExportFeature>();
+    render_svg_impl::<SvgExportFeature>(output, options).0
+}
+
+/// Render SVG for [`TypstDocument`] with the given options.
+pub fn render_svg_with_options(output: &TypstDocument, options: SvgExportOptions) -> String {
+    render_svg_with_report(output, options).0
+}
+
+/// Render SVG for [`TypstDocument`] with the given options, also returning the
+/// report of the stricter minification if configured.
+pub fn render_svg_with_report(
+    output: &TypstDocument,
+    options: SvgExportOptions,
+) -> (String, Option<MinifyReport>) {
+    render_svg_impl::<DynamicExportFeature>(output, options)
+}
+
+fn render_svg_impl<Feat: ExportFeature>(
+    output: &TypstDocument,
+    options: SvgExportOptions,
+) -> (String, Option<MinifyReport>) {
+    let mut doc = SvgExporter::<Feat>::svg_doc(output);
     doc.module.prepare_glyphs();
-    let svg_text = UsingExporter::render(&doc.module, &doc.pages, None);
-    generate_text(transform::minify(svg_text))
+    let web_fonts = options
+        .embed_web_fonts
+        .then(|| WebFonts::from_document(output));
+    let svg_text = SvgExporter::<Feat>::render_impl(
+        &doc.module,
+        &doc.pages,
+        None,
+        options,
+        false,
+        web_fonts.as_ref(),
+    );
+    let (svg_text, report) = transform::minify_with_options(svg_text, &options);
+    (generate_text(svg_text), report)
+}
+
+/// Render a standalone SVG for each page of [`TypstDocument`].
+pub fn render_svg_pages(output: &TypstDocument) -> Vec<String> {
+    let options = SvgExportOptions::from_feature::<SvgExportFeature>();
+    render_svg_pages_impl::<SvgExportFeature>(output, options)
+        .into_iter()
+        .map(|(svg, _)| svg)
+        .collect()
+}
+
+/// Render a standalone SVG for each page of [`TypstDocument`] with the given
+/// options.
+pub fn render_svg_pages_with_options(
+    output: &TypstDocument,
+    options: SvgExportOptions,
+) -> Vec<String> {
+    render_svg_pages_with_report(output, options)
+        .into_iter()
+        .map(|(svg, _)| svg)
+        .collect()
+}
+
+/// Render a standalone SVG for each page of [`TypstDocument`] with the given
+/// options, also returning the report of the stricter minification of each
+/// page if configured.
+pub fn render_svg_pages_with_report(
+    output: &TypstDocument,
+    options: SvgExportOptions,
+) -> Vec<(String, Option<MinifyReport>)> {
+    render_svg_pages_impl::<DynamicExportFeature>(output, options)
+}
+
+fn render_svg_pages_impl<Feat: ExportFeature>(
+    output: &TypstDocument,
+    options: SvgExportOptions,
+) -> Vec<(String, Option<MinifyReport>)> {
+    let mut doc = SvgExporter::<Feat>::svg_doc(output);
+    doc.module.prepare_glyphs();
+    let web_fonts = options
+        .embed_web_fonts
+        .then(|| WebFonts::from_document(output));
+    SvgExporter::<Feat>::render_pages_impl(&doc.module, &doc.pages, options, web_fonts.as_ref())
+        .into_iter()
+        .map(|svg_text| {
+            let (svg_text, report) = transform::minify_with_options(svg_text, &options);
+            (generate_text(svg_text), report)
+        })
+        .collect()
+}
+
+/// Calculate the tight bounding box of the content of each page of
+/// [`TypstDocument`], in the coordinates of the page.
+///
+/// A bounding box is `None` if the page is blank.
+pub fn render_svg_page_bboxes(output: &TypstDocument) -> ZResult<Vec<Option<ir::Rect>>> {
+    let doc = SvgExporter::<SvgExportFeature>::svg_doc(output);
+    let mut pass = Vec2BBoxPass::default();
+    doc.pages
+        .iter()
+        .map(|page| pass.page_bbox(&doc.module, page))
+        .collect()
 }
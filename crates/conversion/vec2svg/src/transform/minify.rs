@@ -1,8 +1,11 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use reflexo::TakeAs;
 
 use crate::backend::{SvgText, SvgTextNode};
+use crate::SvgExportOptions;
 
 pub fn minify_one(text: &mut SvgText) -> bool {
     let content = match text {
@@ -74,3 +77,875 @@ pub fn minify(mut svg: Vec<SvgText>) -> Vec<SvgText> {
     // println!("minify_svg after: {:#?}", svg);
     svg
 }
+
+/// The sizes of an SVG before and after the stricter minification of
+/// [`minify_with_options`], estimated in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinifyReport {
+    pub before: usize,
+    pub after: usize,
+}
+
+impl std::fmt::Display for MinifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "minified svg from {} to {} bytes ({:.1}% smaller)",
+            self.before,
+            self.after,
+            100. * self.before.saturating_sub(self.after) as f64 / self.before.max(1) as f64
+        )
+    }
+}
+
+/// Do stricter minification of SVG in addition to [`minify`], as configured
+/// by [`SvgExportOptions::strict_minify`] and
+/// [`SvgExportOptions::numeric_precision`].
+///
+/// Returns the minified SVG, and its sizes before and after the stricter
+/// minification if it is configured.
+pub fn minify_with_options(
+    svg: Vec<SvgText>,
+    options: &SvgExportOptions,
+) -> (Vec<SvgText>, Option<MinifyReport>) {
+    let mut svg = minify(svg);
+    if !options.strict_minify && options.numeric_precision.is_none() {
+        return (svg, None);
+    }
+
+    let before = svg.iter().map(SvgText::estimated_len).sum::<usize>();
+
+    if let Some(precision) = options.numeric_precision {
+        let mut raw = None;
+        retain_mut(&mut svg, &mut |text| {
+            round_numbers_in(text, precision, &mut raw);
+            true
+        });
+    }
+
+    if options.strict_minify {
+        dedup_defs(&mut svg);
+        drop_empty_groups(&mut svg);
+        merge_styles(&mut svg);
+    }
+
+    let after = svg.iter().map(SvgText::estimated_len).sum::<usize>();
+    (svg, Some(MinifyReport { before, after }))
+}
+
+/// Visits the items in document order, removing the items for which `f`
+/// returns `false`.
+fn retain_mut(svg: &mut Vec<SvgText>, f: &mut impl FnMut(&mut SvgText) -> bool) {
+    svg.retain_mut(|text| {
+        if !f(text) {
+            return false;
+        }
+        if let SvgText::Content(node) = text {
+            retain_mut(&mut Arc::make_mut(node).content, f);
+        }
+        true
+    });
+}
+
+/// Visits the items in document order.
+fn visit(svg: &[SvgText], f: &mut impl FnMut(&SvgText)) {
+    for text in svg {
+        f(text);
+        if let SvgText::Content(node) = text {
+            visit(&node.content, f);
+        }
+    }
+}
+
+/// The elements whose content is not SVG markup, e.g. texts, styles and
+/// scripts, in which numbers are never rounded.
+const RAW_TAGS: &[&str] = &["text", "style", "script", "foreignObject"];
+
+/// Rounds the numbers in the path data of `<path>` tags and the translations
+/// of `<path>` and `<g>` tags.
+///
+/// `raw` tracks the [`RAW_TAGS`] element being skipped, whose content may
+/// continue in the following texts.
+fn round_numbers_in(text: &mut SvgText, precision: u8, raw: &mut Option<&'static str>) {
+    match text {
+        SvgText::Plain(s) => {
+            if let Some(rounded) = map_attrs(s, raw, |tag, name, value| match (tag, name) {
+                ("path", "d") => Some(round_numbers(value, precision)),
+                ("path" | "g", "transform") => Some(round_translations(value, precision)),
+                _ => None,
+            }) {
+                *s = rounded;
+            }
+        }
+        // nodes are rendered as `<g>` tags.
+        SvgText::Content(node) => {
+            if raw.is_some() || !node.attributes.iter().any(|(k, _)| *k == "transform") {
+                return;
+            }
+            for (key, value) in &mut Arc::make_mut(node).attributes {
+                if *key == "transform" {
+                    *value = round_translations(value, precision);
+                }
+            }
+        }
+    }
+}
+
+/// Rounds the translations in transforms, i.e. the arguments of
+/// `translate()` and the last two arguments of `matrix()`. The other
+/// numbers are kept, since a small error in scales or angles is amplified
+/// by the coordinates.
+fn round_translations(s: &str, precision: u8) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(open) = rest.find('(') {
+        let Some(close) = rest[open..].find(')').map(|i| open + i) else {
+            break;
+        };
+        let func = rest[..open].trim_start_matches([' ', ',']);
+        let args = &rest[open + 1..close];
+        out.push_str(&rest[..=open]);
+        match func {
+            "translate" => out.push_str(&round_numbers(args, precision)),
+            "matrix" => {
+                let args = args.split(',').collect::<Vec<_>>();
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    if i >= 4 {
+                        out.push_str(&round_numbers(arg, precision));
+                    } else {
+                        out.push_str(arg);
+                    }
+                }
+            }
+            _ => out.push_str(args),
+        }
+        out.push(')');
+        rest = &rest[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Maps the values of the attributes of the tags in a plain text by
+/// `f(tag, name, value)`, returning `None` if no value is changed.
+///
+/// The content of [`RAW_TAGS`] elements is kept as is, and `raw` tracks the
+/// element being skipped across texts.
+fn map_attrs(
+    s: &str,
+    raw: &mut Option<&'static str>,
+    mut f: impl FnMut(&str, &str, &str) -> Option<String>,
+) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = s;
+    let mut changed = false;
+    loop {
+        if let Some(tag) = *raw {
+            let Some(close) = rest.find(&format!("</{tag}")) else {
+                break;
+            };
+            out.push_str(&rest[..close]);
+            rest = &rest[close..];
+            *raw = None;
+        }
+
+        let Some(open) = rest.find('<') else {
+            break;
+        };
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+
+        let tag_len = tag_len(rest);
+        let (tag, next) = rest.split_at(tag_len);
+        let name_len = tag[1..]
+            .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+            .unwrap_or(tag.len() - 1);
+        let name = &tag[1..1 + name_len];
+        if let Some(raw_tag) = RAW_TAGS.iter().find(|raw_tag| **raw_tag == name) {
+            if !tag.ends_with("/>") {
+                *raw = Some(raw_tag);
+            }
+        }
+
+        let mut attrs = tag;
+        while let Some(pos) = attrs.find("=\"") {
+            let name_start = attrs[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+            let attr = &attrs[name_start..pos];
+            let value_start = pos + 2;
+            let Some(value_len) = attrs[value_start..].find('"') else {
+                break;
+            };
+            let value = &attrs[value_start..value_start + value_len];
+
+            out.push_str(&attrs[..value_start]);
+            match f(name, attr, value) {
+                Some(mapped) => {
+                    changed |= mapped != value;
+                    out.push_str(&mapped);
+                }
+                None => out.push_str(value),
+            }
+            attrs = &attrs[value_start + value_len..];
+        }
+        out.push_str(attrs);
+        rest = next;
+    }
+    out.push_str(rest);
+
+    changed.then_some(out)
+}
+
+/// Gets the length of the tag at the start of the text, i.e. until the
+/// closing `>` out of attribute values, or the whole text if it is not
+/// closed.
+fn tag_len(s: &str) -> usize {
+    let mut quoted = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '>' if !quoted => return i + 1,
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// Rounds the numbers in path data or transforms to the number of decimal
+/// places.
+fn round_numbers(s: &str, precision: u8) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let Some(end) = scan_number(bytes, i) else {
+            let c = s[i..].chars().next().unwrap();
+            out.push(c);
+            i += c.len_utf8();
+            continue;
+        };
+
+        let num = &s[i..end];
+        match num.parse::<f64>() {
+            Ok(v) => {
+                let rounded = format_number(v, precision);
+                // separates numbers which are only delimited by their signs or
+                // dots before rounding, e.g. `1.5.5`.
+                if out.ends_with(|c: char| c.is_ascii_digit() || c == '.')
+                    && rounded.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+                {
+                    out.push(' ');
+                }
+                out.push_str(&rounded);
+            }
+            Err(_) => out.push_str(num),
+        }
+        i = end;
+    }
+    out
+}
+
+/// Scans a number starting at the position, returning its end.
+fn scan_number(b: &[u8], start: usize) -> Option<usize> {
+    let digits = |mut i: usize| {
+        while i < b.len() && b[i].is_ascii_digit() {
+            i += 1;
+        }
+        i
+    };
+
+    let mut i = start;
+    if matches!(b.get(i), Some(b'+' | b'-')) {
+        i += 1;
+    }
+    let int_end = digits(i);
+    let mut end = int_end;
+    if b.get(end) == Some(&b'.') {
+        end = digits(end + 1);
+    }
+    // at least a digit is in the mantissa
+    if int_end == i && end <= int_end + 1 {
+        return None;
+    }
+
+    if matches!(b.get(end), Some(b'e' | b'E')) {
+        let mut exp = end + 1;
+        if matches!(b.get(exp), Some(b'+' | b'-')) {
+            exp += 1;
+        }
+        let exp_end = digits(exp);
+        if exp_end > exp {
+            end = exp_end;
+        }
+    }
+
+    Some(end)
+}
+
+/// Formats a number rounded to the number of decimal places in its shortest
+/// form, e.g. `-0.50` as `-.5`.
+fn format_number(v: f64, precision: u8) -> String {
+    let mut s = format!("{:.*}", precision as usize, v);
+    if s.contains('.') {
+        s.truncate(s.trim_end_matches('0').trim_end_matches('.').len());
+    }
+    match s.as_str() {
+        "-0" => "0".to_owned(),
+        _ if s.starts_with("0.") => s[1..].to_owned(),
+        _ if s.starts_with("-0.") => format!("-{}", &s[2..]),
+        _ => s,
+    }
+}
+
+/// The definitions that are rendered as a single plain text.
+const DEF_TAGS: &[&str] = &["clipPath", "linearGradient", "radialGradient", "pattern"];
+
+/// Removes definitions that are identical to or have the same id as a
+/// previous one, and redirects the references to them.
+fn dedup_defs(svg: &mut Vec<SvgText>) {
+    let mut ids = HashSet::new();
+    let mut by_content = HashMap::new();
+    let mut aliases = HashMap::new();
+
+    retain_mut(svg, &mut |text| {
+        let SvgText::Plain(s) = text else {
+            return true;
+        };
+        let Some((id, content)) = parse_def(s) else {
+            return true;
+        };
+
+        if !ids.insert(id.to_owned()) {
+            return false;
+        }
+        match by_content.entry(content) {
+            Entry::Occupied(first) => {
+                aliases.insert(id.to_owned(), String::clone(first.get()));
+                false
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(id.to_owned());
+                true
+            }
+        }
+    });
+
+    if aliases.is_empty() {
+        return;
+    }
+
+    retain_mut(svg, &mut |text| {
+        match text {
+            SvgText::Plain(s) => {
+                if let Some(redirected) = redirect_urls(s, &aliases) {
+                    *s = redirected;
+                }
+            }
+            SvgText::Content(node) => {
+                let redirected = node
+                    .attributes
+                    .iter()
+                    .map(|(_, value)| redirect_urls(value, &aliases))
+                    .collect::<Vec<_>>();
+                if redirected.iter().any(Option::is_some) {
+                    let node = Arc::make_mut(node);
+                    for ((_, value), redirected) in node.attributes.iter_mut().zip(redirected) {
+                        if let Some(redirected) = redirected {
+                            *value = redirected;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    });
+}
+
+/// Parses a definition rendered as a single plain text, returning its id
+/// and its content without the id.
+fn parse_def(s: &str) -> Option<(&str, String)> {
+    let tag = DEF_TAGS.iter().find(|tag| {
+        s.strip_prefix('<')
+            .and_then(|s| s.strip_prefix(**tag))
+            .is_some_and(|s| s.starts_with(" id=\""))
+    })?;
+    if !s.strip_suffix('>')?.ends_with(&format!("</{tag}")) {
+        return None;
+    }
+
+    let id_start = tag.len() + 6;
+    let id_len = s[id_start..].find('"')?;
+    let id = &s[id_start..id_start + id_len];
+    Some((id, format!("{}{}", &s[..id_start], &s[id_start + id_len..])))
+}
+
+/// Redirects the `url(#id)` references, returning `None` if no reference is
+/// redirected.
+fn redirect_urls(s: &str, aliases: &HashMap<String, String>) -> Option<String> {
+    if !s.contains("url(#") {
+        return None;
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    let mut changed = false;
+    while let Some(pos) = rest.find("url(#") {
+        let id_start = pos + 5;
+        let id_len = rest[id_start..].find(')').unwrap_or(rest.len() - id_start);
+        let id = &rest[id_start..id_start + id_len];
+        out.push_str(&rest[..id_start]);
+        match aliases.get(id) {
+            Some(first) => {
+                changed = true;
+                out.push_str(first);
+            }
+            None => out.push_str(id),
+        }
+        rest = &rest[id_start + id_len..];
+    }
+    out.push_str(rest);
+
+    changed.then_some(out)
+}
+
+/// Drops groups without content, except for the ones carrying data for
+/// scripts, e.g. pages and content hints.
+fn drop_empty_groups(svg: &mut Vec<SvgText>) {
+    svg.retain_mut(|text| match text {
+        SvgText::Plain(s) => !s.is_empty(),
+        SvgText::Content(node) => {
+            if !node.content.is_empty() {
+                drop_empty_groups(&mut Arc::make_mut(node).content);
+            }
+
+            !node.content.is_empty()
+                || node.attributes.iter().any(|(key, value)| {
+                    key.starts_with("data-")
+                        || (*key == "class"
+                            && value
+                                .split(' ')
+                                .any(|c| c == "typst-page" || c == "typst-content-hint"))
+                })
+        }
+    });
+}
+
+/// The presentation attributes which are merged into classes.
+const STYLE_ATTRS: &[&str] = &[
+    "fill",
+    "fill-rule",
+    "stroke",
+    "stroke-width",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "stroke-miterlimit",
+    "stroke-dasharray",
+    "stroke-dashoffset",
+];
+
+/// The presentation attributes of an element, sorted by their names.
+type Style = Vec<(String, String)>;
+
+fn style_of<'a>(attrs: impl Iterator<Item = (&'a str, &'a str)>) -> Style {
+    let mut style = attrs
+        .filter(|(key, _)| STYLE_ATTRS.contains(key))
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .collect::<Style>();
+    style.sort();
+    style
+}
+
+/// Parses the attributes of a `<path/>` rendered as a plain text.
+fn parse_path(s: &str) -> Option<Vec<(&str, &str)>> {
+    let mut rest = s.strip_prefix("<path ")?.strip_suffix("/>")?;
+    let mut attrs = vec![];
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Some(attrs);
+        }
+        let (name, value) = rest.split_once("=\"")?;
+        let (value, next) = value.split_once('"')?;
+        if name.contains(|c: char| c.is_whitespace() || c == '"') {
+            return None;
+        }
+        attrs.push((name, value));
+        rest = next;
+    }
+}
+
+/// Merges the presentation attributes shared by elements into classes,
+/// which are defined in a `<style/>` following the `<svg>` tag.
+fn merge_styles(svg: &mut Vec<SvgText>) {
+    let Some(header) = svg
+        .iter()
+        .position(|text| matches!(text, SvgText::Plain(s) if s.starts_with("<svg")))
+    else {
+        return;
+    };
+
+    let mut counts = HashMap::<Style, usize>::new();
+    visit(svg, &mut |text| {
+        let style = match text {
+            SvgText::Plain(s) => match parse_path(s) {
+                Some(attrs) => style_of(attrs.into_iter()),
+                None => return,
+            },
+            SvgText::Content(node) => {
+                style_of(node.attributes.iter().map(|(k, v)| (*k, v.as_str())))
+            }
+        };
+        if !style.is_empty() {
+            *counts.entry(style).or_default() += 1;
+        }
+    });
+
+    let mut classes = HashMap::new();
+    let mut rules = String::new();
+    for (style, count) in counts {
+        let class = format!("s{:x}", reflexo::hash::hash32(&style));
+        let decls = style
+            .iter()
+            .map(|(key, value)| format!("{key}:{value}"))
+            .collect::<Vec<_>>()
+            .join(";");
+        let attrs_len = style
+            .iter()
+            .map(|(key, value)| key.len() + value.len() + 4)
+            .sum::<usize>();
+        let rule_len = class.len() + decls.len() + 3;
+        // keeps the attributes if the class doesn't save space
+        if count * attrs_len <= rule_len + count * (class.len() + 1) {
+            continue;
+        }
+
+        rules.push_str(&format!(".{class}{{{decls}}}"));
+        classes.insert(style, class);
+    }
+
+    if classes.is_empty() {
+        return;
+    }
+
+    retain_mut(svg, &mut |text| {
+        match text {
+            SvgText::Plain(s) => {
+                let Some(attrs) = parse_path(s) else {
+                    return true;
+                };
+                let Some(class) = classes.get(&style_of(attrs.iter().copied())) else {
+                    return true;
+                };
+
+                let mut path = "<path".to_owned();
+                let mut has_class = false;
+                for (key, value) in attrs {
+                    if STYLE_ATTRS.contains(&key) {
+                        continue;
+                    }
+                    if key == "class" {
+                        has_class = true;
+                        path.push_str(&format!(r#" class="{value} {class}""#));
+                    } else {
+                        path.push_str(&format!(r#" {key}="{value}""#));
+                    }
+                }
+                if !has_class {
+                    path.push_str(&format!(r#" class="{class}""#));
+                }
+                path.push_str("/>");
+                *s = path;
+            }
+            SvgText::Content(node) => {
+                let style = style_of(node.attributes.iter().map(|(k, v)| (*k, v.as_str())));
+                let Some(class) = classes.get(&style) else {
+                    return true;
+                };
+
+                let node = Arc::make_mut(node);
+                node.attributes
+                    .retain(|(key, _)| !STYLE_ATTRS.contains(key));
+                match node.attributes.iter_mut().find(|(key, _)| *key == "class") {
+                    Some((_, value)) => {
+                        value.push(' ');
+                        value.push_str(class);
+                    }
+                    None => node.attributes.push(("class", class.clone())),
+                }
+            }
+        }
+        true
+    });
+
+    svg.insert(
+        header + 1,
+        SvgText::Plain(format!(r#"<style type="text/css">{rules}</style>"#)),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(s: &str) -> SvgText {
+        SvgText::Plain(s.to_owned())
+    }
+
+    fn group(attributes: Vec<(&'static str, &str)>, content: Vec<SvgText>) -> SvgText {
+        SvgText::Content(Arc::new(SvgTextNode {
+            attributes: attributes
+                .into_iter()
+                .map(|(k, v)| (k, v.to_owned()))
+                .collect(),
+            content,
+        }))
+    }
+
+    fn render(svg: &[SvgText]) -> String {
+        crate::backend::generate_text(svg.to_vec())
+    }
+
+    fn round(svg: &[&str]) -> Vec<String> {
+        let mut raw = None;
+        let mut svg = svg.iter().map(|s| plain(s)).collect::<Vec<_>>();
+        retain_mut(&mut svg, &mut |text| {
+            round_numbers_in(text, 1, &mut raw);
+            true
+        });
+        svg.iter()
+            .map(|text| render(std::slice::from_ref(text)))
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_number() {
+        fn scan(s: &str) -> Option<&str> {
+            scan_number(s.as_bytes(), 0).map(|end| &s[..end])
+        }
+        assert_eq!(scan("12 3"), Some("12"));
+        assert_eq!(scan("-1.5,2"), Some("-1.5"));
+        assert_eq!(scan("+.5.5"), Some("+.5"));
+        assert_eq!(scan("1."), Some("1."));
+        assert_eq!(scan("1e-3L"), Some("1e-3"));
+        assert_eq!(scan("2E+10"), Some("2E+10"));
+        // an exponent without digits is not a part of the number.
+        assert_eq!(scan("3e"), Some("3"));
+        assert_eq!(scan("4e-z"), Some("4"));
+        assert_eq!(scan("."), None);
+        assert_eq!(scan("-"), None);
+        assert_eq!(scan("-.e"), None);
+        assert_eq!(scan("M"), None);
+        assert_eq!(scan(""), None);
+        assert_eq!(scan_number(b"M 10", 2), Some(4));
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number(1.23456, 2), "1.23");
+        assert_eq!(format_number(1.5, 0), "2");
+        assert_eq!(format_number(1.0, 3), "1");
+        assert_eq!(format_number(10.0, 1), "10");
+        assert_eq!(format_number(0.5, 2), ".5");
+        assert_eq!(format_number(-0.5, 2), "-.5");
+        assert_eq!(format_number(-0.001, 2), "0");
+        assert_eq!(format_number(0.0, 2), "0");
+        assert_eq!(format_number(-12.345, 1), "-12.3");
+        assert_eq!(format_number(1e-3, 3), ".001");
+    }
+
+    #[test]
+    fn test_round_numbers() {
+        assert_eq!(round_numbers("M 1.234 5.678 L 9 10", 1), "M 1.2 5.7 L 9 10");
+        assert_eq!(round_numbers("M0.04,-0.06Z", 1), "M0,-.1Z");
+        // numbers only delimited by their dots are separated after rounding.
+        assert_eq!(round_numbers("M1.55.55", 1), "M1.6 .6");
+        assert_eq!(round_numbers("M1.6-2.6", 0), "M2-3");
+        assert_eq!(round_numbers("1e2 2.6e-1", 1), "100 .3");
+        assert_eq!(round_numbers("M 1 1 Ł", 1), "M 1 1 Ł");
+        assert_eq!(
+            round_translations("translate(1.234, 5.678) scale(1.234)", 1),
+            "translate(1.2, 5.7) scale(1.234)"
+        );
+        assert_eq!(
+            round_translations("matrix(1.234,0,0,1.234,5.678,9.012)", 1),
+            "matrix(1.234,0,0,1.234,5.7,9)"
+        );
+    }
+
+    #[test]
+    fn test_round_numbers_in() {
+        assert_eq!(
+            round(&[r#"<path d="M 1.23 4.56" transform="translate(1.23, 4.56)" fill="1.23"/>"#]),
+            [r#"<path d="M 1.2 4.6" transform="translate(1.2, 4.6)" fill="1.23"/>"#]
+        );
+        assert_eq!(
+            round(&[r#"<clipPath id="c"><path d="M 1.23 0"/></clipPath>"#]),
+            [r#"<clipPath id="c"><path d="M 1.2 0"/></clipPath>"#]
+        );
+        assert_eq!(
+            round(&[r#"<g transform="translate(1.23, 4.56)" d="1.23">"#]),
+            [r#"<g transform="translate(1.2, 4.6)" d="1.23">"#]
+        );
+
+        // attributes of other tags and texts are kept.
+        let kept = [
+            r#"<use d="1.23456" transform="translate(1.23, 4.56)"/>"#,
+            r#"Set d="1.23456" end"#,
+            r#"<text x="1.23 4.56">Set d="1.23456" <path d="1.23"/> end</text>"#,
+            r#"<foreignObject><h5:div d="1.23"><path d="1.23"/></h5:div></foreignObject>"#,
+        ];
+        assert_eq!(round(&kept), kept);
+
+        // the content of styles and scripts continues in the following texts.
+        let kept = [
+            r#"<style type="text/css">"#,
+            r#"path[d="1.23"] {}"#,
+            "</style>",
+            r#"<script type="text/javascript">"#,
+            r#"x = '<path d="1.23"/>';"#,
+            r#"</script><path d="1.23"/>"#,
+        ];
+        let mut rounded = kept.map(str::to_owned);
+        rounded[5] = r#"</script><path d="1.2"/>"#.to_owned();
+        assert_eq!(round(&kept), rounded);
+
+        let mut svg = vec![
+            plain("<script>"),
+            group(vec![("transform", "translate(1.23, 4.56)")], vec![]),
+            plain("</script>"),
+            group(vec![("transform", "translate(1.23, 4.56)")], vec![]),
+        ];
+        let mut raw = None;
+        retain_mut(&mut svg, &mut |text| {
+            round_numbers_in(text, 1, &mut raw);
+            true
+        });
+        assert_eq!(
+            render(&svg),
+            concat!(
+                r#"<script><g transform="translate(1.23, 4.56)"></g></script>"#,
+                r#"<g transform="translate(1.2, 4.6)"></g>"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_def() {
+        assert_eq!(
+            parse_def(r#"<clipPath id="c1"><path d="M 0 0"/></clipPath>"#),
+            Some((
+                "c1",
+                r#"<clipPath id=""><path d="M 0 0"/></clipPath>"#.to_owned()
+            ))
+        );
+        assert_eq!(
+            parse_def(r#"<linearGradient id="g1" x1="0"></linearGradient>"#),
+            Some((
+                "g1",
+                r#"<linearGradient id="" x1="0"></linearGradient>"#.to_owned()
+            ))
+        );
+        // not a definition, or not closed in the same text.
+        assert_eq!(parse_def(r#"<path id="p1" d="M 0 0"/>"#), None);
+        assert_eq!(parse_def(r#"<clipPath id="c1">"#), None);
+        assert_eq!(parse_def(r#"<pattern id="p1"></clipPath>"#), None);
+        assert_eq!(parse_def(r#"<clipPath class="c"></clipPath>"#), None);
+    }
+
+    #[test]
+    fn test_dedup_defs() {
+        let mut svg = vec![
+            plain("<defs>"),
+            plain(r#"<clipPath id="c1"><path d="M 0 0"/></clipPath>"#),
+            plain(r#"<clipPath id="c2"><path d="M 0 0"/></clipPath>"#),
+            plain(r#"<clipPath id="c3"><path d="M 1 1"/></clipPath>"#),
+            plain(r#"<clipPath id="c1"><path d="M 2 2"/></clipPath>"#),
+            plain("</defs>"),
+            group(
+                vec![("clip-path", "url(#c2)")],
+                vec![plain(r#"<path fill="url(#c3)" clip-path="url(#c2)"/>"#)],
+            ),
+        ];
+        dedup_defs(&mut svg);
+        assert_eq!(
+            render(&svg),
+            concat!(
+                "<defs>",
+                r#"<clipPath id="c1"><path d="M 0 0"/></clipPath>"#,
+                r#"<clipPath id="c3"><path d="M 1 1"/></clipPath>"#,
+                "</defs>",
+                r#"<g clip-path="url(#c1)"><path fill="url(#c3)" clip-path="url(#c1)"/></g>"#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_merge_styles() {
+        let path = r#"<path class="p" d="M 0 0" fill="black" stroke="white" stroke-width="1"/>"#;
+        let mut svg = vec![plain("<svg>"), plain(path), plain(path), plain(path)];
+        svg.push(group(
+            vec![
+                ("stroke", "white"),
+                ("fill", "black"),
+                ("stroke-width", "1"),
+            ],
+            vec![plain(r#"<path d="M 1 1" fill="red"/>"#)],
+        ));
+        svg.push(plain("</svg>"));
+        merge_styles(&mut svg);
+
+        let style = vec![
+            ("fill".to_owned(), "black".to_owned()),
+            ("stroke".to_owned(), "white".to_owned()),
+            ("stroke-width".to_owned(), "1".to_owned()),
+        ];
+        let class = format!("s{:x}", reflexo::hash::hash32(&style));
+        let path = format!(r#"<path class="p {class}" d="M 0 0"/>"#);
+        assert_eq!(
+            render(&svg),
+            [
+                "<svg>".to_owned(),
+                format!(
+                    r#"<style type="text/css">.{class}{{fill:black;stroke:white;stroke-width:1}}</style>"#
+                ),
+                path.clone(),
+                path.clone(),
+                path,
+                format!(r#"<g class="{class}"><path d="M 1 1" fill="red"/></g>"#),
+                "</svg>".to_owned(),
+            ]
+            .concat()
+        );
+
+        // a class that doesn't save space is not merged.
+        let mut svg = vec![plain("<svg>"), plain(r#"<path d="M 0 0" fill="red"/>"#)];
+        merge_styles(&mut svg);
+        assert_eq!(render(&svg), r#"<svg><path d="M 0 0" fill="red"/>"#);
+    }
+
+    #[test]
+    fn test_minify_with_options() {
+        let svg = vec![plain(r#"<svg><path d="M 1.23456 0"/></svg>"#)];
+        let (_, report) = minify_with_options(svg.clone(), &SvgExportOptions::default());
+        assert_eq!(report, None);
+
+        let options = SvgExportOptions {
+            numeric_precision: Some(1),
+            ..Default::default()
+        };
+        let (minified, report) = minify_with_options(svg, &options);
+        assert_eq!(render(&minified), r#"<svg><path d="M 1.2 0"/></svg>"#);
+        let report = report.unwrap();
+        assert_eq!(report.before - report.after, 4);
+        assert_eq!(
+            MinifyReport {
+                before: 200,
+                after: 150
+            }
+            .to_string(),
+            "minified svg from 200 to 150 bytes (25.0% smaller)"
+        );
+    }
+}
@@ -1,2 +1,2 @@
 mod minify;
-pub use minify::minify;
+pub use minify::{minify_with_options, MinifyReport};
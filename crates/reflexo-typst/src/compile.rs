@@ -6,12 +6,19 @@ use std::{
     collections::HashSet,
     ops::Deref,
     path::Path,
-    sync::{Arc, OnceLock},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
 };
 
 use reflexo_vfs::notify::UpstreamUpdateEvent;
+use reflexo_world::package::{
+    DownloadStatus, Notifier, PackageError, PackageRegistry, PackageSpec,
+};
 use reflexo_world::{EntryReader, Revising, TaskInputs};
 use tokio::sync::{mpsc, oneshot};
+use typst::diag::eco_format;
 
 use crate::{exporter::GenericExporter, ExportSignal, TypstDocument};
 use crate::{
@@ -22,11 +29,13 @@ use crate::{
     CompileEnv, CompileReport, CompileSnapshot, CompiledArtifact, ConsoleDiagReporter, WorldDeps,
 };
 
-use crate::task::CacheTask;
+use crate::task::{CacheEvictStats, CacheTask};
 
 pub trait CompilationHandle<F: CompilerFeat>: Send + Sync + 'static {
     fn status(&self, revision: usize, rep: CompileReport);
     fn notify_compile(&self, res: &CompiledArtifact<F>, rep: CompileReport);
+    /// Called after the compilation cache is evicted.
+    fn notify_cache_evict(&self, _stats: CacheEvictStats) {}
 }
 
 impl<F: CompilerFeat + Send + Sync + 'static> CompilationHandle<F>
@@ -36,6 +45,46 @@ impl<F: CompilerFeat + Send + Sync + 'static> CompilationHandle<F>
     fn notify_compile(&self, _: &CompiledArtifact<F>, _: CompileReport) {}
 }
 
+/// Reports the status of package downloads to a [`CompilationHandle`] as
+/// [`CompileReport::PackageDownload`] events.
+struct PackageDownloadReporter<F: CompilerFeat> {
+    handle: Arc<dyn CompilationHandle<F>>,
+    /// The revision being compiled.
+    revision: Arc<AtomicUsize>,
+    /// Whether the compiler actor is stopped, which cancels the downloads.
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<F: CompilerFeat + 'static> PackageDownloadReporter<F> {
+    fn report(&self, spec: &PackageSpec, status: DownloadStatus) {
+        let revision = self.revision.load(Ordering::Relaxed);
+        let rep = CompileReport::PackageDownload(spec.clone(), status);
+        self.handle.status(revision, rep);
+    }
+}
+
+impl<F: CompilerFeat + 'static> Notifier for PackageDownloadReporter<F> {
+    fn downloading(&self, spec: &PackageSpec) {
+        self.report(spec, DownloadStatus::Started);
+    }
+
+    fn progress(&self, spec: &PackageSpec, received: u64, total: Option<u64>) {
+        self.report(spec, DownloadStatus::Progress { received, total });
+    }
+
+    fn downloaded(&self, spec: &PackageSpec) {
+        self.report(spec, DownloadStatus::Finished);
+    }
+
+    fn failed(&self, spec: &PackageSpec, error: &PackageError) {
+        self.report(spec, DownloadStatus::Failed(eco_format!("{error}")));
+    }
+
+    fn is_cancelled(&self, _spec: &PackageSpec) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
 pub enum SucceededArtifact<F: CompilerFeat> {
     Compiled(CompiledArtifact<F>),
     Suspend(CompileSnapshot<F>),
@@ -192,6 +241,12 @@ pub struct CompileActor<F: CompilerFeat> {
     compiling: bool,
     suspended_reason: CompileReasons,
     committed_revision: usize,
+    /// The revision being compiled, shared with the package download
+    /// reporter.
+    compiling_revision: Arc<AtomicUsize>,
+    /// Whether the actor is stopped, shared with the package download
+    /// reporter to cancel the downloads.
+    download_cancelled: Arc<AtomicBool>,
 }
 
 impl<F: CompilerFeat + Send + Sync + 'static> CompileActor<F> {
@@ -208,6 +263,16 @@ impl<F: CompilerFeat + Send + Sync + 'static> CompileActor<F> {
     ) -> Self {
         let entry = verse.entry_state();
 
+        let compiling_revision = Arc::new(AtomicUsize::new(0));
+        let download_cancelled = Arc::new(AtomicBool::new(false));
+        verse
+            .registry
+            .set_notifier(Arc::new(PackageDownloadReporter {
+                handle: compile_handle.clone(),
+                revision: compiling_revision.clone(),
+                cancelled: download_cancelled.clone(),
+            }));
+
         Self {
             verse,
 
@@ -233,6 +298,8 @@ impl<F: CompilerFeat + Send + Sync + 'static> CompileActor<F> {
             compiling: false,
             suspended_reason: no_reason(),
             committed_revision: 0,
+            compiling_revision,
+            download_cancelled,
         }
     }
 
@@ -285,6 +352,7 @@ impl<F: CompilerFeat + Send + Sync + 'static> CompileActor<F> {
                 // If settle, stop the actor.
                 if let Interrupt::Settle(e) = event {
                     log::info!("CompileActor: requested stop");
+                    self.download_cancelled.store(true, Ordering::Relaxed);
                     e.send(()).ok();
                     break 'event_loop;
                 }
@@ -386,6 +454,7 @@ impl<F: CompilerFeat + Send + Sync + 'static> CompileActor<F> {
         // todo unwrap main id
         let id = compiling.world.main_id().unwrap();
         let revision = compiling.world.revision().get();
+        self.compiling_revision.store(revision, Ordering::Relaxed);
 
         h.status(revision, CompileReport::Stage(id, "compiling", start));
 
@@ -464,7 +533,9 @@ impl<F: CompilerFeat + Send + Sync + 'static> CompileActor<F> {
         )));
 
         // Trigger an evict task.
-        self.cache.evict();
+        let h = self.compile_handle.clone();
+        self.cache
+            .evict_with(move |stats| h.notify_cache_evict(stats));
     }
 
     /// Process some interrupt. Return whether it needs compilation.
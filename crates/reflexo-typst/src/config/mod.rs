@@ -1,7 +1,10 @@
 pub use reflexo_world::config as compiler;
 pub use reflexo_world::entry;
+pub mod project;
 pub mod workspace;
 
 pub use compiler::CompileFontOpts;
 pub use compiler::CompileOpts;
+pub use compiler::{PackageRegistryOpts, PackageSource};
+pub use project::{ProjectConfig, TargetConfig};
 pub use workspace::WorkspaceConfig;
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// The project configuration, read from [`ProjectConfig::FILE_NAME`] at the
+/// workspace root.
+///
+/// ```toml
+/// [targets.book]
+/// entry = "book/main.typ"
+/// inputs = { edition = "print" }
+/// formats = ["pdf"]
+/// output = "dist/book"
+/// font-paths = ["assets/fonts"]
+/// creation-timestamp = 1704067200
+/// ```
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProjectConfig {
+    /// The named compilation targets.
+    #[serde(default)]
+    pub targets: BTreeMap<String, TargetConfig>,
+}
+
+impl ProjectConfig {
+    /// The file name of the project configuration.
+    pub const FILE_NAME: &'static str = "typst-ts.toml";
+}
+
+/// A named compilation target in the project configuration.
+///
+/// Paths are relative to the workspace root.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct TargetConfig {
+    /// Path to the entry file.
+    pub entry: String,
+
+    /// String key-value pairs visible through `sys.inputs`.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+
+    /// Output formats, e.g. `pdf` and `svg`.
+    #[serde(default)]
+    pub formats: Vec<String>,
+
+    /// Output directory.
+    pub output: Option<String>,
+
+    /// Additional directories to search for fonts.
+    #[serde(default)]
+    pub font_paths: Vec<String>,
+
+    /// The document's creation date formatted as a UNIX timestamp.
+    pub creation_timestamp: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_config() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+[targets.book]
+entry = "book/main.typ"
+inputs = { edition = "print" }
+formats = ["pdf"]
+output = "dist/book"
+font-paths = ["assets/fonts"]
+creation-timestamp = 1704067200
+
+[targets.web]
+entry = "web.typ"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.targets.keys().collect::<Vec<_>>(), ["book", "web"]);
+        assert_eq!(
+            config.targets["book"],
+            TargetConfig {
+                entry: "book/main.typ".into(),
+                inputs: [("edition".into(), "print".into())].into(),
+                formats: vec!["pdf".into()],
+                output: Some("dist/book".into()),
+                font_paths: vec!["assets/fonts".into()],
+                creation_timestamp: Some(1704067200),
+            }
+        );
+        assert_eq!(
+            config.targets["web"],
+            TargetConfig {
+                entry: "web.typ".into(),
+                ..TargetConfig::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_project_config() {
+        let parse = |config: &str| toml::from_str::<ProjectConfig>(config);
+
+        assert_eq!(parse("").unwrap(), ProjectConfig::default());
+        // the entry is required
+        assert!(parse("[targets.book]\noutput = \"dist\"").is_err());
+        // misspelled fields are rejected
+        assert!(parse("[targets.book]\nentry = \"main.typ\"\nformat = [\"pdf\"]").is_err());
+        assert!(parse("[target.book]\nentry = \"main.typ\"").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::io::IsTerminal;
+use std::io::{IsTerminal, Write};
 use std::sync::Arc;
 
 use codespan_reporting::files::Files;
@@ -23,7 +23,7 @@ use crate::features::{
 use crate::CompileReport;
 use crate::{typst::prelude::*, GenericExporter, PhantomParamData, TakeAs, TypstFileId};
 
-use super::DiagnosticFormat;
+use super::{DiagnosticFormat, DiagnosticRecord};
 
 /// Get stderr with color support if desirable.
 fn color_stream() -> StandardStream {
@@ -43,6 +43,7 @@ fn print_diagnostics<'files, W: World + Files<'files, FileId = TypstFileId>>(
     let mut w = match diagnostic_format {
         DiagnosticFormat::Human => color_stream(),
         DiagnosticFormat::Short => StandardStream::stderr(ColorChoice::Never),
+        DiagnosticFormat::Json => return print_json_diagnostics(world, errors),
     };
 
     let mut config = term::Config {
@@ -84,6 +85,22 @@ fn print_diagnostics<'files, W: World + Files<'files, FileId = TypstFileId>>(
     Ok(())
 }
 
+/// Print diagnostic messages to the terminal as line-delimited JSON.
+fn print_json_diagnostics<'files, W: World + Files<'files, FileId = TypstFileId>>(
+    world: &'files W,
+    errors: EcoVec<SourceDiagnostic>,
+) -> Result<(), codespan_reporting::files::Error> {
+    let mut w = std::io::stderr().lock();
+
+    for diagnostic in errors {
+        let record = DiagnosticRecord::new(world, &diagnostic);
+        serde_json::to_writer(&mut w, &record).map_err(std::io::Error::from)?;
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
 /// Create a label for a span.
 fn label<'files, W: World + Files<'files, FileId = TypstFileId>>(
     world: &'files W,
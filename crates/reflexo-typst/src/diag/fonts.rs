@@ -0,0 +1,433 @@
+use std::collections::HashSet;
+
+use typst::diag::{eco_format, SourceDiagnostic};
+use typst::layout::{Frame, FrameItem};
+use typst::model::Document;
+use typst::syntax::ast::{self, AstNode};
+use typst::syntax::package::{PackageManifest, PackageSpec};
+use typst::syntax::{FileId, LinkedNode, Source, SyntaxKind, VirtualPath};
+use typst::text::FontBook;
+use typst::World;
+
+/// Analyzes a compiled document for fonts that silently fall back, reporting
+/// warnings for
+/// - each font family given to `text` in the sources that is not in the font
+///   book, and
+/// - each character which no font covers, so that it is rendered as the
+///   missing glyph (`.notdef`) of a font.
+///
+/// Font families are found by walking the syntax trees of the main file and
+/// the files and packages it imports or includes by string literals. Only
+/// string literals passed as `font` to a call or a set rule of `text` are
+/// checked, so families given by variables or computed at runtime, or passed
+/// through `text.with(font: ..)`, are not checked.
+pub fn font_diagnostics(world: &dyn World, doc: &Document) -> Vec<SourceDiagnostic> {
+    let mut diags = vec![];
+    FamilyChecker::new(world, &mut diags).check(world.main());
+
+    let mut seen = HashSet::new();
+    for page in &doc.pages {
+        check_glyphs(&page.frame, &mut seen, &mut diags);
+    }
+    diags
+}
+
+struct FamilyChecker<'a> {
+    world: &'a dyn World,
+    book: &'a FontBook,
+    visited: HashSet<FileId>,
+    queue: Vec<FileId>,
+    diags: &'a mut Vec<SourceDiagnostic>,
+}
+
+impl<'a> FamilyChecker<'a> {
+    fn new(world: &'a dyn World, diags: &'a mut Vec<SourceDiagnostic>) -> Self {
+        Self {
+            world,
+            book: world.book(),
+            visited: HashSet::new(),
+            queue: vec![],
+            diags,
+        }
+    }
+
+    fn check(mut self, main: Source) {
+        self.visited.insert(main.id());
+        self.check_node(main.id(), &LinkedNode::new(main.root()));
+
+        while let Some(id) = self.queue.pop() {
+            if let Ok(source) = self.world.source(id) {
+                self.check_node(id, &LinkedNode::new(source.root()));
+            }
+        }
+    }
+
+    fn check_node(&mut self, id: FileId, node: &LinkedNode) {
+        match node.kind() {
+            SyntaxKind::ModuleImport => {
+                let import = node.cast::<ast::ModuleImport>().unwrap();
+                self.visit_path(id, import.source());
+            }
+            SyntaxKind::ModuleInclude => {
+                let include = node.cast::<ast::ModuleInclude>().unwrap();
+                self.visit_path(id, include.source());
+            }
+            SyntaxKind::Named => self.check_named(node),
+            _ => {}
+        }
+
+        for child in node.children() {
+            self.check_node(id, &child);
+        }
+    }
+
+    /// Queues the file imported or included by a string literal.
+    fn visit_path(&mut self, id: FileId, source: ast::Expr) {
+        let ast::Expr::Str(path) = source else {
+            return;
+        };
+        let path = path.get();
+
+        let target = if path.starts_with('@') {
+            let Ok(spec) = path.parse::<PackageSpec>() else {
+                return;
+            };
+            match self.package_entry(spec) {
+                Some(target) => target,
+                None => return,
+            }
+        } else {
+            id.join(&path)
+        };
+
+        if self.visited.insert(target) {
+            self.queue.push(target);
+        }
+    }
+
+    fn package_entry(&self, spec: PackageSpec) -> Option<FileId> {
+        let manifest_id = FileId::new(Some(spec), VirtualPath::new("typst.toml"));
+        let manifest = self.world.file(manifest_id).ok()?;
+        let manifest = std::str::from_utf8(&manifest).ok()?;
+        let manifest: PackageManifest = toml::from_str(manifest).ok()?;
+        Some(manifest_id.join(&manifest.package.entrypoint))
+    }
+
+    /// Checks the `font` argument of `text`, e.g. `set text(font: "Foo")`.
+    fn check_named(&mut self, node: &LinkedNode) {
+        let named = node.cast::<ast::Named>().unwrap();
+        if named.name().as_str() != "font" || !is_text_call(node) {
+            return;
+        }
+
+        let families = match named.expr() {
+            ast::Expr::Str(family) => vec![family],
+            ast::Expr::Array(families) => families
+                .items()
+                .filter_map(|item| match item {
+                    ast::ArrayItem::Pos(ast::Expr::Str(family)) => Some(family),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        for family in families {
+            let name = family.get();
+            if self
+                .book
+                .select_family(&name.to_lowercase())
+                .next()
+                .is_none()
+            {
+                self.diags.push(
+                    SourceDiagnostic::warning(
+                        family.span(),
+                        eco_format!("unknown font family: {name}"),
+                    )
+                    .with_hint("the text falls back to other fonts"),
+                );
+            }
+        }
+    }
+}
+
+/// Whether the named argument is passed to `text`, by a call or a set rule.
+fn is_text_call(named: &LinkedNode) -> bool {
+    let Some(args) = named.parent().filter(|n| n.kind() == SyntaxKind::Args) else {
+        return false;
+    };
+    let callee = match args.parent() {
+        Some(call) if call.kind() == SyntaxKind::FuncCall => {
+            call.cast::<ast::FuncCall>().unwrap().callee()
+        }
+        Some(rule) if rule.kind() == SyntaxKind::SetRule => {
+            rule.cast::<ast::SetRule>().unwrap().target()
+        }
+        _ => return false,
+    };
+
+    match callee {
+        ast::Expr::Ident(ident) => ident.as_str() == "text",
+        ast::Expr::FieldAccess(access) => access.field().as_str() == "text",
+        _ => false,
+    }
+}
+
+/// Reports the characters rendered as missing glyphs, once per character.
+fn check_glyphs(frame: &Frame, seen: &mut HashSet<char>, diags: &mut Vec<SourceDiagnostic>) {
+    for (_, item) in frame.items() {
+        let text = match item {
+            FrameItem::Group(group) => {
+                check_glyphs(&group.frame, seen, diags);
+                continue;
+            }
+            FrameItem::Text(text) => text,
+            _ => continue,
+        };
+
+        for glyph in text.glyphs.iter().filter(|glyph| glyph.id == 0) {
+            let chars = text.text.get(glyph.range()).unwrap_or_default().chars();
+            for c in chars.filter(|c| !c.is_whitespace() && !c.is_control()) {
+                if !seen.insert(c) {
+                    continue;
+                }
+
+                let message = eco_format!("no font covers character {c:?} (U+{:04X})", c as u32);
+                diags.push(SourceDiagnostic::warning(glyph.span.0, message).with_hint(
+                    eco_format!(
+                        "it is rendered as the missing glyph of font {}",
+                        text.font.info().family
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::collections::HashMap;
+
+    use codespan_reporting::files::{Error as CodespanError, Files};
+    use comemo::Prehashed;
+    use reflexo::test_utils::{test_font, triangle};
+    use typst::diag::{FileError, FileResult};
+    use typst::eval::Tracer;
+    use typst::foundations::{Bytes, Datetime};
+    use typst::text::Font;
+    use typst::Library;
+
+    use super::*;
+
+    pub(crate) struct TestWorld {
+        library: Prehashed<Library>,
+        book: Prehashed<FontBook>,
+        fonts: Vec<Font>,
+        files: HashMap<FileId, Source>,
+        main: FileId,
+    }
+
+    impl TestWorld {
+        /// Creates a world of the given files, whose first file is the main
+        /// file. The files of packages are given like `@ns/name:0.1.0/path`.
+        pub(crate) fn new(files: &[(&str, &str)]) -> Self {
+            let fonts =
+                Font::iter(Bytes::from(test_font(&vec![triangle(100); 5]))).collect::<Vec<_>>();
+            assert_eq!(fonts.len(), 1);
+
+            let file_id = |path: &str| match path.strip_prefix('@') {
+                Some(path) => {
+                    let mut parts = path.splitn(3, '/');
+                    let (ns, name, path) = (parts.next(), parts.next(), parts.next());
+                    let spec = format!("@{}/{}", ns.unwrap(), name.unwrap());
+                    FileId::new(Some(spec.parse().unwrap()), VirtualPath::new(path.unwrap()))
+                }
+                None => FileId::new(None, VirtualPath::new(path)),
+            };
+
+            Self {
+                library: Prehashed::new(Library::default()),
+                book: Prehashed::new(FontBook::from_fonts(&fonts)),
+                fonts,
+                main: file_id(files[0].0),
+                files: files
+                    .iter()
+                    .map(|(path, text)| {
+                        let id = file_id(path);
+                        (id, Source::new(id, text.to_string()))
+                    })
+                    .collect(),
+            }
+        }
+
+        fn diagnostics(&self, doc: &Document) -> Vec<String> {
+            let diags = font_diagnostics(self, doc);
+            diags.into_iter().map(|diag| diag.message.into()).collect()
+        }
+
+        /// Checks the font families without compiling.
+        fn family_diagnostics(&self) -> Vec<String> {
+            self.diagnostics(&Document::default())
+        }
+    }
+
+    impl World for TestWorld {
+        fn library(&self) -> &Prehashed<Library> {
+            &self.library
+        }
+
+        fn book(&self) -> &Prehashed<FontBook> {
+            &self.book
+        }
+
+        fn main(&self) -> Source {
+            self.files[&self.main].clone()
+        }
+
+        fn source(&self, id: FileId) -> FileResult<Source> {
+            let path = id.vpath().as_rootless_path();
+            self.files
+                .get(&id)
+                .cloned()
+                .ok_or_else(|| FileError::NotFound(path.into()))
+        }
+
+        fn file(&self, id: FileId) -> FileResult<Bytes> {
+            let source = World::source(self, id)?;
+            Ok(Bytes::from(source.text().as_bytes().to_vec()))
+        }
+
+        fn font(&self, index: usize) -> Option<Font> {
+            self.fonts.get(index).cloned()
+        }
+
+        fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
+            None
+        }
+    }
+
+    impl<'a> Files<'a> for TestWorld {
+        type FileId = FileId;
+        type Name = String;
+        type Source = Source;
+
+        fn name(&'a self, id: FileId) -> Result<String, CodespanError> {
+            let path = id.vpath().as_rooted_path().display();
+            Ok(match id.package() {
+                Some(package) => format!("{package}{path}"),
+                None => id.vpath().as_rootless_path().display().to_string(),
+            })
+        }
+
+        fn source(&'a self, id: FileId) -> Result<Source, CodespanError> {
+            self.files
+                .get(&id)
+                .cloned()
+                .ok_or(CodespanError::FileMissing)
+        }
+
+        fn line_index(&'a self, id: FileId, given: usize) -> Result<usize, CodespanError> {
+            let source = Files::source(self, id)?;
+            source
+                .byte_to_line(given)
+                .ok_or_else(|| CodespanError::IndexTooLarge {
+                    given,
+                    max: source.len_bytes(),
+                })
+        }
+
+        fn line_range(
+            &'a self,
+            id: FileId,
+            given: usize,
+        ) -> Result<std::ops::Range<usize>, CodespanError> {
+            let source = Files::source(self, id)?;
+            source
+                .line_to_range(given)
+                .ok_or_else(|| CodespanError::LineTooLarge {
+                    given,
+                    max: source.len_lines(),
+                })
+        }
+    }
+
+    #[test]
+    fn test_set_rule() {
+        let world = TestWorld::new(&[(
+            "main.typ",
+            "#set text(font: \"Test\")\n#set text(font: \"Missing\")\n#set par(font: \"Other\")",
+        )]);
+        assert_eq!(world.family_diagnostics(), ["unknown font family: Missing"]);
+    }
+
+    #[test]
+    fn test_call_with_array() {
+        let world = TestWorld::new(&[(
+            "main.typ",
+            "#text(font: (\"test\", \"Missing\", \"Other\"))[A]\n#std.text(font: \"Third\")[B]",
+        )]);
+        assert_eq!(
+            world.family_diagnostics(),
+            [
+                "unknown font family: Missing",
+                "unknown font family: Other",
+                "unknown font family: Third",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unchecked_families() {
+        let world = TestWorld::new(&[(
+            "main.typ",
+            "#let font = \"Missing\"\n#set text(font: font)\n#let t = text.with(font: \"Other\")",
+        )]);
+        assert_eq!(world.family_diagnostics(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_imports() {
+        let world = TestWorld::new(&[
+            (
+                "main.typ",
+                "#import \"@preview/unknown:0.1.0\": *\n#import \"@preview/known:0.1.0\": *\n#include \"chapter.typ\"",
+            ),
+            ("chapter.typ", "#text(font: \"Chapter\")[A]"),
+            (
+                "@preview/known:0.1.0/typst.toml",
+                "[package]\nname = \"known\"\nversion = \"0.1.0\"\nentrypoint = \"src/lib.typ\"",
+            ),
+            ("@preview/known:0.1.0/src/lib.typ", "#import \"util.typ\"\n#let f = text(font: \"Lib\")[A]"),
+            ("@preview/known:0.1.0/src/util.typ", "#let g = text(font: \"Util\")[A]"),
+        ]);
+
+        let mut diags = world.family_diagnostics();
+        diags.sort();
+        assert_eq!(
+            diags,
+            [
+                "unknown font family: Chapter",
+                "unknown font family: Lib",
+                "unknown font family: Util",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_notdef() {
+        let world = TestWorld::new(&[(
+            "main.typ",
+            "#set text(font: \"Test\")\nAB\u{20AC}CD\u{20AC}\u{3B1}",
+        )]);
+
+        let doc = typst::compile(&world, &mut Tracer::new()).unwrap();
+        assert_eq!(
+            world.diagnostics(&doc),
+            [
+                "no font covers character '\u{20AC}' (U+20AC)",
+                "no font covers character '\u{3B1}' (U+03B1)",
+            ]
+        );
+    }
+}
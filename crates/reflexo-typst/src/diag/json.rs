@@ -0,0 +1,183 @@
+use std::ops::Range;
+
+use codespan_reporting::files::Files;
+use serde::Serialize;
+use typst::diag::{Severity, SourceDiagnostic};
+use typst::syntax::Span;
+use typst::{World, WorldExt};
+
+use crate::TypstFileId;
+
+/// A machine-readable diagnostic, serialized as one line of JSON by the
+/// [`DiagnosticFormat::Json`] format.
+///
+/// [`DiagnosticFormat::Json`]: super::DiagnosticFormat::Json
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticRecord {
+    /// Either `error` or `warning`.
+    pub severity: &'static str,
+    /// The diagnostic message.
+    pub message: String,
+    /// The location of the diagnostic, if it is attached to a source file.
+    #[serde(flatten)]
+    pub location: Option<DiagnosticLocation>,
+    /// Additional hints to the user.
+    pub hints: Vec<String>,
+    /// The stacktrace-like chain of the diagnostic, innermost first.
+    pub trace: Vec<TraceRecord>,
+}
+
+/// A point in the trace of a [`DiagnosticRecord`].
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceRecord {
+    /// The description of the trace point.
+    pub message: String,
+    /// The location of the trace point.
+    #[serde(flatten)]
+    pub location: Option<DiagnosticLocation>,
+}
+
+/// The location of a span in a source file.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticLocation {
+    /// The user-facing path of the file.
+    pub path: String,
+    /// The byte range of the span in the file.
+    pub range: Range<usize>,
+    /// The 1-based line number of the start of the span.
+    pub line: usize,
+    /// The 1-based column number (in characters) of the start of the span.
+    pub column: usize,
+}
+
+impl DiagnosticRecord {
+    /// Convert a typst diagnostic to a record, resolving spans by the world.
+    pub fn new<'files, W: World + Files<'files, FileId = TypstFileId>>(
+        world: &'files W,
+        diagnostic: &SourceDiagnostic,
+    ) -> Self {
+        Self {
+            severity: match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            message: diagnostic.message.to_string(),
+            location: DiagnosticLocation::new(world, diagnostic.span),
+            hints: diagnostic.hints.iter().map(|e| e.to_string()).collect(),
+            trace: diagnostic
+                .trace
+                .iter()
+                .map(|point| TraceRecord {
+                    message: point.v.to_string(),
+                    location: DiagnosticLocation::new(world, point.span),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl DiagnosticLocation {
+    /// Resolve the location of a span, returning `None` for detached spans.
+    pub fn new<'files, W: World + Files<'files, FileId = TypstFileId>>(
+        world: &'files W,
+        span: Span,
+    ) -> Option<Self> {
+        let id = span.id()?;
+        let range = world.range(span)?;
+
+        let path = Files::name(world, id).ok()?.to_string();
+        let line_index = Files::line_index(world, id, range.start).ok()?;
+        let line_start = Files::line_range(world, id, line_index).ok()?.start;
+        let source = Files::source(world, id).ok()?;
+        let column = source
+            .as_ref()
+            .get(line_start..range.start)
+            .map_or(0, |prefix| prefix.chars().count());
+
+        Some(Self {
+            path,
+            range,
+            line: line_index + 1,
+            column: column + 1,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::diag::Tracepoint;
+    use typst::eval::Tracer;
+    use typst::syntax::{LinkedNode, Spanned};
+
+    use super::*;
+    use crate::diag::TestWorld;
+
+    /// Gets the span of the innermost node at the text in the main file.
+    fn span_of(world: &TestWorld, text: &str) -> Span {
+        let source = world.main();
+        let offset = source.text().find(text).unwrap();
+        let root = LinkedNode::new(source.root());
+        root.leaf_at(offset + 1).unwrap().span()
+    }
+
+    #[test]
+    fn test_compile_error() {
+        let world = TestWorld::new(&[("main.typ", "#let f(x) = x + \"a\"\n#f(1)")]);
+        let errors = typst::compile(&world, &mut Tracer::new()).unwrap_err();
+        assert_eq!(errors.len(), 1);
+
+        let record = DiagnosticRecord::new(&world, &errors[0]);
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "severity": "error",
+                "message": "cannot add integer and string",
+                "path": "main.typ",
+                "range": { "start": 12, "end": 19 },
+                "line": 1,
+                "column": 13,
+                "hints": [],
+                "trace": [{
+                    "message": "error occurred in this call of function `f`",
+                    "path": "main.typ",
+                    "range": { "start": 21, "end": 25 },
+                    "line": 2,
+                    "column": 2,
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_hints_and_detached_span() {
+        let world = TestWorld::new(&[("main.typ", "= Title\n\u{3B1}\u{3B2} #image(\"x.png\")")]);
+
+        // the range is in bytes, while the column is in characters.
+        let mut warning = SourceDiagnostic::warning(span_of(&world, "image"), "unknown image")
+            .with_hint("check the path")
+            .with_hint("or the root");
+        warning.trace.push(Spanned::new(
+            Tracepoint::Show("heading".into()),
+            Span::detached(),
+        ));
+        let record = DiagnosticRecord::new(&world, &warning);
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            concat!(
+                r#"{"severity":"warning","message":"unknown image","path":"main.typ","#,
+                r#""range":{"start":14,"end":19},"line":2,"column":5,"#,
+                r#""hints":["check the path","or the root"],"#,
+                r#""trace":[{"message":"error occurred while applying show rule to this heading"}]}"#,
+            )
+        );
+
+        // a detached span is not located.
+        let error = SourceDiagnostic::error(Span::detached(), "failed to load font");
+        let record = DiagnosticRecord::new(&world, &error);
+        assert!(record.location.is_none());
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"severity":"error","message":"failed to load font","hints":[],"trace":[]}"#
+        );
+    }
+}
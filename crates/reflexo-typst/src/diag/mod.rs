@@ -4,11 +4,21 @@ mod console;
 #[cfg(feature = "system-compile")]
 pub use console::*;
 
+mod json;
+pub use json::*;
+
+mod fonts;
+#[cfg(test)]
+pub(crate) use fonts::tests::TestWorld;
+pub use fonts::*;
+
 /// Which format to use for diagnostics.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub enum DiagnosticFormat {
     Human,
     Short,
+    /// Line-delimited JSON, one [`DiagnosticRecord`] per line.
+    Json,
 }
 
 impl Default for DiagnosticFormat {
@@ -5,11 +5,20 @@ use typst::{diag::SourceResult, World};
 #[cfg(feature = "ast")]
 pub mod ast;
 
+pub mod deps;
+
+pub mod glyph_pack;
+
 pub mod json;
 
+pub mod pages;
+
 #[cfg(feature = "pdf")]
 pub mod pdf;
 
+#[cfg(feature = "png")]
+pub mod png;
+
 #[cfg(feature = "svg")]
 pub mod svg;
 
@@ -114,7 +123,10 @@ where
 pub mod builtins {
     use std::{fs::File, sync::Arc};
 
-    use crate::{exporter_utils::map_err, AsOwnedBytes, AsOwnedString, AsWritable, Transformer};
+    use crate::{
+        exporter_utils::map_err, AsOwnedBytes, AsOwnedString, AsWritable, PageSelection,
+        Transformer, TypstDocument,
+    };
 
     use super::{utils, DynExporter, Exporter};
     use ecow::EcoVec;
@@ -164,6 +176,29 @@ pub mod builtins {
         }
     }
 
+    /// Records a timing scope named `name` around the inner exporter, see
+    /// [`crate::profile`].
+    pub struct TimedExporter<E> {
+        name: &'static str,
+        exporter: E,
+    }
+
+    impl<E> TimedExporter<E> {
+        pub fn new(name: &'static str, exporter: E) -> Self {
+            Self { name, exporter }
+        }
+    }
+
+    impl<I, O, E> Exporter<I, O> for TimedExporter<E>
+    where
+        E: Exporter<I, O>,
+    {
+        fn export(&self, world: &dyn World, output: Arc<I>) -> SourceResult<O> {
+            let _scope = typst_timing::TimingScope::new(self.name, None);
+            self.exporter.export(world, output)
+        }
+    }
+
     /// The Exporter<From<&Input>> must be explicitly constructed.
     pub struct FromExporter<Input, AsInput> {
         exporter: GroupExporter<AsInput>,
@@ -216,6 +251,68 @@ pub mod builtins {
         }
     }
 
+    /// Writes each page produced by the inner exporter to a separate file.
+    ///
+    /// Given the path `dir/main.png`, the pages are written to `dir/main-1.png`,
+    /// `dir/main-2.png`, and so on. If only some pages are selected, they are
+    /// still named by their page numbers in the whole document.
+    pub struct FsPagedPathExporter<E> {
+        path: std::path::PathBuf,
+        pages: Option<PageSelection>,
+        exporter: E,
+    }
+
+    impl<E> FsPagedPathExporter<E> {
+        pub fn new(path: std::path::PathBuf, exporter: E) -> Self {
+            Self {
+                path,
+                pages: None,
+                exporter,
+            }
+        }
+
+        /// Exports only the selected pages.
+        pub fn with_pages(mut self, pages: Option<PageSelection>) -> Self {
+            self.pages = pages;
+            self
+        }
+
+        /// Gets the output path of the page numbered `n`, starting from 1.
+        pub fn page_path(&self, n: usize) -> std::path::PathBuf {
+            let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+            let file_name = match self.path.extension() {
+                Some(ext) => format!("{stem}-{n}.{}", ext.to_string_lossy()),
+                None => format!("{stem}-{n}"),
+            };
+            self.path.with_file_name(file_name)
+        }
+
+        /// Gets the output paths of the pages exported from a document.
+        pub fn page_paths(&self, doc: &TypstDocument) -> SourceResult<Vec<std::path::PathBuf>> {
+            let count = doc.pages.len();
+            let numbers = match &self.pages {
+                Some(pages) => pages.indices(count).map_err(map_err)?,
+                None => (0..count).collect(),
+            };
+            Ok(numbers.into_iter().map(|i| self.page_path(i + 1)).collect())
+        }
+    }
+
+    impl<E> Exporter<TypstDocument> for FsPagedPathExporter<E>
+    where
+        E: Exporter<TypstDocument, Vec<Vec<u8>>>,
+    {
+        fn export(&self, world: &dyn World, output: Arc<TypstDocument>) -> SourceResult<()> {
+            let (doc, numbers) =
+                PageSelection::select_or_all(self.pages.as_ref(), output).map_err(map_err)?;
+            let pages = self.exporter.export(world, doc)?;
+            for (n, page) in numbers.into_iter().zip(pages.iter()) {
+                std::fs::write(self.page_path(n), page).map_err(map_err)?;
+            }
+            Ok(())
+        }
+    }
+
     impl<I, Bytes, E> Exporter<I> for FsPathExporter<Bytes, E>
     where
         E: Exporter<I, Bytes>,
@@ -0,0 +1,329 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use reflexo::debug_loc::DataSource;
+use reflexo_world::font::{FontResolver, FontResolverImpl};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use typst::diag::SourceResult;
+use typst::model::Document as TypstDocument;
+use typst::World;
+
+use crate::exporter_builtins::FsPagedPathExporter;
+use crate::exporter_utils::map_err;
+use crate::{CompiledArtifact, CompilerFeat, Exporter, PageSelection};
+
+/// The files accessed during a compilation.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DepsManifest {
+    /// The output files depending on the listed files.
+    pub targets: Vec<PathBuf>,
+    /// The source files and other files (images, data, etc.) read by the
+    /// compilation, including files from packages.
+    pub files: Vec<DepsRecord>,
+    /// The font files loaded by the compilation.
+    pub fonts: Vec<DepsRecord>,
+    /// The packages used by the compilation, e.g. `@preview/example:0.1.0`.
+    pub packages: Vec<String>,
+}
+
+/// An output of a compilation, which is a target of the depfile.
+#[derive(Debug, Clone)]
+pub enum DepsTarget {
+    /// A single output file.
+    File(PathBuf),
+    /// The output files written per page, named after the path like
+    /// [`FsPagedPathExporter`] does.
+    Pages(PathBuf, Option<PageSelection>),
+}
+
+/// A file accessed during a compilation.
+#[derive(Debug, Clone, Serialize)]
+pub struct DepsRecord {
+    pub path: PathBuf,
+    /// The package containing the file, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+    /// The SHA-256 hash of the content in the form of `sha256:<hex>`.
+    pub hash: String,
+}
+
+impl DepsManifest {
+    /// Collects the files accessed by the world of a compiled artifact.
+    ///
+    /// The world is expected to be a snapshot of a single compilation, so
+    /// that it only holds the files and fonts accessed by that compilation.
+    /// Files which fail to read are not listed.
+    pub fn new<F>(artifact: &CompiledArtifact<F>, targets: Vec<PathBuf>) -> Self
+    where
+        F: CompilerFeat<FontResolver = FontResolverImpl>,
+    {
+        let world = artifact.world.as_ref();
+
+        // Collects ids first, since reading files locks the slots again.
+        let ids = world
+            .source_db
+            .slots
+            .lock()
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+
+        let mut files = BTreeMap::new();
+        let mut packages = BTreeSet::new();
+        for id in ids {
+            let Ok(path) = world.path_for_id(id) else {
+                continue;
+            };
+            let Ok(content) = world.file(id) else {
+                continue;
+            };
+            let package = id.package().map(ToString::to_string);
+            packages.extend(package.clone());
+
+            files.insert(
+                path.clone(),
+                DepsRecord {
+                    path,
+                    package,
+                    hash: content_hash(&content),
+                },
+            );
+        }
+
+        let resolver = world.font_resolver.as_ref();
+        let mut fonts = BTreeMap::new();
+        for idx in world.accessed_fonts() {
+            let Some(DataSource::Fs(source)) =
+                resolver.describe_font_by_id(idx).as_deref().cloned()
+            else {
+                // Embedded and in-memory fonts have no file to depend on.
+                continue;
+            };
+            let Some(font) = resolver.font(idx) else {
+                continue;
+            };
+            let path = PathBuf::from(source.path);
+            fonts.entry(path.clone()).or_insert_with(|| DepsRecord {
+                path,
+                package: None,
+                hash: content_hash(font.data()),
+            });
+        }
+
+        Self {
+            targets,
+            files: files.into_values().collect(),
+            fonts: fonts.into_values().collect(),
+            packages: packages.into_iter().collect(),
+        }
+    }
+
+    /// Renders a Makefile-style depfile, in which the targets depend on all
+    /// the accessed files.
+    pub fn to_depfile(&self) -> String {
+        let targets = self.targets.iter().map(|p| escape_depfile_path(p));
+        let deps = self.files.iter().chain(self.fonts.iter());
+        let deps = deps.map(|r| escape_depfile_path(&r.path));
+
+        let mut depfile = targets.collect::<Vec<_>>().join(" ");
+        depfile.push(':');
+        for dep in deps {
+            depfile.push_str(" \\\n  ");
+            depfile.push_str(&dep);
+        }
+        depfile.push('\n');
+        depfile
+    }
+}
+
+/// Writes a Makefile-style depfile and a JSON manifest of the files accessed
+/// during a compilation.
+pub struct DepsExporter {
+    targets: Vec<DepsTarget>,
+    depfile: Option<PathBuf>,
+    manifest: Option<PathBuf>,
+}
+
+impl DepsExporter {
+    /// Creates an exporter for the given output files, which are the targets
+    /// of the depfile.
+    pub fn new(targets: Vec<DepsTarget>) -> Self {
+        Self {
+            targets,
+            depfile: None,
+            manifest: None,
+        }
+    }
+
+    /// Writes the depfile to the given path.
+    pub fn with_depfile(mut self, path: PathBuf) -> Self {
+        self.depfile = Some(path);
+        self
+    }
+
+    /// Writes the JSON manifest to the given path.
+    pub fn with_manifest(mut self, path: PathBuf) -> Self {
+        self.manifest = Some(path);
+        self
+    }
+
+    /// Expands the targets into the output files of the document.
+    ///
+    /// Page outputs are only known with the document, and are skipped
+    /// without one.
+    fn target_paths(&self, doc: Option<&TypstDocument>) -> SourceResult<Vec<PathBuf>> {
+        let mut targets = vec![];
+        for target in &self.targets {
+            match (target, doc) {
+                (DepsTarget::File(path), _) => targets.push(path.clone()),
+                (DepsTarget::Pages(path, pages), Some(doc)) => {
+                    let exporter = FsPagedPathExporter::new(path.clone(), ());
+                    targets.extend(exporter.with_pages(pages.clone()).page_paths(doc)?);
+                }
+                (DepsTarget::Pages(..), None) => {}
+            }
+        }
+        // Without outputs, the depfile itself is the target.
+        if targets.is_empty() {
+            targets.extend(self.depfile.iter().cloned());
+        }
+        Ok(targets)
+    }
+}
+
+impl<F> Exporter<CompiledArtifact<F>> for DepsExporter
+where
+    F: CompilerFeat<FontResolver = FontResolverImpl>,
+{
+    fn export(&self, _world: &dyn World, output: Arc<CompiledArtifact<F>>) -> SourceResult<()> {
+        // A failed compilation keeps the pages of the last successful one.
+        let doc = output.success_doc();
+        let targets = self.target_paths(doc.as_deref())?;
+        let manifest = DepsManifest::new(&output, targets);
+
+        if let Some(path) = &self.depfile {
+            std::fs::write(path, manifest.to_depfile()).map_err(map_err)?;
+        }
+        if let Some(path) = &self.manifest {
+            let json = serde_json::to_vec_pretty(&manifest).map_err(map_err)?;
+            std::fs::write(path, json).map_err(map_err)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn content_hash(content: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(content)))
+}
+
+/// Escapes a path in the way GNU Make and Ninja read depfiles.
+fn escape_depfile_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' | '#' => escaped.push('\\'),
+            '$' => escaped.push('$'),
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::layout::{Frame, Page, Size};
+
+    use super::*;
+
+    fn record(path: &str) -> DepsRecord {
+        DepsRecord {
+            path: path.into(),
+            package: None,
+            hash: content_hash(b""),
+        }
+    }
+
+    #[test]
+    fn test_escape_depfile_path() {
+        let escape = |s: &str| escape_depfile_path(Path::new(s));
+        assert_eq!(escape("main.typ"), "main.typ");
+        assert_eq!(escape("my docs/main.typ"), "my\\ docs/main.typ");
+        assert_eq!(escape("#1.typ"), "\\#1.typ");
+        assert_eq!(escape("$HOME.typ"), "$$HOME.typ");
+    }
+
+    #[test]
+    fn test_to_depfile() {
+        let manifest = DepsManifest {
+            targets: vec!["out.pdf".into(), "my out.svg".into()],
+            files: vec![record("main.typ"), record("a b.typ")],
+            fonts: vec![record("fonts/$.ttf")],
+            packages: vec![],
+        };
+        assert_eq!(
+            manifest.to_depfile(),
+            "out.pdf my\\ out.svg: \\\n  main.typ \\\n  a\\ b.typ \\\n  fonts/$$.ttf\n"
+        );
+
+        let empty = DepsManifest {
+            targets: vec!["out.pdf".into()],
+            ..Default::default()
+        };
+        assert_eq!(empty.to_depfile(), "out.pdf:\n");
+        assert_eq!(content_hash(b"").len(), "sha256:".len() + 64);
+    }
+
+    #[test]
+    fn test_target_paths() {
+        let page = |number| Page {
+            frame: Frame::soft(Size::zero()),
+            numbering: None,
+            number,
+        };
+        let doc = TypstDocument {
+            pages: (1..=3).map(page).collect(),
+            ..Default::default()
+        };
+
+        let exporter = DepsExporter::new(vec![
+            DepsTarget::File("out.pdf".into()),
+            DepsTarget::Pages("out.svg".into(), Some("1,3".parse().unwrap())),
+            DepsTarget::Pages("page.png".into(), None),
+        ]);
+        let paths = exporter.target_paths(Some(&doc)).unwrap();
+        let expected = [
+            "out.pdf",
+            "out-1.svg",
+            "out-3.svg",
+            "page-1.png",
+            "page-2.png",
+            "page-3.png",
+        ];
+        assert_eq!(paths, expected.map(PathBuf::from));
+
+        // Pages are skipped without a document, which leaves the depfile as
+        // the target if nothing else is.
+        assert_eq!(
+            exporter.target_paths(None).unwrap(),
+            [PathBuf::from("out.pdf")]
+        );
+        let exporter = DepsExporter::new(vec![DepsTarget::Pages("out.svg".into(), None)])
+            .with_depfile("out.d".into());
+        assert_eq!(
+            exporter.target_paths(None).unwrap(),
+            [PathBuf::from("out.d")]
+        );
+
+        // Out of range selections are errors.
+        let exporter = DepsExporter::new(vec![DepsTarget::Pages(
+            "out.svg".into(),
+            Some("4".parse().unwrap()),
+        )]);
+        assert!(exporter.target_paths(Some(&doc)).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use reflexo_typst2vec::pass::Typst2VecPass;
+use reflexo_world::font::GlyphPack;
+use typst::model::Document as TypstDocument;
+use typst::{diag::SourceResult, World};
+
+use super::Exporter;
+
+/// Builds a [`GlyphPack`] of a document, which keeps the glyphs lowered by
+/// the `glyph2vec` pass, i.e. the glyphs embedded by the vector exporters.
+#[derive(Debug, Default)]
+pub struct GlyphPackExporter;
+
+impl Exporter<TypstDocument, GlyphPack> for GlyphPackExporter {
+    fn export(&self, _world: &dyn World, output: Arc<TypstDocument>) -> SourceResult<GlyphPack> {
+        let pass = Typst2VecPass::default();
+        pass.doc(&output.introspector, &output);
+        Ok(GlyphPack::new(pass.glyphs.used_glyphs()))
+    }
+}
@@ -0,0 +1,441 @@
+use core::fmt;
+use std::num::NonZeroUsize;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use typst::introspection::{Introspector, Meta};
+use typst::layout::{Frame, FrameItem, GroupItem, Position};
+use typst::model::Destination;
+use typst::{diag::SourceResult, model::Document, World};
+
+use crate::{exporter_utils::map_err, Exporter};
+
+/// A selection of pages by their physical page numbers (starting from 1),
+/// e.g. `1,3-5`.
+///
+/// Each comma separated item is either a single page `n`, a closed range
+/// `n-m`, or an open range `n-` or `-m`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageSelection {
+    ranges: Vec<(NonZeroUsize, Option<NonZeroUsize>)>,
+}
+
+impl PageSelection {
+    /// Selects only the first page.
+    pub fn first() -> Self {
+        Self {
+            ranges: vec![(NonZeroUsize::MIN, Some(NonZeroUsize::MIN))],
+        }
+    }
+
+    /// Gets the 0-based indices of selected pages in document order, checking
+    /// that all explicitly mentioned pages exist in a document of `count`
+    /// pages.
+    pub fn indices(&self, count: usize) -> Result<Vec<usize>, PageSelectionError> {
+        let mut selected = vec![false; count];
+        for &(start, end) in &self.ranges {
+            let last = end.unwrap_or(start).get().max(start.get());
+            if last > count {
+                return Err(PageSelectionError::OutOfRange { page: last, count });
+            }
+
+            let end = end.map_or(count, NonZeroUsize::get);
+            selected[start.get() - 1..end].fill(true);
+        }
+
+        Ok((0..count).filter(|&i| selected[i]).collect())
+    }
+
+    /// Creates a document containing only the selected pages, along with the
+    /// physical page numbers (starting from 1) of the selected pages in the
+    /// original document.
+    ///
+    /// The introspector is rebuilt from the selected pages, so that outlines
+    /// and named destinations point to the pages in the selection. Internal
+    /// links are retargeted to the selected pages, and dropped if their
+    /// targets are not selected.
+    pub fn select(&self, doc: &Document) -> Result<(Document, Vec<usize>), PageSelectionError> {
+        let indices = self.indices(doc.pages.len())?;
+        let numbers = indices.iter().map(|i| i + 1).collect::<Vec<_>>();
+
+        // maps an original page number to the one in the selection.
+        let page_of = |page: NonZeroUsize| {
+            let i = numbers.iter().position(|n| *n == page.get())?;
+            NonZeroUsize::new(i + 1)
+        };
+        let retarget = |dest: &Destination| match dest {
+            Destination::Url(..) => Some(dest.clone()),
+            Destination::Position(pos) => page_of(pos.page).map(|page| {
+                Destination::Position(Position {
+                    page,
+                    point: pos.point,
+                })
+            }),
+            Destination::Location(loc) => {
+                page_of(doc.introspector.position(*loc).page).map(|_| dest.clone())
+            }
+        };
+
+        let pages = indices
+            .iter()
+            .map(|&i| {
+                let mut page = doc.pages[i].clone();
+                if let Some(frame) = retarget_links(&page.frame, &retarget) {
+                    page.frame = frame;
+                }
+                page
+            })
+            .collect::<Vec<_>>();
+
+        let mut introspector = Introspector::default();
+        introspector.rebuild(&pages);
+        let doc = Document {
+            pages,
+            introspector,
+            ..doc.clone()
+        };
+        Ok((doc, numbers))
+    }
+
+    /// Selects the pages of a document if a selection is given, along with
+    /// the physical page numbers of the selected pages.
+    ///
+    /// Page-based outputs should be named by these numbers, so that they are
+    /// named by the same page regardless of the selection.
+    pub fn select_or_all(
+        selection: Option<&Self>,
+        doc: Arc<Document>,
+    ) -> Result<(Arc<Document>, Vec<usize>), PageSelectionError> {
+        match selection {
+            Some(selection) => {
+                let (doc, numbers) = selection.select(&doc)?;
+                Ok((Arc::new(doc), numbers))
+            }
+            None => {
+                let numbers = (1..=doc.pages.len()).collect();
+                Ok((doc, numbers))
+            }
+        }
+    }
+}
+
+impl FromStr for PageSelection {
+    type Err = PageSelectionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let page = |v: &str| -> Result<Option<NonZeroUsize>, PageSelectionError> {
+            let v = v.trim();
+            if v.is_empty() {
+                return Ok(None);
+            }
+            match v.parse::<usize>() {
+                Ok(n) => NonZeroUsize::new(n)
+                    .map(Some)
+                    .ok_or(PageSelectionError::ZeroPage),
+                Err(_) => Err(PageSelectionError::Invalid(v.to_owned())),
+            }
+        };
+
+        let mut ranges = vec![];
+        for item in s.split(',') {
+            let range = match item.split_once('-') {
+                Some((start, end)) => {
+                    let (start, end) = (page(start)?, page(end)?);
+                    if start.is_none() && end.is_none() {
+                        return Err(PageSelectionError::Invalid(item.trim().to_owned()));
+                    }
+                    (start.unwrap_or(NonZeroUsize::MIN), end)
+                }
+                None => {
+                    let n = page(item)?.ok_or(PageSelectionError::Empty)?;
+                    (n, Some(n))
+                }
+            };
+
+            if let (start, Some(end)) = range {
+                if start > end {
+                    return Err(PageSelectionError::Reversed(start.get(), end.get()));
+                }
+            }
+            ranges.push(range);
+        }
+
+        Ok(Self { ranges })
+    }
+}
+
+impl fmt::Display for PageSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (start, end)) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            match end {
+                Some(end) if end == start => write!(f, "{start}")?,
+                Some(end) => write!(f, "{start}-{end}")?,
+                None => write!(f, "{start}-")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Rebuilds a frame whose links are retargeted by `f`, which returns `None`
+/// to drop a link. Returns `None` if no link is changed.
+fn retarget_links(
+    frame: &Frame,
+    f: &impl Fn(&Destination) -> Option<Destination>,
+) -> Option<Frame> {
+    let mut changed = false;
+    let mut items = Vec::with_capacity(frame.items().len());
+    for (pos, item) in frame.items() {
+        let item = match item {
+            FrameItem::Group(group) => match retarget_links(&group.frame, f) {
+                Some(frame) => {
+                    changed = true;
+                    FrameItem::Group(GroupItem {
+                        frame,
+                        ..group.clone()
+                    })
+                }
+                None => item.clone(),
+            },
+            FrameItem::Meta(Meta::Link(dest), size) => match f(dest) {
+                Some(retargeted) if retargeted == *dest => item.clone(),
+                Some(retargeted) => {
+                    changed = true;
+                    FrameItem::Meta(Meta::Link(retargeted), *size)
+                }
+                None => {
+                    changed = true;
+                    continue;
+                }
+            },
+            _ => item.clone(),
+        };
+        items.push((*pos, item));
+    }
+
+    if !changed {
+        return None;
+    }
+    let mut retargeted = Frame::new(frame.size(), frame.kind());
+    if frame.has_baseline() {
+        retargeted.set_baseline(frame.baseline());
+    }
+    for (pos, item) in items {
+        retargeted.push(pos, item);
+    }
+    Some(retargeted)
+}
+
+/// An error when parsing or applying a [`PageSelection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PageSelectionError {
+    /// An empty item is found in the selection.
+    Empty,
+    /// An item cannot be parsed.
+    Invalid(String),
+    /// Page numbers start from 1.
+    ZeroPage,
+    /// A range whose start is greater than its end.
+    Reversed(usize, usize),
+    /// A selected page doesn't exist in the document.
+    OutOfRange { page: usize, count: usize },
+}
+
+impl fmt::Display for PageSelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "empty page selection item"),
+            Self::Invalid(v) => write!(f, "invalid page selection item: {v:?}"),
+            Self::ZeroPage => write!(f, "page numbers start from 1"),
+            Self::Reversed(start, end) => {
+                write!(f, "invalid page range {start}-{end}: start is after end")
+            }
+            Self::OutOfRange { page, count } => {
+                write!(
+                    f,
+                    "page {page} is out of range, the document has {count} page(s)"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PageSelectionError {}
+
+/// Exports only the selected pages of a document with the inner exporter.
+///
+/// The pages of the selected document are renumbered from 1. Exporters naming
+/// outputs by page numbers should select pages by themselves, see
+/// [`PageSelection::select_or_all`].
+pub struct SelectPagesExporter<E> {
+    pages: PageSelection,
+    exporter: E,
+}
+
+impl<E> SelectPagesExporter<E> {
+    pub fn new(pages: PageSelection, exporter: E) -> Self {
+        Self { pages, exporter }
+    }
+}
+
+impl<O, E> Exporter<Document, O> for SelectPagesExporter<E>
+where
+    E: Exporter<Document, O>,
+{
+    fn export(&self, world: &dyn World, output: Arc<Document>) -> SourceResult<O> {
+        let (doc, _) = self.pages.select(&output).map_err(map_err)?;
+        self.exporter.export(world, Arc::new(doc))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use typst::layout::{Page, Size};
+
+    use super::*;
+    use crate::exporter_builtins::FsPagedPathExporter;
+
+    fn parse(s: &str) -> Result<PageSelection, PageSelectionError> {
+        s.parse()
+    }
+
+    #[test]
+    fn test_parse_page_selection() {
+        assert_eq!(parse("1").unwrap(), PageSelection::first());
+        assert_eq!(parse("1,3-5").unwrap().to_string(), "1,3-5");
+        assert_eq!(parse(" 2 - , -3 ").unwrap().to_string(), "2-,1-3");
+
+        assert_eq!(parse(""), Err(PageSelectionError::Empty));
+        assert_eq!(parse("1,,2"), Err(PageSelectionError::Empty));
+        assert_eq!(parse("0"), Err(PageSelectionError::ZeroPage));
+        assert_eq!(parse("-"), Err(PageSelectionError::Invalid("-".into())));
+        assert_eq!(parse("a-2"), Err(PageSelectionError::Invalid("a".into())));
+        assert_eq!(parse("5-3"), Err(PageSelectionError::Reversed(5, 3)));
+    }
+
+    #[test]
+    fn test_page_selection_indices() {
+        let indices = |s: &str, count| parse(s).unwrap().indices(count);
+
+        assert_eq!(indices("1", 3), Ok(vec![0]));
+        assert_eq!(indices("3,1-2,2", 3), Ok(vec![0, 1, 2]));
+        assert_eq!(indices("2-", 4), Ok(vec![1, 2, 3]));
+        assert_eq!(indices("-2", 4), Ok(vec![0, 1]));
+        assert_eq!(
+            indices("3-5", 4),
+            Err(PageSelectionError::OutOfRange { page: 5, count: 4 })
+        );
+        assert_eq!(
+            indices("5-", 4),
+            Err(PageSelectionError::OutOfRange { page: 5, count: 4 })
+        );
+    }
+
+    fn doc_of(count: usize) -> Document {
+        let page = |number| Page {
+            frame: Frame::soft(Size::zero()),
+            numbering: None,
+            number,
+        };
+        Document {
+            pages: (1..=count).map(page).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_select_page_numbers() {
+        let doc = doc_of(3);
+
+        let (selected, numbers) = parse("2-3").unwrap().select(&doc).unwrap();
+        assert_eq!(selected.pages.len(), 2);
+        assert_eq!(numbers, vec![2, 3]);
+
+        let (_, numbers) = PageSelection::select_or_all(None, Arc::new(doc)).unwrap();
+        assert_eq!(numbers, vec![1, 2, 3]);
+    }
+
+    /// Collects the links in a frame.
+    fn links(frame: &Frame, links: &mut Vec<Destination>) {
+        for (_, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => self::links(&group.frame, links),
+                FrameItem::Meta(Meta::Link(dest), _) => links.push(dest.clone()),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "pdf")]
+    fn test_select_links_and_outline() {
+        use typst::eval::Tracer;
+        use typst::foundations::Smart;
+
+        use crate::diag::TestWorld;
+
+        let world = TestWorld::new(&[(
+            "main.typ",
+            concat!(
+                "#outline()\n#pagebreak()\n= Alpha <a>\n#pagebreak()\n= Beta <b>\n",
+                "#link(<a>)[A] #link(<b>)[B]\n",
+                "#link((page: 2, x: 0pt, y: 0pt))[C] #link((page: 3, x: 0pt, y: 0pt))[D]",
+            ),
+        )]);
+        let doc = typst::compile(&world, &mut Tracer::new()).unwrap();
+        assert_eq!(doc.pages.len(), 3);
+
+        let (selected, numbers) = parse("1,3").unwrap().select(&doc).unwrap();
+        assert_eq!(numbers, vec![1, 3]);
+
+        // the links to `Alpha` on the unselected page are dropped, and the
+        // others point to the selected pages.
+        let mut dests = vec![];
+        for page in &selected.pages {
+            links(&page.frame, &mut dests);
+        }
+        let pages = dests
+            .iter()
+            .map(|dest| match dest {
+                Destination::Location(loc) => selected.introspector.position(*loc).page.get(),
+                Destination::Position(pos) => pos.page.get(),
+                Destination::Url(..) => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(pages, [2, 2, 2]);
+
+        let pdf = typst_pdf::pdf(&selected, Smart::Auto, None);
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert_eq!(pdf.matches("/Subtype /Link").count(), 3);
+        assert!(pdf.contains("/Title (Beta)"));
+        assert!(!pdf.contains("(Alpha)"));
+        assert!(pdf.contains("(b)"));
+        assert!(!pdf.contains("(a)"));
+    }
+
+    #[test]
+    fn test_paged_output_names() {
+        let doc = doc_of(3);
+        let paths = |pages: Option<&str>| {
+            FsPagedPathExporter::new(PathBuf::from("out/main.png"), ())
+                .with_pages(pages.map(|s| parse(s).unwrap()))
+                .page_paths(&doc)
+                .unwrap()
+        };
+
+        assert_eq!(
+            paths(Some("2-3")),
+            vec![
+                PathBuf::from("out/main-2.png"),
+                PathBuf::from("out/main-3.png")
+            ]
+        );
+        assert_eq!(paths(Some("3")), vec![PathBuf::from("out/main-3.png")]);
+        assert_eq!(paths(None).len(), 3);
+    }
+}
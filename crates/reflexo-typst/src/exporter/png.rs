@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use typst::{diag::SourceResult, visualize::Color, World};
+
+use crate::{exporter_utils::map_err, Exporter};
+
+/// Rasterizes each page of a document into a PNG image.
+#[derive(Debug, Clone)]
+pub struct PngExporter {
+    pixel_per_pt: f32,
+    fill: Color,
+}
+
+impl Default for PngExporter {
+    fn default() -> Self {
+        Self {
+            pixel_per_pt: 3.,
+            fill: Color::WHITE,
+        }
+    }
+}
+
+impl PngExporter {
+    /// Sets the number of pixels per point, i.e. the scale of the raster.
+    pub fn with_pixel_per_pt(mut self, v: f32) -> Self {
+        self.pixel_per_pt = v;
+        self
+    }
+
+    /// Sets the background color of the rendered pages.
+    pub fn with_fill(mut self, v: Color) -> Self {
+        self.fill = v;
+        self
+    }
+}
+
+impl Exporter<typst::model::Document, Vec<Vec<u8>>> for PngExporter {
+    fn export(
+        &self,
+        _world: &dyn World,
+        output: Arc<typst::model::Document>,
+    ) -> SourceResult<Vec<Vec<u8>>> {
+        output
+            .pages
+            .iter()
+            .map(|page| {
+                typst_render::render(&page.frame, self.pixel_per_pt, self.fill)
+                    .encode_png()
+                    .map_err(map_err)
+            })
+            .collect()
+    }
+}
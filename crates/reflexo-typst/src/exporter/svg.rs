@@ -1,39 +1,158 @@
 use std::sync::Arc;
 
 use reflexo_vec2svg::{
-    render_svg, render_svg_html, DefaultExportFeature, ExportFeature, SvgExporter,
+    ir::Rect, render_svg, render_svg_html, render_svg_html_with_options,
+    render_svg_html_with_report, render_svg_page_bboxes, render_svg_pages,
+    render_svg_pages_with_options, render_svg_pages_with_report, render_svg_with_options,
+    render_svg_with_report, DefaultExportFeature, ExportFeature, MinifyReport, SvgExportOptions,
+    SvgExporter,
 };
 use typst::model::Document as TypstDocument;
 use typst::{diag::SourceResult, World};
 
-use super::Exporter;
+use super::{utils::map_err, Exporter};
 
 pub struct SvgHtmlExporter<Feat> {
+    options: Option<SvgExportOptions>,
     _marker: std::marker::PhantomData<Feat>,
 }
 
 impl<Feat> Default for SvgHtmlExporter<Feat> {
     fn default() -> Self {
         Self {
+            options: None,
             _marker: Default::default(),
         }
     }
 }
 
+impl<Feat> SvgHtmlExporter<Feat> {
+    /// Sets the options of SVG export, which replace the features.
+    pub fn with_options(mut self, v: SvgExportOptions) -> Self {
+        self.options = Some(v);
+        self
+    }
+}
+
 impl<Feat: ExportFeature> Exporter<TypstDocument, String> for SvgHtmlExporter<Feat> {
     fn export(&self, _world: &dyn World, output: Arc<TypstDocument>) -> SourceResult<String> {
         // html wrap
-        Ok(render_svg_html::<Feat>(&output))
+        Ok(match self.options {
+            Some(options) => render_svg_html_with_options(&output, options),
+            None => render_svg_html::<Feat>(&output),
+        })
+    }
+}
+
+/// Exports the SVG with the report of the stricter minification, see
+/// [`MinifyReport`].
+impl<Feat: ExportFeature> Exporter<TypstDocument, (String, Option<MinifyReport>)>
+    for SvgHtmlExporter<Feat>
+{
+    fn export(
+        &self,
+        _world: &dyn World,
+        output: Arc<TypstDocument>,
+    ) -> SourceResult<(String, Option<MinifyReport>)> {
+        Ok(match self.options {
+            Some(options) => render_svg_html_with_report(&output, options),
+            None => (render_svg_html::<Feat>(&output), None),
+        })
     }
 }
 
 #[derive(Default)]
-pub struct PureSvgExporter;
+pub struct PureSvgExporter {
+    options: Option<SvgExportOptions>,
+}
+
+impl PureSvgExporter {
+    /// Sets the options of SVG export, which replace the features of
+    /// [`reflexo_vec2svg::SvgExportFeature`].
+    pub fn with_options(mut self, v: SvgExportOptions) -> Self {
+        self.options = Some(v);
+        self
+    }
+}
 
 impl Exporter<TypstDocument, String> for PureSvgExporter {
     fn export(&self, _world: &dyn World, output: Arc<TypstDocument>) -> SourceResult<String> {
-        // html wrap
-        Ok(render_svg(&output))
+        Ok(match self.options {
+            Some(options) => render_svg_with_options(&output, options),
+            None => render_svg(&output),
+        })
+    }
+}
+
+/// Exports the SVG with the report of the stricter minification, see
+/// [`MinifyReport`].
+impl Exporter<TypstDocument, (String, Option<MinifyReport>)> for PureSvgExporter {
+    fn export(
+        &self,
+        _world: &dyn World,
+        output: Arc<TypstDocument>,
+    ) -> SourceResult<(String, Option<MinifyReport>)> {
+        Ok(match self.options {
+            Some(options) => render_svg_with_report(&output, options),
+            None => (render_svg(&output), None),
+        })
+    }
+}
+
+/// Renders each page of a document into a standalone SVG.
+#[derive(Default)]
+pub struct PureSvgPagesExporter {
+    options: Option<SvgExportOptions>,
+}
+
+impl PureSvgPagesExporter {
+    /// Sets the options of SVG export, which replace the features of
+    /// [`reflexo_vec2svg::SvgExportFeature`].
+    pub fn with_options(mut self, v: SvgExportOptions) -> Self {
+        self.options = Some(v);
+        self
+    }
+}
+
+impl Exporter<TypstDocument, Vec<String>> for PureSvgPagesExporter {
+    fn export(&self, _world: &dyn World, output: Arc<TypstDocument>) -> SourceResult<Vec<String>> {
+        Ok(match self.options {
+            Some(options) => render_svg_pages_with_options(&output, options),
+            None => render_svg_pages(&output),
+        })
+    }
+}
+
+/// Exports the SVGs with the reports of the stricter minification, see
+/// [`MinifyReport`].
+impl Exporter<TypstDocument, Vec<(String, Option<MinifyReport>)>> for PureSvgPagesExporter {
+    fn export(
+        &self,
+        _world: &dyn World,
+        output: Arc<TypstDocument>,
+    ) -> SourceResult<Vec<(String, Option<MinifyReport>)>> {
+        Ok(match self.options {
+            Some(options) => render_svg_pages_with_report(&output, options),
+            None => render_svg_pages(&output)
+                .into_iter()
+                .map(|svg| (svg, None))
+                .collect(),
+        })
+    }
+}
+
+/// Calculates the tight bounding box of the content of each page of a
+/// document, e.g. to crop the pages rendered into SVG.
+#[derive(Default)]
+pub struct SvgPageBBoxesExporter {}
+
+impl Exporter<TypstDocument, Vec<Option<Rect>>> for SvgPageBBoxesExporter {
+    fn export(
+        &self,
+        _world: &dyn World,
+        output: Arc<TypstDocument>,
+    ) -> SourceResult<Vec<Option<Rect>>> {
+        render_svg_page_bboxes(&output).map_err(map_err)
     }
 }
 
@@ -2,11 +2,45 @@ use core::fmt;
 use std::io::Write;
 use std::sync::Arc;
 
+use serde::Serialize;
+use typst::layout::{Frame, FrameItem, Point, Transform};
+
 use crate::exporter_utils::map_err;
-use crate::{Transformer, TypstDocument};
+use crate::{PageSelection, Transformer, TypstDocument};
+
+/// How the text is extracted from a document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextExportMode {
+    /// Concatenates text items in the order they appear in frames.
+    #[default]
+    Raw,
+    /// Orders text items geometrically in reading order, breaks lines on
+    /// baseline changes, and separates pages by form feeds (`\x0c`).
+    Layout,
+    /// Emits a JSON array with the text and the bounding boxes of text items
+    /// of each page, in the same reading order as [`TextExportMode::Layout`].
+    Json,
+}
 
 #[derive(Debug, Clone, Default)]
-pub struct TextExporter {}
+pub struct TextExporter {
+    mode: TextExportMode,
+    pages: Option<PageSelection>,
+}
+
+impl TextExporter {
+    pub fn with_mode(mut self, mode: TextExportMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Exports only the selected pages, which are still numbered by their
+    /// page numbers in the whole document.
+    pub fn with_pages(mut self, pages: Option<PageSelection>) -> Self {
+        self.pages = pages;
+        self
+    }
+}
 
 impl<W> Transformer<(Arc<TypstDocument>, W)> for TextExporter
 where
@@ -17,9 +51,36 @@ where
         _world: &dyn typst::World,
         (output, writer): (Arc<TypstDocument>, W),
     ) -> typst::diag::SourceResult<()> {
+        let (output, numbers) =
+            PageSelection::select_or_all(self.pages.as_ref(), output).map_err(map_err)?;
         let mut w = std::io::BufWriter::new(writer);
 
-        write!(w, "{}", FullTextDigest(output)).map_err(map_err)?;
+        match self.mode {
+            TextExportMode::Raw => write!(w, "{}", FullTextDigest(output)).map_err(map_err)?,
+            TextExportMode::Layout => {
+                for (i, page) in output.pages.iter().enumerate() {
+                    if i > 0 {
+                        w.write_all(b"\x0c").map_err(map_err)?;
+                    }
+                    let lines = PageText::new(&page.frame).lines();
+                    w.write_all(lines.join("\n").as_bytes()).map_err(map_err)?;
+                }
+            }
+            TextExportMode::Json => {
+                let texts = output.pages.iter().map(|page| PageText::new(&page.frame));
+                let texts = texts.collect::<Vec<_>>();
+
+                let pages = output.pages.iter().zip(&texts).zip(numbers);
+                let pages = pages.map(|((page, text), page_no)| PageTextRecord {
+                    page: page_no,
+                    width: page.frame.width().to_pt(),
+                    height: page.frame.height().to_pt(),
+                    text: text.lines().join("\n"),
+                    items: text.ordered().into_iter().map(Into::into).collect(),
+                });
+                serde_json::to_writer(&mut w, &pages.collect::<Vec<_>>()).map_err(map_err)?;
+            }
+        }
 
         w.flush().unwrap();
         Ok(())
@@ -60,3 +121,372 @@ impl fmt::Display for FullTextDigest {
         Ok(())
     }
 }
+
+#[derive(Serialize)]
+struct PageTextRecord<'a> {
+    /// The page number, starting from 1.
+    page: usize,
+    /// The width of the page in points.
+    width: f64,
+    /// The height of the page in points.
+    height: f64,
+    /// The text of the page, whose lines are separated by `\n`.
+    text: String,
+    /// The text items of the page in reading order.
+    items: Vec<TextItemRecord<'a>>,
+}
+
+#[derive(Serialize)]
+struct TextItemRecord<'a> {
+    text: &'a str,
+    /// The bounding box `[x0, y0, x1, y1]` in points, from the top-left
+    /// corner of the page.
+    bbox: [f64; 4],
+}
+
+impl<'a> From<&'a TextRun> for TextItemRecord<'a> {
+    fn from(run: &'a TextRun) -> Self {
+        Self {
+            text: &run.text,
+            bbox: run.bbox,
+        }
+    }
+}
+
+/// A text item placed on a page.
+struct TextRun {
+    text: String,
+    /// The bounding box `[x0, y0, x1, y1]` in page coordinates.
+    bbox: [f64; 4],
+    /// The y coordinate of the baseline in page coordinates.
+    baseline: f64,
+}
+
+impl TextRun {
+    fn height(&self) -> f64 {
+        self.bbox[3] - self.bbox[1]
+    }
+
+    fn span(&self, axis: Axis) -> (f64, f64) {
+        match axis {
+            Axis::X => (self.bbox[0], self.bbox[2]),
+            Axis::Y => (self.bbox[1], self.bbox[3]),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+/// The text runs of a page, which are arranged in reading order by a
+/// recursive XY-cut.
+struct PageText {
+    runs: Vec<TextRun>,
+}
+
+impl PageText {
+    fn new(frame: &Frame) -> Self {
+        let mut runs = vec![];
+        Self::collect(frame, Transform::identity(), &mut runs);
+        Self { runs }
+    }
+
+    fn collect(frame: &Frame, ts: Transform, runs: &mut Vec<TextRun>) {
+        for (pos, item) in frame.items() {
+            match item {
+                FrameItem::Group(g) => {
+                    let ts = ts
+                        .pre_concat(Transform::translate(pos.x, pos.y))
+                        .pre_concat(g.transform);
+                    Self::collect(&g.frame, ts, runs);
+                }
+                FrameItem::Text(t) if !t.text.trim().is_empty() => {
+                    let metrics = t.font.metrics();
+                    let top = pos.y - metrics.ascender.at(t.size);
+                    let bottom = pos.y - metrics.descender.at(t.size);
+                    let right = pos.x + t.width();
+
+                    let corners = [(pos.x, top), (right, top), (pos.x, bottom), (right, bottom)]
+                        .map(|(x, y)| Point::new(x, y).transform(ts));
+                    let (xs, ys) = (corners.map(|p| p.x.to_pt()), corners.map(|p| p.y.to_pt()));
+                    let min = |v: [f64; 4]| v.into_iter().fold(f64::INFINITY, f64::min);
+                    let max = |v: [f64; 4]| v.into_iter().fold(f64::NEG_INFINITY, f64::max);
+
+                    runs.push(TextRun {
+                        text: t.text.to_string(),
+                        bbox: [min(xs), min(ys), max(xs), max(ys)],
+                        baseline: pos.transform(ts).y.to_pt(),
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Gets the text runs in reading order.
+    fn ordered(&self) -> Vec<&TextRun> {
+        self.ordered_lines().into_iter().flatten().collect()
+    }
+
+    /// Gets the text lines in reading order.
+    fn lines(&self) -> Vec<String> {
+        self.ordered_lines()
+            .into_iter()
+            .map(|line| {
+                let mut text = String::new();
+                let mut prev: Option<&TextRun> = None;
+                for run in line {
+                    if let Some(prev) = prev {
+                        let gap = run.bbox[0] - prev.bbox[2];
+                        let spaced = prev.text.ends_with(char::is_whitespace)
+                            || run.text.starts_with(char::is_whitespace);
+                        if !spaced && gap > 0.15 * run.height().max(prev.height()) {
+                            text.push(' ');
+                        }
+                    }
+                    text.push_str(&run.text);
+                    prev = Some(run);
+                }
+                text
+            })
+            .collect()
+    }
+
+    fn ordered_lines(&self) -> Vec<Vec<&TextRun>> {
+        let mut lines = vec![];
+        Self::xy_cut(self.runs.iter().collect(), &mut lines);
+        lines
+    }
+
+    /// Recursively splits runs at the widest gap, preferring a vertical cut
+    /// between columns of text over a horizontal cut.
+    fn xy_cut<'a>(runs: Vec<&'a TextRun>, lines: &mut Vec<Vec<&'a TextRun>>) {
+        if let Some((left, right)) = Self::widest_gap(&runs, Axis::X) {
+            if Self::is_column(&left) && Self::is_column(&right) {
+                Self::xy_cut(left, lines);
+                Self::xy_cut(right, lines);
+                return;
+            }
+        }
+
+        if let Some((upper, lower)) = Self::widest_gap(&runs, Axis::Y) {
+            Self::xy_cut(upper, lines);
+            Self::xy_cut(lower, lines);
+            return;
+        }
+
+        lines.extend(Self::group_lines(runs));
+    }
+
+    /// Finds the widest gap between runs projected on the axis, and splits
+    /// the runs at the gap.
+    #[allow(clippy::type_complexity)]
+    fn widest_gap<'a>(
+        runs: &[&'a TextRun],
+        axis: Axis,
+    ) -> Option<(Vec<&'a TextRun>, Vec<&'a TextRun>)> {
+        let mut sorted = runs.to_vec();
+        sorted.sort_by(|a, b| a.span(axis).0.total_cmp(&b.span(axis).0));
+
+        let mut widest: Option<(f64, usize)> = None;
+        let mut end = f64::NEG_INFINITY;
+        for (i, run) in sorted.iter().enumerate() {
+            let (start, run_end) = run.span(axis);
+            let gap = start - end;
+            if i > 0 && gap > 0. && !matches!(widest, Some((w, _)) if w >= gap) {
+                widest = Some((gap, i));
+            }
+            end = end.max(run_end);
+        }
+
+        let (_, i) = widest?;
+        let after = sorted.split_off(i);
+        Some((sorted, after))
+    }
+
+    /// Whether the runs look like a column of text, i.e. they have multiple
+    /// lines which mostly fill the width of the column.
+    fn is_column(runs: &[&TextRun]) -> bool {
+        let lines = Self::group_lines(runs.to_vec());
+        if lines.len() < 2 {
+            return false;
+        }
+
+        let (x0, x1) = runs
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(x0, x1), r| {
+                (x0.min(r.bbox[0]), x1.max(r.bbox[2]))
+            });
+        let width = x1 - x0;
+        if width <= 0. {
+            return false;
+        }
+
+        let filled = lines
+            .iter()
+            .map(|line| (line[line.len() - 1].bbox[2] - line[0].bbox[0]) / width)
+            .sum::<f64>();
+        filled / lines.len() as f64 >= 0.6
+    }
+
+    /// Groups runs sharing (approximately) the same baseline into lines,
+    /// sorted from top to bottom and from left to right.
+    fn group_lines(mut runs: Vec<&TextRun>) -> Vec<Vec<&TextRun>> {
+        runs.sort_by(|a, b| a.baseline.total_cmp(&b.baseline));
+
+        let mut lines: Vec<Vec<&TextRun>> = vec![];
+        for run in runs {
+            match lines.last_mut() {
+                Some(line)
+                    if (run.baseline - line[0].baseline).abs()
+                        <= 0.5 * run.height().max(line[0].height()) =>
+                {
+                    line.push(run)
+                }
+                _ => lines.push(vec![run]),
+            }
+        }
+
+        for line in &mut lines {
+            line.sort_by(|a, b| a.bbox[0].total_cmp(&b.bbox[0]));
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::eval::Tracer;
+
+    use super::*;
+    use crate::diag::TestWorld;
+
+    fn run(text: &str, x0: f64, y0: f64, x1: f64, y1: f64) -> TextRun {
+        TextRun {
+            text: text.to_owned(),
+            bbox: [x0, y0, x1, y1],
+            baseline: y1 - 2.,
+        }
+    }
+
+    fn texts<'a>(lines: Vec<Vec<&'a TextRun>>) -> Vec<Vec<&'a str>> {
+        let line = |line: Vec<&'a TextRun>| line.into_iter().map(|r| r.text.as_str()).collect();
+        lines.into_iter().map(line).collect()
+    }
+
+    #[test]
+    fn test_group_lines() {
+        let runs = [
+            run("c", 50., 20., 60., 30.),
+            run("a", 0., 0., 10., 10.),
+            // a superscript slightly above the baseline
+            run("b", 10., -3., 15., 7.),
+            // far below the baseline
+            run("d", 0., 6., 10., 16.),
+        ];
+        let lines = PageText::group_lines(runs.iter().collect());
+        assert_eq!(texts(lines), [vec!["a", "b"], vec!["d"], vec!["c"]]);
+    }
+
+    #[test]
+    fn test_is_column() {
+        let full = [run("a", 0., 0., 100., 10.), run("b", 0., 12., 90., 22.)];
+        assert!(PageText::is_column(&full.iter().collect::<Vec<_>>()));
+
+        // a single line is not a column
+        assert!(!PageText::is_column(&[&full[0]]));
+
+        // lines mostly not filling the width
+        let ragged = [run("a", 0., 0., 100., 10.), run("b", 0., 12., 5., 22.)];
+        assert!(!PageText::is_column(&ragged.iter().collect::<Vec<_>>()));
+    }
+
+    #[test]
+    fn test_two_column_order() {
+        let mut runs = vec![run("Title", 0., -20., 220., -10.)];
+        for (i, y) in [0., 12., 24.].into_iter().enumerate() {
+            runs.push(run(&format!("R{}", i + 1), 120., y, 220., y + 10.));
+            runs.push(run(&format!("L{}", i + 1), 0., y, 100., y + 10.));
+        }
+        let text = PageText { runs };
+
+        let lines = text.lines();
+        assert_eq!(lines, ["Title", "L1", "L2", "L3", "R1", "R2", "R3"]);
+        let ordered = text.ordered().into_iter().map(|r| r.text.as_str());
+        assert_eq!(
+            ordered.collect::<Vec<_>>(),
+            ["Title", "L1", "L2", "L3", "R1", "R2", "R3"]
+        );
+    }
+
+    #[test]
+    fn test_line_spacing() {
+        let text = PageText {
+            runs: vec![
+                run("A", 0., 0., 10., 10.),
+                run("B", 10., 0., 20., 10.),
+                run("C", 30., 0., 40., 10.),
+                run("D ", 40., 12., 50., 22.),
+                run("E", 60., 12., 70., 22.),
+            ],
+        };
+        assert_eq!(text.lines(), ["AB C", "D E"]);
+    }
+
+    fn export(mode: TextExportMode, pages: Option<&str>) -> String {
+        let world = TestWorld::new(&[(
+            "main.typ",
+            concat!(
+                "#set page(width: 100pt, height: 100pt, margin: 10pt)\n",
+                "#set text(size: 10pt)\n",
+                "AB#linebreak()CD\n#pagebreak()\nDA",
+            ),
+        )]);
+        let doc = typst::compile(&world, &mut Tracer::new()).unwrap();
+
+        let exporter = TextExporter::default()
+            .with_mode(mode)
+            .with_pages(pages.map(|p| p.parse().unwrap()));
+        let mut out = vec![];
+        exporter.export(&world, (Arc::new(doc), &mut out)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_export_text() {
+        assert_eq!(export(TextExportMode::Raw, None), "ABCDDA");
+        assert_eq!(export(TextExportMode::Layout, None), "AB\nCD\x0cDA");
+        assert_eq!(export(TextExportMode::Layout, Some("2")), "DA");
+    }
+
+    #[test]
+    fn test_export_json() {
+        let json = export(TextExportMode::Json, Some("2"));
+        let pages: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let pages = pages.as_array().unwrap();
+        assert_eq!(pages.len(), 1);
+
+        let page = &pages[0];
+        let keys = page.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["height", "items", "page", "text", "width"]);
+        assert_eq!(page["page"], 2);
+        assert_eq!(page["width"], 100.);
+        assert_eq!(page["height"], 100.);
+        assert_eq!(page["text"], "DA");
+
+        let items = page["items"].as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["text"], "DA");
+        let bbox = items[0]["bbox"].as_array().unwrap();
+        let bbox = bbox.iter().map(|v| v.as_f64().unwrap()).collect::<Vec<_>>();
+        // two glyphs of half an em from the left margin, spanning from the
+        // ascender to the descender
+        assert_eq!(bbox.len(), 4);
+        assert!((bbox[0] - 10.).abs() < 1e-6, "{bbox:?}");
+        assert!((bbox[2] - 20.).abs() < 1e-6, "{bbox:?}");
+        assert!(bbox[1] >= 10. && bbox[3] > bbox[1], "{bbox:?}");
+    }
+}
@@ -66,6 +66,7 @@ impl CompileFeature<DiagnosticFormat> for DiagFmtFeature {
             match value {
                 DiagnosticFormat::Human => "",
                 DiagnosticFormat::Short => "s",
+                DiagnosticFormat::Json => "j",
             }
             .into(),
         )
@@ -74,7 +75,11 @@ impl CompileFeature<DiagnosticFormat> for DiagFmtFeature {
     fn retrieve(&self, features: &FeatureSet) -> DiagnosticFormat {
         features
             .slot(DIAG_FEATURE)
-            .and_then(|s| (s == "s").then_some(DiagnosticFormat::Short))
+            .and_then(|s| match s.as_str() {
+                "s" => Some(DiagnosticFormat::Short),
+                "j" => Some(DiagnosticFormat::Json),
+                _ => None,
+            })
             .unwrap_or_default()
     }
 }
@@ -93,6 +98,10 @@ impl<T> BuiltinFeature<T> {
 
 pub static WITH_COMPILING_STATUS_FEATURE: BuiltinFeature<bool> = BuiltinFeature::<bool>::new();
 
+/// Reports unknown font families and characters no font covers as warnings
+/// after compilation, see [`crate::diag::font_diagnostics`].
+pub static FONT_DIAGNOSTICS_FEATURE: BuiltinFeature<bool> = BuiltinFeature::<bool>::new();
+
 impl CompileFeature<bool> for BuiltinFeature<bool> {
     fn configure(&self, features: FeatureSet, value: bool) -> FeatureSet {
         features.configure_slot(&self.0, if value { "1" } else { "" }.into())
@@ -42,19 +42,28 @@ pub(crate) mod exporter;
 #[cfg(feature = "ast")]
 pub use exporter::ast::{dump_ast, AstExporter};
 
+pub use exporter::deps::{DepsExporter, DepsManifest, DepsRecord, DepsTarget};
+
+pub use exporter::glyph_pack::GlyphPackExporter;
+
 pub use exporter::json::JsonExporter;
 
+pub use exporter::pages::{PageSelection, PageSelectionError, SelectPagesExporter};
+
 #[cfg(feature = "pdf")]
 pub use exporter::pdf::PdfDocExporter;
 #[cfg(feature = "pdf")]
 pub use typst_pdf::pdf;
 
+#[cfg(feature = "png")]
+pub use exporter::png::PngExporter;
+
 #[cfg(feature = "svg")]
 pub use exporter::svg::*;
 #[cfg(feature = "svg")]
 pub use reflexo_vec2svg as svg;
 
-pub use exporter::text::TextExporter;
+pub use exporter::text::{TextExportMode, TextExporter};
 
 pub use reflexo_typst2vec as vector;
 pub use reflexo_typst2vec::debug_loc;
@@ -84,6 +93,7 @@ mod driver;
 pub mod eval;
 mod export;
 pub mod features;
+pub mod profile;
 pub mod query;
 mod utils;
 
@@ -127,6 +137,8 @@ use core::fmt;
 use std::sync::Arc;
 use std::sync::OnceLock;
 
+use crate::features::{CompileFeature, FONT_DIAGNOSTICS_FEATURE};
+use crate::package::{DownloadStatus, PackageSpec};
 use crate::typst::prelude::*;
 use ::typst::{
     diag::{At, Hint, SourceDiagnostic, SourceResult},
@@ -180,12 +192,14 @@ pub enum CompileReport {
         EcoVec<SourceDiagnostic>,
         reflexo::time::Duration,
     ),
+    /// A package is being downloaded during compilation.
+    PackageDownload(PackageSpec, DownloadStatus),
 }
 
 impl CompileReport {
     pub fn compiling_id(&self) -> Option<TypstFileId> {
         Some(match self {
-            Self::Suspend => return None,
+            Self::Suspend | Self::PackageDownload(..) => return None,
             Self::Stage(id, ..)
             | Self::CompileError(id, ..)
             | Self::ExportError(id, ..)
@@ -196,7 +210,7 @@ impl CompileReport {
 
     pub fn duration(&self) -> Option<std::time::Duration> {
         match self {
-            Self::Suspend | Self::Stage(..) => None,
+            Self::Suspend | Self::Stage(..) | Self::PackageDownload(..) => None,
             Self::CompileError(_, _, dur)
             | Self::ExportError(_, _, dur)
             | Self::CompileWarning(_, _, dur)
@@ -206,7 +220,7 @@ impl CompileReport {
 
     pub fn diagnostics(self) -> Option<EcoVec<SourceDiagnostic>> {
         match self {
-            Self::Suspend | Self::Stage(..) => None,
+            Self::Suspend | Self::Stage(..) | Self::PackageDownload(..) => None,
             Self::CompileError(_, diagnostics, ..)
             | Self::ExportError(_, diagnostics, ..)
             | Self::CompileWarning(_, diagnostics, ..)
@@ -236,6 +250,18 @@ impl<'a> fmt::Display for CompileReportMsg<'a> {
             CompileError(_, _, duration) | ExportError(_, _, duration) => {
                 write!(f, "{:?}: compilation failed after {:?}", input, duration)
             }
+            PackageDownload(spec, status) => match status {
+                DownloadStatus::Started => write!(f, "{spec}: downloading ..."),
+                DownloadStatus::Progress {
+                    received,
+                    total: Some(total),
+                } => write!(f, "{spec}: downloaded {received} of {total} bytes"),
+                DownloadStatus::Progress { received, .. } => {
+                    write!(f, "{spec}: downloaded {received} bytes")
+                }
+                DownloadStatus::Finished => write!(f, "{spec}: download finished"),
+                DownloadStatus::Failed(err) => write!(f, "{spec}: download failed: {err}"),
+            },
         }
     }
 }
@@ -404,13 +430,19 @@ pub trait Compiler {
     ) -> SourceResult<Arc<Document>> {
         self.reset()?;
 
-        let res = match env.tracer.as_mut() {
-            Some(tracer) => ::typst::compile(world, tracer),
-            None => ::typst::compile(world, &mut Tracer::default()),
-        };
+        let mut default_tracer = Tracer::default();
+        let tracer = env.tracer.as_mut().unwrap_or(&mut default_tracer);
 
         // compile document
-        res.map(Arc::new)
+        let doc = ::typst::compile(world, tracer)?;
+
+        if FONT_DIAGNOSTICS_FEATURE.retrieve(&env.features) {
+            for warning in diag::font_diagnostics(world, &doc) {
+                tracer.warn(warning);
+            }
+        }
+
+        Ok(Arc::new(doc))
     }
 
     /// With **the compilation state**, query the matches for the selector.
@@ -0,0 +1,121 @@
+//! Opt-in profiling of compilations.
+//!
+//! When [`enable`]d, timing spans are recorded for the compilation stages
+//! instrumented by typst (compiling, evaluation, typesetting and layout of
+//! elements), and for reading files, parsing sources, loading fonts,
+//! downloading packages and exporting (see [`TimedExporter`]).
+//!
+//! The recorded spans can be exported in the Chrome trace-event format by
+//! [`export_chrome_trace`], which can be viewed in `chrome://tracing` or
+//! <https://ui.perfetto.dev>.
+//!
+//! [`TimedExporter`]: crate::exporter_builtins::TimedExporter
+
+use std::io::Write;
+
+use serde_json::json;
+use typst::{syntax::Span, World};
+
+pub use reflexo_world::source::SourceCacheStats;
+pub use typst_timing::{clear, enable, is_enabled, timed, TimingScope};
+
+/// Writes the recorded spans as a JSON array of Chrome trace events.
+///
+/// The memoization cache of typst does not expose statistics, so the hit
+/// ratio of the source cache is reported instead. For each compilation in
+/// `source_cache_stats`, a counter event named `source cache (<name>)` is
+/// appended, which counts the files carried over unchanged from previous
+/// revisions (`hits`) and those loaded from scratch or changed (`misses`). The
+/// statistics should be taken from the world of each compilation, see
+/// [`SourceDb::cache_stats`].
+///
+/// [`SourceDb::cache_stats`]: reflexo_world::source::SourceDb::cache_stats
+pub fn export_chrome_trace(
+    world: &dyn World,
+    source_cache_stats: &[(String, SourceCacheStats)],
+    writer: impl Write,
+) -> Result<(), String> {
+    // Reading sources may record spans, which deadlocks if it happens during
+    // exporting, so spans are resolved after exporting.
+    let mut spans = vec![];
+    let mut buf = vec![];
+    typst_timing::export_json(&mut buf, |span| {
+        spans.push(span);
+        (String::new(), spans.len() as u32 - 1)
+    })?;
+
+    let mut events: Vec<serde_json::Value> =
+        serde_json::from_slice(&buf).map_err(|e| format!("failed to read events: {e}"))?;
+    for args in events.iter_mut().filter_map(|e| e["args"].as_object_mut()) {
+        let span = args["line"].as_u64().and_then(|i| spans.get(i as usize));
+        let (file, line) = span.map_or_else(Default::default, |span| resolve_span(world, *span));
+        args.insert("file".into(), file.into());
+        args.insert("line".into(), line.into());
+    }
+
+    let ts = events
+        .iter()
+        .filter_map(|e| e["ts"].as_f64())
+        .fold(0., f64::max);
+    for (name, stats) in source_cache_stats {
+        events.push(json!({
+            "name": format!("source cache ({name})"),
+            "cat": "reflexo",
+            "ph": "C",
+            "ts": ts,
+            "pid": 1,
+            "tid": 0,
+            "args": { "hits": stats.hits, "misses": stats.misses },
+        }));
+    }
+
+    let events =
+        serde_json::to_vec(&events).map_err(|e| format!("failed to serialize events: {e}"))?;
+    write_all(writer, &events)
+}
+
+fn write_all(mut writer: impl Write, buf: &[u8]) -> Result<(), String> {
+    writer
+        .write_all(buf)
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("failed to write events: {e}"))
+}
+
+/// Resolves the span to the file path and the 1-based line number.
+fn resolve_span(world: &dyn World, span: Span) -> (String, u32) {
+    let Some(id) = span.id() else {
+        return (String::new(), 0);
+    };
+    let path = id.vpath().as_rootless_path().display().to_string();
+    let path = match id.package() {
+        Some(package) => format!("{package}/{path}"),
+        None => path,
+    };
+
+    let line = world.source(id).ok().and_then(|source| {
+        let range = source.range(span)?;
+        source.byte_to_line(range.start)
+    });
+    (path, line.map_or(0, |line| line as u32 + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diag::TestWorld;
+
+    #[test]
+    fn test_source_cache_counters() {
+        let world = TestWorld::new(&[("main.typ", "A")]);
+        let stats = SourceCacheStats { hits: 2, misses: 1 };
+
+        let mut buf = vec![];
+        export_chrome_trace(&world, &[("main.typ".into(), stats)], &mut buf).unwrap();
+        let events: Vec<serde_json::Value> = serde_json::from_slice(&buf).unwrap();
+
+        let counter = events.last().unwrap();
+        assert_eq!(counter["name"], "source cache (main.typ)");
+        assert_eq!(counter["ph"], "C");
+        assert_eq!(counter["args"], json!({ "hits": 2, "misses": 1 }));
+    }
+}
@@ -1,17 +1,107 @@
 //! The actor that handles cache evicting.
 
 use std::sync::{atomic::AtomicUsize, Arc};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
 
 use super::{FutureFolder, SyncTaskFactory};
 
+/// Decides when the compilation cache is evicted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheEvictPolicy {
+    /// Evicts after every compilation.
+    #[default]
+    Always,
+    /// Evicts once every given number of compilations.
+    Revisions(usize),
+    /// Evicts when at least the given wall time has elapsed since the last
+    /// eviction.
+    Interval(Duration),
+    /// Evicts when the resident memory of the process exceeds the given
+    /// number of bytes.
+    ///
+    /// The memory usage is only known on Linux, see
+    /// [`CacheEvictPolicy::is_supported`]. If it cannot be determined, the
+    /// usage is treated as below the threshold and nothing is evicted.
+    MemoryThreshold(u64),
+}
+
+impl CacheEvictPolicy {
+    /// Whether the policy can be decided on the current platform.
+    ///
+    /// [`CacheEvictPolicy::MemoryThreshold`] never evicts where the memory
+    /// usage of the process is unknown, so callers should pick another policy
+    /// there.
+    pub fn is_supported(&self) -> bool {
+        match self {
+            CacheEvictPolicy::MemoryThreshold(..) => cfg!(target_os = "linux"),
+            _ => true,
+        }
+    }
+
+    /// Decides whether to evict, given the number of compilations and the
+    /// elapsed time since the last eviction, and the resident memory of the
+    /// process if known.
+    pub fn should_evict(
+        &self,
+        revisions_since_evict: usize,
+        since_evict: Duration,
+        resident_memory: Option<u64>,
+    ) -> bool {
+        match *self {
+            CacheEvictPolicy::Always => true,
+            CacheEvictPolicy::Revisions(n) => revisions_since_evict >= n,
+            CacheEvictPolicy::Interval(interval) => since_evict >= interval,
+            CacheEvictPolicy::MemoryThreshold(threshold) => {
+                resident_memory.is_some_and(|usage| usage >= threshold)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CacheUserConfig {
+    /// The maximum age of cache entries in evictions, see [`comemo::evict`].
     pub max_age: usize,
+    /// When to evict the cache.
+    pub policy: CacheEvictPolicy,
 }
 
 impl Default for CacheUserConfig {
     fn default() -> Self {
-        Self { max_age: 30 }
+        Self {
+            max_age: 30,
+            policy: CacheEvictPolicy::default(),
+        }
+    }
+}
+
+/// The metrics of cache evictions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheEvictStats {
+    /// The number of evictions performed.
+    pub evict_count: usize,
+    /// The time spent in the last eviction.
+    pub last_evict_duration: Duration,
+    /// The total time spent in evictions.
+    pub total_evict_duration: Duration,
+}
+
+#[derive(Debug)]
+struct CacheEvictState {
+    last_evict: Instant,
+    revisions_since_evict: usize,
+    stats: CacheEvictStats,
+}
+
+impl Default for CacheEvictState {
+    fn default() -> Self {
+        Self {
+            last_evict: Instant::now(),
+            revisions_since_evict: 0,
+            stats: CacheEvictStats::default(),
+        }
     }
 }
 
@@ -20,6 +110,7 @@ pub struct CacheTask {
     factory: SyncTaskFactory<CacheUserConfig>,
     cache_evict_folder: FutureFolder,
     revision: Arc<AtomicUsize>,
+    state: Arc<Mutex<CacheEvictState>>,
 }
 
 impl CacheTask {
@@ -28,14 +119,31 @@ impl CacheTask {
             factory: SyncTaskFactory::new(c),
             cache_evict_folder: FutureFolder::default(),
             revision: Arc::new(AtomicUsize::default()),
+            state: Arc::default(),
         }
     }
 
+    /// Gets the metrics of cache evictions so far.
+    pub fn stats(&self) -> CacheEvictStats {
+        self.state.lock().stats
+    }
+
     pub fn evict(&self) {
+        self.evict_with(|_| {});
+    }
+
+    /// Evicts the cache if the policy allows, calling `on_evicted` with the
+    /// updated metrics after the eviction is done.
+    pub fn evict_with(&self, on_evicted: impl FnOnce(CacheEvictStats) + Send + Sync + 'static) {
         let revision = self
             .revision
             .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         let task = self.factory.task();
+        if !self.should_evict(&task.policy) {
+            return;
+        }
+
+        let state = self.state.clone();
         self.cache_evict_folder.spawn(revision, || {
             Box::pin(async move {
                 let _ = FutureFolder::compute(move |_| {
@@ -44,6 +152,16 @@ impl CacheTask {
                     comemo::evict(task.max_age);
                     let elapsed = evict_start.elapsed();
                     log::debug!("CacheEvictTask: evict cache in {elapsed:?}");
+
+                    let stats = {
+                        let mut state = state.lock();
+                        let stats = &mut state.stats;
+                        stats.evict_count += 1;
+                        stats.last_evict_duration = elapsed;
+                        stats.total_evict_duration += elapsed;
+                        *stats
+                    };
+                    on_evicted(stats);
                 })
                 .await;
 
@@ -51,4 +169,92 @@ impl CacheTask {
             })
         });
     }
+
+    fn should_evict(&self, policy: &CacheEvictPolicy) -> bool {
+        let mut state = self.state.lock();
+        state.revisions_since_evict += 1;
+
+        let memory = match policy {
+            CacheEvictPolicy::MemoryThreshold(..) => resident_memory(),
+            _ => None,
+        };
+        let evict = policy.should_evict(
+            state.revisions_since_evict,
+            state.last_evict.elapsed(),
+            memory,
+        );
+
+        if evict {
+            state.last_evict = Instant::now();
+            state.revisions_since_evict = 0;
+        }
+        evict
+    }
+}
+
+/// Gets the resident memory of the current process in bytes.
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb = line.split_whitespace().nth(1)?.parse::<u64>().ok()?;
+    Some(kb * 1024)
+}
+
+/// Gets the resident memory of the current process in bytes.
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<u64> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict_policy() {
+        let secs = Duration::from_secs;
+
+        assert!(CacheEvictPolicy::Always.should_evict(1, secs(0), None));
+
+        let revisions = CacheEvictPolicy::Revisions(3);
+        assert!(!revisions.should_evict(2, secs(100), None));
+        assert!(revisions.should_evict(3, secs(0), None));
+
+        let interval = CacheEvictPolicy::Interval(secs(10));
+        assert!(!interval.should_evict(100, secs(9), None));
+        assert!(interval.should_evict(1, secs(10), None));
+
+        let memory = CacheEvictPolicy::MemoryThreshold(1024);
+        assert!(!memory.should_evict(1, secs(0), Some(1023)));
+        assert!(memory.should_evict(1, secs(0), Some(1024)));
+        // unknown memory usage is treated as below the threshold
+        assert!(!memory.should_evict(100, secs(100), None));
+    }
+
+    #[test]
+    fn test_evict_policy_supported() {
+        assert!(CacheEvictPolicy::Always.is_supported());
+        assert!(CacheEvictPolicy::Revisions(3).is_supported());
+        assert!(CacheEvictPolicy::Interval(Duration::from_secs(1)).is_supported());
+        assert_eq!(
+            CacheEvictPolicy::MemoryThreshold(1024).is_supported(),
+            cfg!(target_os = "linux")
+        );
+        // the resident memory is known wherever the policy is supported
+        assert!(
+            !CacheEvictPolicy::MemoryThreshold(1024).is_supported() || resident_memory().is_some()
+        );
+    }
+
+    #[test]
+    fn test_evict_resets_counters() {
+        let task = CacheTask::new(CacheUserConfig::default());
+        let policy = CacheEvictPolicy::Revisions(2);
+
+        assert!(!task.should_evict(&policy));
+        assert!(task.should_evict(&policy));
+        assert!(!task.should_evict(&policy));
+        assert!(task.should_evict(&policy));
+    }
 }
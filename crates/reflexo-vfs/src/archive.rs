@@ -0,0 +1,637 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use reflexo::ImmutPath;
+use typst::diag::{eco_format, FileError, FileResult};
+
+use crate::{AccessModel, Bytes, Time};
+
+/// A zip or tar archive indexed in memory, whose entries are read without
+/// unpacking the archive to the file system.
+///
+/// Gzip compressed tar archives are decompressed once when the archive is
+/// loaded, while the deflated entries of zip archives are decompressed on
+/// reading.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    /// The modification time of all entries in the archive.
+    mtime: Time,
+    /// The data of the archive, or the decompressed data of a gzip compressed
+    /// tar archive.
+    data: Bytes,
+    /// The file entries, keyed by the relative paths in the archive.
+    files: BTreeMap<PathBuf, ArchiveEntry>,
+    /// The directories in the archive, including the implicit parents of the
+    /// file entries.
+    dirs: BTreeSet<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+struct ArchiveEntry {
+    /// The range of the (compressed) content in the data.
+    range: Range<usize>,
+    /// Whether the content is compressed by deflate.
+    deflated: bool,
+    /// The size of the uncompressed content.
+    size: usize,
+    /// The CRC-32 checksum of the uncompressed content, if any.
+    crc32: Option<u32>,
+}
+
+impl Archive {
+    /// Loads an archive from memory, detecting whether it is a zip, tar or
+    /// gzip compressed tar archive by its content. Tar archives are detected
+    /// by the `ustar` magic of POSIX and GNU tar, so pre-POSIX tar archives
+    /// are rejected.
+    ///
+    /// All entries in the archive have the given modification time.
+    pub fn new(data: Bytes, mtime: Time) -> io::Result<Self> {
+        let mut archive = Self {
+            mtime,
+            data,
+            files: BTreeMap::new(),
+            dirs: BTreeSet::new(),
+        };
+
+        if archive.data.starts_with(b"PK") {
+            archive.index_zip()?;
+        } else if archive.data.starts_with(&[0x1f, 0x8b]) {
+            // The size of the decompressed data modulo 2^32 is stored in the
+            // last four bytes, so tar archives over 4 GiB are rejected.
+            let size = archive
+                .data
+                .len()
+                .checked_sub(4)
+                .map(|pos| &archive.data[pos..]);
+            let size = size.map_or(0, |s| u32::from_le_bytes(s.try_into().unwrap()));
+            let decoder = flate2::read::GzDecoder::new(archive.data.as_slice());
+            let data = read_bounded(decoder, size as usize)?;
+            archive.data = Bytes::from(data);
+            if !is_tar(&archive.data) {
+                return Err(invalid_data("not a tar archive in gzip"));
+            }
+            archive.index_tar()?;
+        } else if is_tar(&archive.data) {
+            archive.index_tar()?;
+        } else {
+            return Err(invalid_data("not a zip or tar archive"));
+        }
+
+        Ok(archive)
+    }
+
+    /// Loads an archive from the local file system.
+    ///
+    /// All entries in the archive have the modification time of the archive
+    /// file.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mtime = std::fs::metadata(path)?.modified()?;
+        Self::new(Bytes::from(std::fs::read(path)?), mtime)
+    }
+
+    /// Gets the relative paths of the files in the archive.
+    pub fn file_paths(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(PathBuf::as_path)
+    }
+
+    /// Whether the relative path is a file in the archive.
+    pub fn is_file(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Whether the relative path is a directory in the archive. The empty path
+    /// is the root directory.
+    pub fn is_dir(&self, path: &Path) -> bool {
+        path.as_os_str().is_empty() || self.dirs.contains(path)
+    }
+
+    /// Reads the content of a file by the relative path in the archive.
+    pub fn read(&self, path: &Path) -> io::Result<Bytes> {
+        let Some(entry) = self.files.get(path) else {
+            return Err(io::ErrorKind::NotFound.into());
+        };
+
+        let data = &self.data[entry.range.clone()];
+        let content = if entry.deflated {
+            read_bounded(flate2::read::DeflateDecoder::new(data), entry.size)?
+        } else {
+            data.to_vec()
+        };
+
+        if let Some(crc32) = entry.crc32 {
+            let mut crc = flate2::Crc::new();
+            crc.update(&content);
+            if content.len() != entry.size || crc.sum() != crc32 {
+                return Err(invalid_data("checksum mismatch of archive entry"));
+            }
+        }
+
+        Ok(Bytes::from(content))
+    }
+
+    fn add_file(&mut self, name: &Path, entry: ArchiveEntry) {
+        let Some(path) = normalize(name) else {
+            log::warn!("skipped archive entry outside of the archive: {name:?}");
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            self.add_dir(parent);
+        }
+        self.files.insert(path, entry);
+    }
+
+    fn add_dir(&mut self, name: &Path) {
+        let Some(path) = normalize(name) else {
+            return;
+        };
+        for dir in path.ancestors().filter(|dir| !dir.as_os_str().is_empty()) {
+            if !self.dirs.insert(dir.to_owned()) {
+                break;
+            }
+        }
+    }
+
+    fn index_tar(&mut self) -> io::Result<()> {
+        let data = self.data.clone();
+        let mut tar = tar::Archive::new(data.as_slice());
+        for entry in tar.entries()? {
+            let entry = entry?;
+            let path = entry.path()?.into_owned();
+            match entry.header().entry_type() {
+                tar::EntryType::Regular | tar::EntryType::Continuous => {
+                    let start = entry.raw_file_position() as usize;
+                    let size = entry.size() as usize;
+                    let entry = ArchiveEntry {
+                        range: start..start + size,
+                        deflated: false,
+                        size,
+                        crc32: None,
+                    };
+                    self.add_file(&path, entry);
+                }
+                tar::EntryType::Directory => self.add_dir(&path),
+                ty => log::warn!("skipped archive entry of type {ty:?}: {path:?}"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Indexes the central directory of a zip archive.
+    ///
+    /// Zip64 and encrypted archives are not supported.
+    fn index_zip(&mut self) -> io::Result<()> {
+        const END_OF_CENTRAL_DIR: &[u8] = b"PK\x05\x06";
+        const CENTRAL_DIR_HEADER: &[u8] = b"PK\x01\x02";
+        const LOCAL_FILE_HEADER: &[u8] = b"PK\x03\x04";
+
+        let data = self.data.clone();
+        let malformed = || invalid_data("malformed zip archive");
+
+        // The end of central directory record is followed by a comment of at
+        // most 65535 bytes.
+        let eocd = (0..data.len().saturating_sub(21))
+            .rev()
+            .take(65536)
+            .find(|&pos| data[pos..].starts_with(END_OF_CENTRAL_DIR))
+            .ok_or_else(malformed)?;
+        let count = read_u16(&data, eocd + 10).ok_or_else(malformed)?;
+        let mut pos = read_u32(&data, eocd + 16).ok_or_else(malformed)? as usize;
+        if count == u16::MAX || pos == u32::MAX as usize {
+            return Err(invalid_data("zip64 archives are not supported"));
+        }
+
+        for _ in 0..count {
+            let header = data.get(pos..pos + 46).ok_or_else(malformed)?;
+            if !header.starts_with(CENTRAL_DIR_HEADER) {
+                return Err(malformed());
+            }
+            let u16_at = |at| read_u16(header, at).unwrap() as usize;
+            let u32_at = |at| read_u32(header, at).unwrap();
+
+            let (flags, method) = (u16_at(8), u16_at(10));
+            let (crc32, compressed_size, size) = (u32_at(16), u32_at(20), u32_at(24));
+            let (name_len, extra_len, comment_len) = (u16_at(28), u16_at(30), u16_at(32));
+            let offset = u32_at(42) as usize;
+
+            let name = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or_else(malformed)?;
+            let name = Path::new(std::str::from_utf8(name).map_err(|_| malformed())?);
+            pos += 46 + name_len + extra_len + comment_len;
+
+            if name.as_os_str().to_string_lossy().ends_with('/') {
+                self.add_dir(name);
+                continue;
+            }
+            if flags & 1 != 0 {
+                return Err(invalid_data("encrypted zip archives are not supported"));
+            }
+            if [compressed_size, size, offset as u32].contains(&u32::MAX) {
+                return Err(invalid_data("zip64 archives are not supported"));
+            }
+            let deflated = match method {
+                0 => false,
+                8 => true,
+                _ => return Err(invalid_data("unsupported compression method of zip entry")),
+            };
+
+            // The local file header repeats the name, but its extra field may
+            // differ from the central directory.
+            let local = data.get(offset..offset + 30).ok_or_else(malformed)?;
+            if !local.starts_with(LOCAL_FILE_HEADER) {
+                return Err(malformed());
+            }
+            let start = offset
+                + 30
+                + read_u16(local, 26).unwrap() as usize
+                + read_u16(local, 28).unwrap() as usize;
+            let end = start + compressed_size as usize;
+            if end > data.len() {
+                return Err(malformed());
+            }
+
+            let entry = ArchiveEntry {
+                range: start..end,
+                deflated,
+                size: size as usize,
+                crc32: Some(crc32),
+            };
+            self.add_file(name, entry);
+        }
+
+        Ok(())
+    }
+}
+
+/// Provides archive access model which mounts archives as directories over
+/// the underlying access model.
+///
+/// A path under the root of a mounted archive is resolved to the entry in the
+/// archive, e.g. `/path/to/bundle.zip/main.typ` is resolved to the entry
+/// `main.typ` if the archive is mounted at `/path/to/bundle.zip`. Other paths
+/// are resolved by the underlying access model. Use
+/// [`crate::dummy::DummyAccessModel`] as the underlying access model to access
+/// archives only.
+#[derive(Debug, Clone)]
+pub struct ArchiveAccessModel<M> {
+    mounts: Vec<(ImmutPath, Arc<Archive>)>,
+    /// The underlying access model
+    pub inner: M,
+}
+
+impl<M: AccessModel> ArchiveAccessModel<M> {
+    /// Create a new [`ArchiveAccessModel`] with the given inner access model
+    pub fn new(inner: M) -> Self {
+        Self {
+            mounts: vec![],
+            inner,
+        }
+    }
+
+    /// Mount an archive at the given root path, which shadows the archive
+    /// previously mounted at the same path.
+    pub fn mount(&mut self, root: ImmutPath, archive: Archive) {
+        self.mounts.retain(|(r, _)| *r != root);
+        self.mounts.push((root, Arc::new(archive)));
+    }
+
+    /// Resolve a path to the archive it is in and the relative path in the
+    /// archive.
+    fn resolve<'a>(&'a self, src: &'a Path) -> Option<(&'a Archive, &'a Path)> {
+        self.mounts
+            .iter()
+            .rev()
+            .find_map(|(root, archive)| Some((archive.as_ref(), src.strip_prefix(root).ok()?)))
+    }
+}
+
+impl<M: AccessModel> AccessModel for ArchiveAccessModel<M> {
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn mtime(&self, src: &Path) -> FileResult<Time> {
+        match self.resolve(src) {
+            Some((archive, path)) if archive.is_file(path) || archive.is_dir(path) => {
+                Ok(archive.mtime)
+            }
+            Some(_) => Err(FileError::NotFound(src.into())),
+            None => self.inner.mtime(src),
+        }
+    }
+
+    fn is_file(&self, src: &Path) -> FileResult<bool> {
+        match self.resolve(src) {
+            Some((archive, path)) if archive.is_file(path) => Ok(true),
+            Some((archive, path)) if archive.is_dir(path) => Ok(false),
+            Some(_) => Err(FileError::NotFound(src.into())),
+            None => self.inner.is_file(src),
+        }
+    }
+
+    fn real_path(&self, src: &Path) -> FileResult<ImmutPath> {
+        match self.resolve(src) {
+            Some(_) => Ok(src.into()),
+            None => self.inner.real_path(src),
+        }
+    }
+
+    fn content(&self, src: &Path) -> FileResult<Bytes> {
+        match self.resolve(src) {
+            Some((archive, path)) if archive.is_dir(path) => Err(FileError::IsDirectory),
+            Some((archive, path)) => archive.read(path).map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => FileError::NotFound(src.into()),
+                _ => FileError::Other(Some(eco_format!("{err}: {}", src.display()))),
+            }),
+            None => self.inner.content(src),
+        }
+    }
+}
+
+/// Normalizes the name of an archive entry to a relative path, or returns
+/// `None` if the entry is outside of the archive.
+fn normalize(name: &Path) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(path)
+}
+
+/// Whether the data starts with a tar header of POSIX or GNU tar.
+fn is_tar(data: &[u8]) -> bool {
+    data.get(257..262) == Some(b"ustar".as_slice())
+}
+
+/// Reads the decompressed data, failing if it is larger than the declared
+/// size instead of inflating without a bound.
+fn read_bounded(reader: impl Read, size: usize) -> io::Result<Vec<u8>> {
+    let mut content = vec![];
+    reader.take(size as u64 + 1).read_to_end(&mut content)?;
+    if content.len() > size {
+        return Err(invalid_data(
+            "decompressed data larger than its declared size",
+        ));
+    }
+    Ok(content)
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::dummy::DummyAccessModel;
+
+    /// Builds a zip archive of the given entries, whose names ending with `/`
+    /// are directories, and whose contents are deflated if `deflate` is set.
+    fn zip(entries: &[(&str, &[u8], bool)]) -> Vec<u8> {
+        let mut data = vec![];
+        let mut central_dir = vec![];
+        for &(name, content, deflate) in entries {
+            let mut crc = flate2::Crc::new();
+            crc.update(content);
+            let compressed = if deflate {
+                let mut encoder = flate2::write::DeflateEncoder::new(vec![], Default::default());
+                encoder.write_all(content).unwrap();
+                encoder.finish().unwrap()
+            } else {
+                content.to_vec()
+            };
+
+            // The fields from the version needed to extract to the lengths of
+            // the name and the extra field, shared by both headers.
+            let mut fields = vec![20, 0, 0, 0, if deflate { 8 } else { 0 }, 0, 0, 0, 0, 0];
+            fields.extend(crc.sum().to_le_bytes());
+            fields.extend((compressed.len() as u32).to_le_bytes());
+            fields.extend((content.len() as u32).to_le_bytes());
+            fields.extend((name.len() as u16).to_le_bytes());
+            fields.extend([0, 0]);
+
+            central_dir.extend(b"PK\x01\x02\x14\x00");
+            central_dir.extend(&fields);
+            central_dir.extend([0; 10]);
+            central_dir.extend((data.len() as u32).to_le_bytes());
+            central_dir.extend(name.as_bytes());
+
+            data.extend(b"PK\x03\x04");
+            data.extend(&fields);
+            data.extend(name.as_bytes());
+            data.extend(compressed);
+        }
+
+        let offset = data.len() as u32;
+        let count = entries.len() as u16;
+        data.extend(&central_dir);
+        data.extend(b"PK\x05\x06\0\0\0\0");
+        data.extend(count.to_le_bytes());
+        data.extend(count.to_le_bytes());
+        data.extend((central_dir.len() as u32).to_le_bytes());
+        data.extend(offset.to_le_bytes());
+        data.extend([0, 0]);
+        data
+    }
+
+    fn tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(vec![]);
+        for &(name, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], Default::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn load(data: Vec<u8>) -> io::Result<Archive> {
+        Archive::new(Bytes::from(data), Time::UNIX_EPOCH)
+    }
+
+    fn error_of(data: Vec<u8>) -> String {
+        let err = load(data).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{err}");
+        err.to_string()
+    }
+
+    #[test]
+    fn test_tar_archive() {
+        let mut builder = tar::Builder::new(vec![]);
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        builder
+            .append_data(&mut header, "./src/main.typ", b"Hello".as_slice())
+            .unwrap();
+        let data = builder.into_inner().unwrap();
+
+        let archive = Archive::new(Bytes::from(data), Time::UNIX_EPOCH).unwrap();
+        let mut model = ArchiveAccessModel::new(DummyAccessModel);
+        model.mount(Path::new("/bundle.tar").into(), archive);
+
+        let main = Path::new("/bundle.tar/src/main.typ");
+        assert!(model.is_file(main).unwrap());
+        assert_eq!(model.content(main).unwrap().as_slice(), b"Hello");
+        assert!(!model.is_file(Path::new("/bundle.tar/src")).unwrap());
+        assert!(!model.is_file(Path::new("/bundle.tar")).unwrap());
+        assert!(matches!(
+            model.content(Path::new("/bundle.tar/main.typ")),
+            Err(FileError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_zip_archive() {
+        let content = b"#set page(width: 10cm)\n".repeat(20);
+        let data = zip(&[
+            ("src/", b"", false),
+            ("src/main.typ", b"Hello", false),
+            ("src/lib.typ", &content, true),
+            ("assets/empty.txt", b"", true),
+        ]);
+
+        let archive = load(data).unwrap();
+        let paths = archive.file_paths().collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            ["assets/empty.txt", "src/lib.typ", "src/main.typ"].map(Path::new)
+        );
+        assert!(archive.is_dir(Path::new("src")));
+        assert!(archive.is_dir(Path::new("assets")));
+        assert!(archive.is_dir(Path::new("")));
+        assert!(!archive.is_dir(Path::new("src/main.typ")));
+
+        let read = |path: &str| archive.read(Path::new(path)).unwrap();
+        assert_eq!(read("src/main.typ").as_slice(), b"Hello");
+        assert_eq!(read("src/lib.typ").as_slice(), content.as_slice());
+        assert_eq!(read("assets/empty.txt").as_slice(), b"");
+        let err = archive.read(Path::new("src/missing.typ")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_zip_checksum_mismatch() {
+        let mut data = zip(&[("main.typ", b"Hello", false)]);
+        // Corrupts the stored content after the local file header.
+        let pos = 30 + "main.typ".len();
+        data[pos] = b'J';
+
+        let archive = load(data).unwrap();
+        let err = archive.read(Path::new("main.typ")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_tar_gz_archive() {
+        let data = gzip(&tar(&[("main.typ", b"Hello"), ("a/b/c.typ", b"World")]));
+
+        let archive = load(data).unwrap();
+        assert_eq!(
+            archive.read(Path::new("main.typ")).unwrap().as_slice(),
+            b"Hello"
+        );
+        assert_eq!(
+            archive.read(Path::new("a/b/c.typ")).unwrap().as_slice(),
+            b"World"
+        );
+        assert!(archive.is_dir(Path::new("a")));
+        assert!(archive.is_dir(Path::new("a/b")));
+    }
+
+    #[test]
+    fn test_entries_outside_of_archive() {
+        let data = zip(&[("../evil.typ", b"", false), ("/abs.typ", b"", false)]);
+        let archive = load(data).unwrap();
+        assert_eq!(archive.file_paths().count(), 0);
+    }
+
+    #[test]
+    fn test_malformed_archives() {
+        assert_eq!(error_of(b"= Hello".to_vec()), "not a zip or tar archive");
+        assert_eq!(error_of(vec![]), "not a zip or tar archive");
+        assert_eq!(error_of(gzip(b"= Hello")), "not a tar archive in gzip");
+
+        // A truncated gzip stream.
+        let data = gzip(&tar(&[("main.typ", b"Hello")]));
+        let err = load(data[..data.len() / 2].to_vec()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let data = zip(&[("main.typ", b"Hello", false)]);
+        // Without the end of central directory record.
+        assert_eq!(
+            error_of(data[..data.len() - 22].to_vec()),
+            "malformed zip archive"
+        );
+        // With a central directory out of the archive.
+        let mut bad_offset = data.clone();
+        let len = bad_offset.len();
+        bad_offset[len - 6..len - 2].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(error_of(bad_offset), "malformed zip archive");
+        // With an entry larger than the archive.
+        let mut bad_size = data.clone();
+        bad_size[18..22].copy_from_slice(&1000u32.to_le_bytes());
+        let central_dir = data.len() - 22 - 46 - "main.typ".len();
+        bad_size[central_dir + 20..central_dir + 24].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(error_of(bad_size), "malformed zip archive");
+
+        // Encrypted entries and other compression methods.
+        let mut encrypted = data.clone();
+        encrypted[central_dir + 8] = 1;
+        assert_eq!(
+            error_of(encrypted),
+            "encrypted zip archives are not supported"
+        );
+        let mut lzma = data.clone();
+        lzma[central_dir + 10] = 14;
+        assert_eq!(
+            error_of(lzma),
+            "unsupported compression method of zip entry"
+        );
+    }
+
+    #[test]
+    fn test_oversized_decompression() {
+        const OVERSIZED: &str = "decompressed data larger than its declared size";
+
+        // A deflated entry declaring a smaller size than its content.
+        let data = zip(&[("main.typ", &[0; 10000], true)]);
+        let mut bomb = data.clone();
+        bomb[22..26].copy_from_slice(&10u32.to_le_bytes());
+        let central_dir = data.len() - 22 - 46 - "main.typ".len();
+        bomb[central_dir + 24..central_dir + 28].copy_from_slice(&10u32.to_le_bytes());
+        let archive = load(bomb).unwrap();
+        let err = archive.read(Path::new("main.typ")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(err.to_string(), OVERSIZED);
+
+        // A gzip stream declaring a smaller size than its content.
+        let mut bomb = gzip(&tar(&[("main.typ", &[0; 10000])]));
+        let len = bomb.len();
+        bomb[len - 4..].copy_from_slice(&1024u32.to_le_bytes());
+        assert_eq!(error_of(bomb), OVERSIZED);
+    }
+}
@@ -11,6 +11,11 @@ pub mod browser;
 #[cfg(feature = "system")]
 pub mod system;
 
+/// Provides ArchiveAccessModel that mounts zip or tar archives as directories
+/// over the underlying access model.
+#[cfg(feature = "archive")]
+pub mod archive;
+
 /// Provides dummy access model.
 ///
 /// Note: we can still perform compilation with dummy access model, since
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use reflexo::AsCowBytes;
@@ -17,7 +18,8 @@ pub struct CompileOpts {
     /// Additional input arguments to compile the entry file.
     pub inputs: Dict,
 
-    /// Path to font profile for cache
+    /// Path to font profile for cache, which is rewritten when new or
+    /// modified font files are found
     #[serde(rename = "fontProfileCachePath")]
     pub font_profile_cache_path: PathBuf,
 
@@ -33,12 +35,17 @@ pub struct CompileOpts {
     #[serde(rename = "withEmbeddedFonts")]
     #[serde_as(as = "Vec<AsCowBytes>")]
     pub with_embedded_fonts: Vec<Cow<'static, [u8]>>,
+
+    /// Where to fetch packages from
+    #[serde(rename = "packageRegistry", default)]
+    pub package_registry: PackageRegistryOpts,
 }
 
 #[serde_as]
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct CompileFontOpts {
-    /// Path to font profile for cache
+    /// Path to font profile for cache, which is rewritten when new or
+    /// modified font files are found
     #[serde(rename = "fontProfileCachePath")]
     pub font_profile_cache_path: PathBuf,
 
@@ -66,3 +73,110 @@ impl From<CompileOpts> for CompileFontOpts {
         }
     }
 }
+
+/// The default registry serving the `preview` namespace.
+pub const DEFAULT_PACKAGE_REGISTRY: &str = "https://packages.typst.org";
+
+/// The default time in seconds for which the package index is cached, i.e.
+/// one day.
+pub const DEFAULT_PACKAGE_INDEX_TTL: u64 = 24 * 60 * 60;
+
+/// The default number of times to retry a download after network failures.
+pub const DEFAULT_PACKAGE_RETRIES: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct PackageRegistryOpts {
+    /// Base URLs of registries serving the `preview` namespace, tried in
+    /// order. Uses [`DEFAULT_PACKAGE_REGISTRY`] if empty.
+    #[serde(default)]
+    pub registries: Vec<String>,
+
+    /// Sources of packages by namespace, tried in order. Overrides the
+    /// registries if `preview` is given.
+    #[serde(default)]
+    pub namespaces: BTreeMap<String, Vec<PackageSource>>,
+
+    /// Directories containing packages at
+    /// `{dir}/{namespace}/{name}/{version}`, e.g. vendored packages, which
+    /// are searched before the data and cache directories.
+    #[serde(default)]
+    pub paths: Vec<PathBuf>,
+
+    /// Fails instead of downloading packages which are not on disk.
+    #[serde(default)]
+    pub offline: bool,
+
+    /// Path to the lockfile, in which the resolved packages are recorded
+    /// and against which they are verified. See
+    /// [`PackageLock`](crate::package::lock::PackageLock).
+    #[serde(default)]
+    pub lockfile: Option<PathBuf>,
+
+    /// The time in seconds for which the on-disk copy of the package index
+    /// is used without refetching. Uses [`DEFAULT_PACKAGE_INDEX_TTL`] if not
+    /// given.
+    #[serde(default)]
+    pub index_ttl: Option<u64>,
+
+    /// The timeout in seconds of each HTTP request, including reading the
+    /// response. Uses the default timeout of the HTTP client, i.e. 30
+    /// seconds, if not given.
+    #[serde(default)]
+    pub timeout: Option<u64>,
+
+    /// The number of times to retry a download after network failures. Uses
+    /// [`DEFAULT_PACKAGE_RETRIES`] if not given.
+    #[serde(default)]
+    pub retries: Option<u32>,
+}
+
+impl PackageRegistryOpts {
+    /// Gets the sources of packages in the namespace, tried in order.
+    pub fn sources(&self, namespace: &str) -> Vec<PackageSource> {
+        if let Some(sources) = self.namespaces.get(namespace) {
+            return sources.clone();
+        }
+        if namespace != "preview" {
+            return vec![];
+        }
+
+        if self.registries.is_empty() {
+            vec![PackageSource::Url(DEFAULT_PACKAGE_REGISTRY.to_owned())]
+        } else {
+            self.registries
+                .iter()
+                .cloned()
+                .map(PackageSource::Url)
+                .collect()
+        }
+    }
+}
+
+/// A source of packages in a namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PackageSource {
+    /// Downloads packages over HTTP.
+    ///
+    /// The URL is either a template containing `{namespace}`, `{name}` and
+    /// `{version}` placeholders, e.g.
+    /// `https://example.com/{name}-{version}.tar.gz`, or a base URL of a
+    /// registry, from which packages are downloaded at
+    /// `{url}/{namespace}/{name}-{version}.tar.gz`.
+    Url(String),
+    /// Reads packages from a local directory, in which a package is stored
+    /// at `{dir}/{namespace}/{name}/{version}`, the same layout as
+    /// [`PackageRegistryOpts::paths`].
+    Dir(PathBuf),
+}
+
+impl PackageSource {
+    /// Parses a URL or a path to a local directory.
+    pub fn parse(source: &str) -> Self {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            Self::Url(source.to_owned())
+        } else {
+            Self::Dir(PathBuf::from(source))
+        }
+    }
+}
@@ -0,0 +1,502 @@
+//! Glyph packs, which bundle fonts pruned to the glyphs used by documents.
+//!
+//! A font in a glyph pack keeps the tables needed for layout, e.g. `cmap`,
+//! `hmtx` and `GSUB`, but the outlines of glyphs unused by the documents are
+//! dropped from the `glyf` table. Glyph ids are left unchanged, so the
+//! documents lay out and render the same as with the full fonts.
+//!
+//! Fonts with CFF outlines are kept whole.
+//!
+//! The glyphs to keep are usually those lowered by the `glyph2vec` pass, see
+//! `GlyphPackExporter` in `reflexo-typst`. The outlines are pruned here rather
+//! than by a font subsetter, since subsetters for PDF embedding drop the
+//! `cmap` and layout tables, which are needed to lay out documents.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use reflexo::debug_loc::{DataSource, MemoryDataSource};
+use reflexo::error::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+use typst::foundations::Bytes;
+use typst::text::{Coverage, Font, FontInfo};
+
+use super::{BufferFontLoader, FontSlot};
+
+/// The version of the glyph pack format.
+pub const GLYPH_PACK_VERSION: u32 = 1;
+
+/// A bundle of fonts pruned to the glyphs used by documents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlyphPack {
+    /// The version of the glyph pack format.
+    pub version: u32,
+    /// The pruned fonts.
+    pub fonts: Vec<GlyphPackFont>,
+}
+
+/// A font in a glyph pack.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlyphPackFont {
+    /// The info of the original font, which is registered into the font book.
+    ///
+    /// The coverage is restricted to the characters of the kept glyphs, so
+    /// that other characters fall back to other fonts.
+    pub info: FontInfo,
+    /// The sorted ids of the glyphs used by the documents.
+    pub glyphs: Vec<u16>,
+    /// The index of the font in `data`, which is non-zero only if the font is
+    /// kept whole in a font collection.
+    #[serde(default)]
+    pub index: u32,
+    /// The data of the pruned font.
+    #[serde_as(as = "Base64")]
+    pub data: Vec<u8>,
+}
+
+impl GlyphPack {
+    /// Builds a glyph pack from the ids of the glyphs used in fonts.
+    pub fn new(usages: impl IntoIterator<Item = (Font, BTreeSet<u16>)>) -> Self {
+        Self {
+            version: GLYPH_PACK_VERSION,
+            fonts: usages
+                .into_iter()
+                .map(|(font, glyphs)| GlyphPackFont::new(&font, &glyphs))
+                .collect(),
+        }
+    }
+
+    /// Reads a glyph pack in JSON.
+    pub fn from_slice(data: &[u8]) -> ZResult<Self> {
+        let pack: Self = serde_json::from_slice(data).map_err(map_string_err("GlyphPackFmt"))?;
+        pack.check()?;
+        Ok(pack)
+    }
+
+    /// Writes the glyph pack in JSON.
+    pub fn to_vec(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    /// Checks whether the glyph pack is in a supported version.
+    pub fn check(&self) -> ZResult<()> {
+        if self.version != GLYPH_PACK_VERSION {
+            return Err(error_once!(
+                "unsupported glyph pack version",
+                version: self.version
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl GlyphPackFont {
+    /// Prunes a font to the given glyphs.
+    pub fn new(font: &Font, glyphs: &BTreeSet<u16>) -> Self {
+        let mut info = font.info().clone();
+        let (index, data) = match prune_font(font.data(), font.index(), glyphs) {
+            Some((data, kept)) => {
+                if let Some(kept) = kept {
+                    info.coverage = restrict_coverage(font, &kept);
+                }
+                (0, data)
+            }
+            None => (font.index(), font.data().to_vec()),
+        };
+
+        Self {
+            info,
+            glyphs: glyphs.iter().copied().collect(),
+            index,
+            data,
+        }
+    }
+
+    /// Makes a lazily loaded slot of the font to register into a font book.
+    pub fn into_slot(self) -> (FontInfo, FontSlot) {
+        let name = format!("glyph pack: {}", self.info.family);
+        let slot = FontSlot::new_boxed(BufferFontLoader {
+            buffer: Some(Bytes::from(self.data)),
+            index: self.index,
+        })
+        .describe(DataSource::Memory(MemoryDataSource { name }));
+
+        (self.info, slot)
+    }
+}
+
+/// Gets the coverage of the characters that the font maps to the given glyphs.
+fn restrict_coverage(font: &Font, glyphs: &BTreeSet<u16>) -> Coverage {
+    let ttf = font.ttf();
+    let codepoints = font.info().coverage.iter().filter(|&c| {
+        char::from_u32(c)
+            .and_then(|c| ttf.glyph_index(c))
+            .is_some_and(|gid| glyphs.contains(&gid.0))
+    });
+    Coverage::from_vec(codepoints.collect())
+}
+
+type Table<'a> = ([u8; 4], Cow<'a, [u8]>);
+
+/// Extracts a font from the data, in which the outlines of glyphs not in
+/// `glyphs` are removed if the font has a `glyf` table.
+///
+/// Returns the font and the ids of the kept glyphs if the outlines are pruned,
+/// or `None` if the data is malformed.
+fn prune_font(
+    data: &[u8],
+    index: u32,
+    glyphs: &BTreeSet<u16>,
+) -> Option<(Vec<u8>, Option<BTreeSet<u16>>)> {
+    let (version, mut tables) = read_tables(data, index)?;
+    if tables.is_empty() {
+        return None;
+    }
+    // The digital signature is invalidated by the modification.
+    tables.retain(|(tag, _)| tag != b"DSIG");
+
+    let table = |tag: &[u8; 4]| tables.iter().find(|(t, _)| t == tag).map(|(_, d)| d);
+    let mut kept = None;
+    if let (Some(glyf), Some(loca)) = (table(b"glyf"), table(b"loca")) {
+        let head = table(b"head")?;
+        let num_glyphs = read_u16(table(b"maxp")?, 4)?;
+        let (mut glyf, mut loca, mut head, keep) =
+            prune_glyf(glyf, loca, head, num_glyphs, glyphs)?;
+        kept = Some(keep);
+
+        for (tag, d) in &mut tables {
+            match &*tag {
+                b"glyf" => *d = Cow::Owned(std::mem::take(&mut glyf)),
+                b"loca" => *d = Cow::Owned(std::mem::take(&mut loca)),
+                b"head" => *d = Cow::Owned(std::mem::take(&mut head)),
+                _ => {}
+            }
+        }
+    }
+
+    Some((write_sfnt(version, tables), kept))
+}
+
+/// The rebuilt `glyf`, `loca` and `head` tables, and the kept glyphs.
+type PrunedGlyf = (Vec<u8>, Vec<u8>, Vec<u8>, BTreeSet<u16>);
+
+/// Rebuilds the `glyf`, `loca` and `head` tables, keeping `.notdef`, the
+/// glyphs and the components of composite glyphs, which are also returned.
+fn prune_glyf(
+    glyf: &[u8],
+    loca: &[u8],
+    head: &[u8],
+    num_glyphs: u16,
+    glyphs: &BTreeSet<u16>,
+) -> Option<PrunedGlyf> {
+    let long = read_u16(head, 50)? != 0;
+    let offset = |gid: usize| match long {
+        true => read_u32(loca, gid * 4).map(|o| o as usize),
+        false => read_u16(loca, gid * 2).map(|o| o as usize * 2),
+    };
+    let glyph = |gid: usize| glyf.get(offset(gid)?..offset(gid + 1)?);
+
+    let mut keep = BTreeSet::new();
+    let mut queue = vec![0];
+    queue.extend(glyphs.iter().copied());
+    while let Some(gid) = queue.pop() {
+        if gid >= num_glyphs || !keep.insert(gid) {
+            continue;
+        }
+        queue.extend(components(glyph(gid as usize)?));
+    }
+
+    let mut new_glyf = vec![];
+    let mut offsets = vec![0];
+    for gid in 0..num_glyphs as usize {
+        if keep.contains(&(gid as u16)) {
+            new_glyf.extend_from_slice(glyph(gid)?);
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+        offsets.push(new_glyf.len());
+    }
+
+    // The short format stores offsets divided by two in 16 bits.
+    let long = new_glyf.len() > 0x1FFFE;
+    let new_loca = offsets
+        .into_iter()
+        .flat_map(|o| match long {
+            true => (o as u32).to_be_bytes().to_vec(),
+            false => ((o / 2) as u16).to_be_bytes().to_vec(),
+        })
+        .collect();
+    let mut new_head = head.to_vec();
+    new_head[50..52].copy_from_slice(&(long as u16).to_be_bytes());
+
+    Some((new_glyf, new_loca, new_head, keep))
+}
+
+/// Gets the ids of the components of a composite glyph.
+fn components(glyph: &[u8]) -> Vec<u16> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut gids = vec![];
+    // Simple glyphs have a non-negative number of contours.
+    match read_u16(glyph, 0) {
+        Some(n) if (n as i16) < 0 => {}
+        _ => return gids,
+    }
+
+    let mut pos = 10;
+    while let (Some(flags), Some(gid)) = (read_u16(glyph, pos), read_u16(glyph, pos + 2)) {
+        gids.push(gid);
+        pos += match flags & ARG_1_AND_2_ARE_WORDS {
+            0 => 6,
+            _ => 8,
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            pos += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            pos += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            pos += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    gids
+}
+
+/// Reads the tables of the font at `index` in a font file or collection.
+fn read_tables(data: &[u8], index: u32) -> Option<(u32, Vec<Table<'_>>)> {
+    let base = if data.get(0..4)? == b"ttcf" {
+        read_u32(data, 12 + 4 * index as usize)? as usize
+    } else if index == 0 {
+        0
+    } else {
+        return None;
+    };
+
+    let version = read_u32(data, base)?;
+    let num_tables = read_u16(data, base + 4)? as usize;
+    let tables = (0..num_tables)
+        .map(|i| {
+            let record = base + 12 + 16 * i;
+            let tag = data.get(record..record + 4)?.try_into().ok()?;
+            let offset = read_u32(data, record + 8)? as usize;
+            let len = read_u32(data, record + 12)? as usize;
+            Some((
+                tag,
+                Cow::Borrowed(data.get(offset..offset.checked_add(len)?)?),
+            ))
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some((version, tables))
+}
+
+/// Writes a font file with the tables, updating the checksums.
+fn write_sfnt(version: u32, mut tables: Vec<Table>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    for (tag, d) in &mut tables {
+        // The checksum adjustment is computed over the whole file later.
+        if tag == b"head" && d.len() >= 12 {
+            d.to_mut()[8..12].fill(0);
+        }
+    }
+
+    let num_tables = tables.len() as u16;
+    let entry_selector = num_tables.max(1).ilog2() as u16;
+    let search_range = (1u16 << entry_selector) * 16;
+
+    let mut out = vec![];
+    out.extend(version.to_be_bytes());
+    out.extend(num_tables.to_be_bytes());
+    out.extend(search_range.to_be_bytes());
+    out.extend(entry_selector.to_be_bytes());
+    out.extend((num_tables * 16).saturating_sub(search_range).to_be_bytes());
+
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, d) in &tables {
+        out.extend(tag);
+        out.extend(checksum(d).to_be_bytes());
+        out.extend((offset as u32).to_be_bytes());
+        out.extend((d.len() as u32).to_be_bytes());
+        offset += d.len().next_multiple_of(4);
+    }
+
+    let mut head = None;
+    for (tag, d) in &tables {
+        if tag == b"head" {
+            head = Some(out.len());
+        }
+        out.extend_from_slice(d);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(pos) = head.filter(|pos| pos + 12 <= out.len()) {
+        let adjustment = 0xB1B0AFBAu32.wrapping_sub(checksum(&out));
+        out[pos + 8..pos + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    out
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use reflexo::test_utils::{test_font, test_font_tables, triangle, words};
+    use typst::text::{FontBook, FontVariant};
+
+    use super::*;
+
+    /// A composite glyph of the given components with 16-bit offsets.
+    fn composite(components: &[u16]) -> Vec<u8> {
+        let mut glyph = [-1i16, 0, 0, 0, 0]
+            .iter()
+            .flat_map(|w| w.to_be_bytes())
+            .collect::<Vec<_>>();
+        for (i, gid) in components.iter().enumerate() {
+            let more = if i + 1 < components.len() { 0x0020 } else { 0 };
+            glyph.extend((0x0003u16 | more).to_be_bytes());
+            glyph.extend(gid.to_be_bytes());
+            glyph.extend([0; 4]);
+        }
+        glyph
+    }
+
+    #[test]
+    fn test_components() {
+        assert_eq!(components(&triangle(100)), Vec::<u16>::new());
+        assert_eq!(components(&composite(&[2])), [2]);
+        assert_eq!(components(&composite(&[5, 6, 7])), [5, 6, 7]);
+
+        // A component with 8-bit offsets and a scale, followed by one with
+        // 16-bit offsets and a two by two transform.
+        let mut glyph = words(&[0xFFFF, 0, 0, 0, 0]);
+        glyph.extend(words(&[0x0028, 5]));
+        glyph.extend([0, 0]);
+        glyph.extend(words(&[0x4000]));
+        glyph.extend(words(&[0x0081, 6, 0, 0, 0x4000, 0, 0, 0x4000]));
+        assert_eq!(components(&glyph), [5, 6]);
+
+        // Truncated glyphs are read as far as possible.
+        assert_eq!(components(&composite(&[5, 6])[..20]), [5]);
+        assert_eq!(components(&[]), Vec::<u16>::new());
+    }
+
+    #[test]
+    fn test_write_sfnt() {
+        let tables = test_font_tables(&[triangle(100), triangle(200)]);
+        let tables = tables
+            .into_iter()
+            .map(|(tag, data)| (tag, Cow::Owned(data)));
+        let font = write_sfnt(0x00010000, tables.collect());
+        // The checksum adjustment makes the checksum of the file a magic.
+        assert_eq!(checksum(&font), 0xB1B0AFBA);
+
+        let (version, tables) = read_tables(&font, 0).unwrap();
+        assert_eq!(version, 0x00010000);
+        let tags = tables.iter().map(|(tag, _)| tag).collect::<Vec<_>>();
+        assert_eq!(tags.len(), 8);
+        assert!(tags.windows(2).all(|w| w[0] < w[1]));
+
+        // Empty fonts are rejected instead of overflowing the header.
+        let empty = write_sfnt(0x00010000, vec![]);
+        assert_eq!(empty.len(), 12);
+        assert_eq!(prune_font(&empty, 0, &BTreeSet::new()), None);
+        assert_eq!(prune_font(b"not a font", 0, &BTreeSet::new()), None);
+        assert_eq!(prune_font(&font, 1, &BTreeSet::new()), None);
+    }
+
+    #[test]
+    fn test_prune_font() {
+        let glyphs = [
+            triangle(100),
+            triangle(200),
+            triangle(300),
+            composite(&[2]),
+            triangle(400),
+        ];
+        let font = test_font(&glyphs);
+
+        // Keeps `.notdef`, `C` and its component `B`.
+        let (pruned, kept) = prune_font(&font, 0, &BTreeSet::from([3])).unwrap();
+        assert_eq!(kept, Some(BTreeSet::from([0, 2, 3])));
+        assert!(pruned.len() < font.len());
+        assert_eq!(checksum(&pruned), 0xB1B0AFBA);
+
+        let original = Font::new(Bytes::from(font), 0).unwrap();
+        let font = Font::new(Bytes::from(pruned), 0).unwrap();
+        assert_eq!(font.info(), original.info());
+        assert_eq!(font.info().family, "Test");
+
+        let ttf = font.ttf();
+        assert_eq!(ttf.number_of_glyphs(), 5);
+        let bbox = |c: char| ttf.glyph_bounding_box(ttf.glyph_index(c).unwrap());
+        assert!(bbox('A').is_none());
+        assert_eq!(bbox('B').map(|b| b.x_max), Some(300));
+        assert_eq!(bbox('C').map(|b| b.x_max), Some(300));
+        assert!(bbox('D').is_none());
+        // Metrics of pruned glyphs are kept.
+        assert_eq!(
+            ttf.glyph_hor_advance(ttf.glyph_index('D').unwrap()),
+            Some(500)
+        );
+    }
+
+    #[test]
+    fn test_glyph_pack_round_trip() {
+        let font = test_font(&[triangle(100), triangle(200), triangle(300)]);
+        let font = Font::new(Bytes::from(font), 0).unwrap();
+        let pack = GlyphPack::new([(font.clone(), BTreeSet::from([2]))]);
+        assert_eq!(pack.fonts[0].glyphs, [2]);
+        assert_eq!(pack.fonts[0].index, 0);
+
+        let pack = GlyphPack::from_slice(&pack.to_vec()).unwrap();
+        let (info, _) = pack.fonts[0].clone().into_slot();
+        assert_eq!(info.family, font.info().family);
+        assert_eq!(info.coverage.iter().collect::<Vec<_>>(), ['B' as u32]);
+
+        let mut pack = pack;
+        pack.version += 1;
+        assert!(GlyphPack::from_slice(&pack.to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_glyph_pack_fallback() {
+        let glyphs = [triangle(100), triangle(200), triangle(300), composite(&[2])];
+        let font = Font::new(Bytes::from(test_font(&glyphs)), 0).unwrap();
+        let pack = GlyphPack::new([(font.clone(), BTreeSet::from([3]))]);
+        let (info, _) = pack.fonts[0].clone().into_slot();
+        // `B` is kept as a component of `C`.
+        let covered = info.coverage.iter().collect::<Vec<_>>();
+        assert_eq!(covered, ['B' as u32, 'C' as u32]);
+
+        let mut other = font.info().clone();
+        other.family = "Other".into();
+        let book = FontBook::from_infos([info, other]);
+        let select = |text| book.select_fallback(None, FontVariant::default(), text);
+        assert_eq!(select("B"), Some(0));
+        assert_eq!(select("C"), Some(0));
+        // The pruned characters fall back to the other font.
+        assert_eq!(select("A"), Some(1));
+        assert_eq!(select("D"), Some(1));
+    }
+}
@@ -6,6 +6,9 @@ use reflexo::ReadAllOnce;
 /// A FontLoader would help load a font from somewhere.
 pub trait FontLoader {
     fn load(&mut self) -> Option<Font>;
+
+    /// The index of the font in its font file or collection.
+    fn index(&self) -> u32;
 }
 
 /// Load font from a buffer.
@@ -18,6 +21,10 @@ impl FontLoader for BufferFontLoader {
     fn load(&mut self) -> Option<Font> {
         Font::new(self.buffer.take().unwrap(), self.index)
     }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
 }
 
 pub struct LazyBufferFontLoader<R> {
@@ -40,4 +47,8 @@ impl<R: ReadAllOnce + Sized> FontLoader for LazyBufferFontLoader<R> {
         self.read.take().unwrap().read_all(&mut buf).ok()?;
         Font::new(buf.into(), self.index)
     }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
 }
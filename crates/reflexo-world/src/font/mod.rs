@@ -23,3 +23,9 @@ pub use resolver::*;
 
 pub(crate) mod partial_book;
 pub use partial_book::*;
+
+pub(crate) mod glyph_pack;
+pub use glyph_pack::*;
+
+pub(crate) mod usage;
+pub use usage::*;
@@ -1,8 +1,17 @@
+use core::fmt;
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
-use std::{collections::HashMap, time::SystemTime};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 use typst::text::{Coverage, FontInfo};
 
+/// The version of the font profile format.
+pub const FONT_PROFILE_VERSION: &str = "v1beta";
+
 type FontMetaDict = HashMap<String, String>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -119,6 +128,76 @@ impl FontProfileItem {
     pub fn add_info(&mut self, info: FontInfoItem) {
         self.info.push(info);
     }
+
+    /// Profiles the font file at the given path, which should be canonical.
+    ///
+    /// The content hash is computed only if `with_hash` is set, since it
+    /// requires reading the whole file.
+    pub fn from_path(path: &Path, with_hash: bool) -> std::io::Result<Self> {
+        let data = std::fs::read(path)?;
+        let hash = if with_hash {
+            content_hash(&data)
+        } else {
+            String::new()
+        };
+
+        let mut item = Self::new("path", hash);
+        item.set_path(path.to_string_lossy().into_owned());
+        item.set_mtime(std::fs::metadata(path)?.modified()?);
+        for (i, info) in FontInfo::iter(&data).enumerate() {
+            let mut info_item = FontInfoItem::new(info);
+            info_item.set_coverage_hash(get_font_coverage_hash(&info_item.info.coverage));
+            if i != 0 {
+                info_item.set_index(i as u32);
+            }
+            item.add_info(info_item);
+        }
+        Ok(item)
+    }
+
+    /// Checks whether the font file has changed since it was profiled, by the
+    /// modification time, and also by the content hash if `check_hash` is set
+    /// and the item has a hash.
+    pub fn stale(&self, check_hash: bool) -> Option<FontProfileStale> {
+        let Some(path) = self.path() else {
+            return Some(FontProfileStale::Missing);
+        };
+        let Ok(meta) = std::fs::metadata(path) else {
+            return Some(FontProfileStale::Missing);
+        };
+        if !meta.modified().is_ok_and(|m| self.mtime_is_exact(m)) {
+            return Some(FontProfileStale::Modified);
+        }
+        if check_hash && !self.hash.is_empty() {
+            match std::fs::read(path) {
+                Ok(data) if content_hash(&data) == self.hash => {}
+                Ok(..) => return Some(FontProfileStale::Changed),
+                Err(..) => return Some(FontProfileStale::Missing),
+            }
+        }
+        None
+    }
+}
+
+/// The reason why an item of a font profile is out of date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontProfileStale {
+    /// The font file doesn't exist anymore.
+    Missing,
+    /// The modification time of the font file has changed.
+    Modified,
+    /// The content of the font file has changed.
+    Changed,
+}
+
+impl fmt::Display for FontProfileStale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing"),
+            Self::Modified => write!(f, "modified"),
+            Self::Changed => write!(f, "content changed"),
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -128,6 +207,109 @@ pub struct FontProfile {
     pub items: Vec<FontProfileItem>,
 }
 
+impl FontProfile {
+    /// Reads a profile in the format of gzipped JSON.
+    ///
+    /// Profiles generated by another version of the profile format or the
+    /// compiler are rejected, since the font information may be changed.
+    pub fn read(path: &Path, build_info: &str) -> Result<Self, FontProfileError> {
+        let io_err = |err: std::io::Error| match err.kind() {
+            std::io::ErrorKind::NotFound => FontProfileError::NotFound(path.to_owned()),
+            _ => FontProfileError::Io(path.to_owned(), err),
+        };
+
+        let file = std::fs::File::open(path).map_err(io_err)?;
+        let mut content = vec![];
+        flate2::read::GzDecoder::new(file)
+            .read_to_end(&mut content)
+            .map_err(|err| FontProfileError::Malformed(path.to_owned(), err.to_string()))?;
+        let profile: Self = serde_json::from_slice(&content)
+            .map_err(|err| FontProfileError::Malformed(path.to_owned(), err.to_string()))?;
+
+        if profile.version != FONT_PROFILE_VERSION || profile.build_info != build_info {
+            return Err(FontProfileError::VersionMismatch {
+                path: path.to_owned(),
+                expected: format!("{FONT_PROFILE_VERSION} ({build_info})"),
+                found: format!("{} ({})", profile.version, profile.build_info),
+            });
+        }
+        Ok(profile)
+    }
+
+    /// Writes the profile in the format of gzipped JSON.
+    ///
+    /// The profile is written to a temporary file in the same directory and
+    /// then renamed, so readers never see a partially written profile.
+    pub fn write(&self, path: &Path) -> Result<(), FontProfileError> {
+        let io_err = |err| FontProfileError::Io(path.to_owned(), err);
+
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        if let Some(dir) = dir {
+            std::fs::create_dir_all(dir).map_err(io_err)?;
+        }
+
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        let content = serde_json::to_vec(self).unwrap();
+        encoder.write_all(&content).map_err(io_err)?;
+        let content = encoder.finish().map_err(io_err)?;
+
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+        std::fs::write(&tmp_path, content).map_err(io_err)?;
+        std::fs::rename(&tmp_path, path).map_err(|err| {
+            let _ = std::fs::remove_file(&tmp_path);
+            io_err(err)
+        })
+    }
+}
+
+/// An error when reading or writing a [`FontProfile`].
+#[derive(Debug)]
+pub enum FontProfileError {
+    /// The profile doesn't exist.
+    NotFound(PathBuf),
+    /// The profile cannot be read or written.
+    Io(PathBuf, std::io::Error),
+    /// The profile is not a gzipped JSON font profile.
+    Malformed(PathBuf, String),
+    /// The profile is generated by another version of the profile format or
+    /// the compiler.
+    VersionMismatch {
+        path: PathBuf,
+        expected: String,
+        found: String,
+    },
+}
+
+impl fmt::Display for FontProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(path) => write!(f, "font profile {} not found", path.display()),
+            Self::Io(path, err) => {
+                write!(f, "cannot access font profile {}: {err}", path.display())
+            }
+            Self::Malformed(path, err) => {
+                write!(f, "malformed font profile {}: {err}", path.display())
+            }
+            Self::VersionMismatch {
+                path,
+                expected,
+                found,
+            } => write!(
+                f,
+                "font profile {} is generated by version {found}, expected {expected}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FontProfileError {}
+
+fn content_hash(data: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(sha2::Sha256::digest(data)))
+}
+
 pub fn get_font_coverage_hash(coverage: &Coverage) -> String {
     let mut coverage_hash = sha2::Sha256::new();
     coverage
@@ -136,3 +318,104 @@ pub fn get_font_coverage_hash(coverage: &Coverage) -> String {
     let coverage_hash = coverage_hash.finalize();
     format!("sha256:{:x}", coverage_hash)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("font-profile-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn profile(build_info: &str) -> FontProfile {
+        let mut item = FontProfileItem::new("path", "sha256:00".to_owned());
+        item.set_path("/fonts/a.ttf".to_owned());
+        FontProfile {
+            version: FONT_PROFILE_VERSION.to_owned(),
+            build_info: build_info.to_owned(),
+            items: vec![item],
+        }
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_read_write_round_trip() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join("fonts.profile.json.gz");
+
+        profile("0.1.0").write(&path).unwrap();
+        let read = FontProfile::read(&path, "0.1.0").unwrap();
+        assert_eq!(read.items.len(), 1);
+        assert_eq!(read.items[0].path().unwrap(), "/fonts/a.ttf");
+        assert_eq!(read.items[0].hash(), "sha256:00");
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_not_found() {
+        let dir = temp_dir("not-found");
+        let path = dir.join("missing.json.gz");
+
+        let err = FontProfile::read(&path, "0.1.0").unwrap_err();
+        assert!(matches!(err, FontProfileError::NotFound(p) if p == path));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_malformed() {
+        let dir = temp_dir("malformed");
+
+        let not_gzip = dir.join("not-gzip.json.gz");
+        std::fs::write(&not_gzip, b"{\"version\": \"v1beta\"}").unwrap();
+        let err = FontProfile::read(&not_gzip, "0.1.0").unwrap_err();
+        assert!(matches!(err, FontProfileError::Malformed(p, _) if p == not_gzip));
+
+        let not_json = dir.join("not-json.json.gz");
+        std::fs::write(&not_json, gzip(b"not a profile")).unwrap();
+        let err = FontProfile::read(&not_json, "0.1.0").unwrap_err();
+        assert!(matches!(err, FontProfileError::Malformed(p, _) if p == not_json));
+
+        let truncated = dir.join("truncated.json.gz");
+        let content = gzip(&serde_json::to_vec(&profile("0.1.0")).unwrap());
+        std::fs::write(&truncated, &content[..content.len() / 2]).unwrap();
+        let err = FontProfile::read(&truncated, "0.1.0").unwrap_err();
+        assert!(matches!(err, FontProfileError::Malformed(p, _) if p == truncated));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_version_mismatch() {
+        let dir = temp_dir("version-mismatch");
+
+        let path = dir.join("build.json.gz");
+        profile("0.1.0").write(&path).unwrap();
+        let err = FontProfile::read(&path, "0.2.0").unwrap_err();
+        let FontProfileError::VersionMismatch {
+            expected, found, ..
+        } = err
+        else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(expected, format!("{FONT_PROFILE_VERSION} (0.2.0)"));
+        assert_eq!(found, format!("{FONT_PROFILE_VERSION} (0.1.0)"));
+
+        let path = dir.join("format.json.gz");
+        let mut old = profile("0.1.0");
+        old.version = "v0".to_owned();
+        old.write(&path).unwrap();
+        let err = FontProfile::read(&path, "0.1.0").unwrap_err();
+        assert!(matches!(err, FontProfileError::VersionMismatch { .. }));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
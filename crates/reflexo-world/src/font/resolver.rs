@@ -7,9 +7,10 @@ use std::{
 
 use comemo::Prehashed;
 use reflexo::debug_loc::DataSource;
+use reflexo::error::prelude::*;
 use typst::text::{Font, FontBook, FontInfo};
 
-use super::{BufferFontLoader, FontProfile, FontSlot, PartialFontBook};
+use super::{BufferFontLoader, FontProfile, FontSlot, GlyphPack, PartialFontBook};
 use crate::Bytes;
 
 /// A FontResolver can resolve a font by index.
@@ -103,6 +104,17 @@ impl FontResolverImpl {
         None
     }
 
+    /// Describes the source of the font at the index of the font book.
+    pub fn describe_font_by_id(&self, idx: usize) -> Option<Arc<DataSource>> {
+        self.fonts.get(idx)?.description.clone()
+    }
+
+    /// Gets the index in its font file of the font at the index of the font
+    /// book, without loading the font.
+    pub fn font_index_by_id(&self, idx: usize) -> Option<u32> {
+        Some(self.fonts.get(idx)?.index())
+    }
+
     pub fn modify_font_data(&mut self, idx: usize, buffer: Bytes) {
         let mut font_book = self.partial_book.lock().unwrap();
         for (i, info) in FontInfo::iter(buffer.as_slice()).enumerate() {
@@ -171,8 +183,18 @@ impl FontResolverImpl {
         self.fonts = font_slots;
     }
 
-    pub fn add_glyph_packs(&mut self) {
-        todo!()
+    /// Adds the fonts in glyph packs, see [`GlyphPack`].
+    pub fn add_glyph_packs(&mut self, packs: Vec<GlyphPack>) -> ZResult<()> {
+        for pack in &packs {
+            pack.check()?;
+        }
+
+        for font in packs.into_iter().flat_map(|pack| pack.fonts) {
+            let (info, slot) = font.into_slot();
+            self.append_font(info, slot);
+        }
+        self.rebuild();
+        Ok(())
     }
 }
 
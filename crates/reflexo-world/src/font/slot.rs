@@ -4,6 +4,7 @@ use std::sync::Arc;
 use reflexo::debug_loc::DataSource;
 use reflexo::QueryRef;
 use typst::text::Font;
+use typst_timing::timed;
 
 use crate::font::FontLoader;
 
@@ -12,12 +13,14 @@ type FontSlotInner = QueryRef<Option<Font>, (), Box<dyn FontLoader + Send>>;
 /// Lazy Font Reference, load as needed.
 pub struct FontSlot {
     inner: FontSlotInner,
+    index: u32,
     pub description: Option<Arc<DataSource>>,
 }
 
 impl FontSlot {
     pub fn with_value(f: Option<Font>) -> Self {
         Self {
+            index: f.as_ref().map_or(0, Font::index),
             inner: FontSlotInner::with_value(f),
             description: None,
         }
@@ -25,6 +28,7 @@ impl FontSlot {
 
     pub fn new(f: Box<dyn FontLoader + Send>) -> Self {
         Self {
+            index: f.index(),
             inner: FontSlotInner::with_context(f),
             description: None,
         }
@@ -37,10 +41,17 @@ impl FontSlot {
     pub fn describe(self, desc: DataSource) -> Self {
         Self {
             inner: self.inner,
+            index: self.index,
             description: Some(Arc::new(desc)),
         }
     }
 
+    /// Gets the index of the font in its font file or collection, without
+    /// loading the font.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
     /// Gets the reference to the font load result (possible uninitialized).
     ///
     /// Returns `None` if the cell is empty, or being initialized. This
@@ -54,7 +65,9 @@ impl FontSlot {
 
     /// Gets or make the font load result.
     pub fn get_or_init(&self) -> Option<Font> {
-        let res = self.inner.compute_with_context(|mut c| Ok(c.load()));
+        let res = self
+            .inner
+            .compute_with_context(|mut c| Ok(timed!("load font", c.load())));
         { unsafe { res.unwrap_unchecked() } }.clone()
     }
 }
@@ -1,7 +1,6 @@
 use std::{
     borrow::Cow,
-    collections::HashMap,
-    fs::File,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
 };
@@ -9,8 +8,7 @@ use std::{
 use fontdb::Database;
 use reflexo::debug_loc::{DataSource, MemoryDataSource};
 use reflexo::error::prelude::*;
-use reflexo_vfs::system::LazyFile;
-use sha2::{Digest, Sha256};
+use reflexo_vfs::{archive::Archive, system::LazyFile};
 use typst::{
     diag::{FileError, FileResult},
     foundations::Bytes,
@@ -18,64 +16,75 @@ use typst::{
 };
 
 use super::{
-    BufferFontLoader, FontProfile, FontProfileItem, FontResolverImpl, FontSlot,
-    LazyBufferFontLoader, PartialFontBook,
+    BufferFontLoader, FontProfile, FontProfileError, FontProfileItem, FontResolverImpl, FontSlot,
+    LazyBufferFontLoader, PartialFontBook, FONT_PROFILE_VERSION,
 };
 use crate::{build_info, config::CompileFontOpts};
 
 #[derive(Debug, Default)]
 struct FontProfileRebuilder {
+    /// The items of the loaded profile, which are reused if up to date.
     path_items: HashMap<PathBuf, FontProfileItem>,
+    /// The paths of the font files in the new profile.
+    recorded: HashSet<PathBuf>,
     pub profile: FontProfile,
     can_profile: bool,
+    /// Whether to hash the content of the profiled font files.
+    with_hash: bool,
+    /// The number of items reused from the loaded profile.
+    reused: usize,
+    /// The number of items profiled, which are new or out of date.
+    regenerated: usize,
 }
 
 impl FontProfileRebuilder {
-    /// Index the fonts in the file at the given path.
-    #[allow(dead_code)]
-    fn search_file(&mut self, path: impl AsRef<Path>) -> Option<&FontProfileItem> {
-        let path = path.as_ref().canonicalize().unwrap();
-        if let Some(item) = self.path_items.get(&path) {
-            return Some(item);
+    /// Profiles the font file at the given path, reusing the loaded item if
+    /// the file is not modified since then. When hashing, loaded items
+    /// without a content hash are regenerated as well.
+    fn search_file(&mut self, path: &Path) {
+        let Ok(path) = path.canonicalize() else {
+            return;
+        };
+        if !self.recorded.insert(path.clone()) {
+            return;
         }
 
-        if let Ok(mut file) = File::open(&path) {
-            let hash = if self.can_profile {
-                let mut hasher = Sha256::new();
-                let _bytes_written = std::io::copy(&mut file, &mut hasher).unwrap();
-                let hash = hasher.finalize();
-
-                format!("sha256:{}", hex::encode(hash))
-            } else {
-                "".to_owned()
-            };
+        if let Some(item) = self.path_items.remove(&path) {
+            let has_hash = !self.with_hash || !item.hash().is_empty();
+            if has_hash && item.stale(false).is_none() {
+                self.profile.items.push(item);
+                self.reused += 1;
+                return;
+            }
+        }
 
-            let mut profile_item = FontProfileItem::new("path", hash);
-            profile_item.set_path(path.to_str().unwrap().to_owned());
-            profile_item.set_mtime(file.metadata().unwrap().modified().unwrap());
-
-            // println!("searched font: {:?}", path);
-
-            // if let Ok(mmap) = unsafe { Mmap::map(&file) } {
-            //     for (i, info) in FontInfo::iter(&mmap).enumerate() {
-            //         let coverage_hash = get_font_coverage_hash(&info.coverage);
-            //         let mut ff = FontInfoItem::new(info);
-            //         ff.set_coverage_hash(coverage_hash);
-            //         if i != 0 {
-            //             ff.set_index(i as u32);
-            //         }
-            //         profile_item.add_info(ff);
-            //     }
-            // }
-
-            self.profile.items.push(profile_item);
-            return self.profile.items.last();
+        match FontProfileItem::from_path(&path, self.with_hash) {
+            Ok(item) => {
+                self.profile.items.push(item);
+                self.regenerated += 1;
+            }
+            Err(err) => log::warn!("failed to profile font {}: {err}", path.display()),
         }
+    }
 
-        None
+    /// Whether the new profile differs from the loaded one, i.e. some items
+    /// are regenerated, or some loaded items are not found anymore.
+    fn is_dirty(&self) -> bool {
+        self.regenerated > 0 || !self.path_items.is_empty()
     }
 }
 
+/// The changes to a loaded font profile made by searching fonts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FontProfileStats {
+    /// The number of items reused from the loaded profile.
+    pub reused: usize,
+    /// The number of items profiled, which are new or out of date.
+    pub regenerated: usize,
+    /// The number of loaded items whose font files are not found anymore.
+    pub removed: usize,
+}
+
 /// Searches for fonts.
 #[derive(Debug)]
 pub struct SystemFontSearcher {
@@ -91,7 +100,7 @@ impl SystemFontSearcher {
     /// Create a new, empty system searcher.
     pub fn new() -> Self {
         let mut profile_rebuilder = FontProfileRebuilder::default();
-        "v1beta".clone_into(&mut profile_rebuilder.profile.version);
+        FONT_PROFILE_VERSION.clone_into(&mut profile_rebuilder.profile.version);
         profile_rebuilder.profile.build_info = build_info::VERSION.to_string();
         let db = Database::new();
 
@@ -105,14 +114,20 @@ impl SystemFontSearcher {
     }
 
     /// Resolve fonts from given options.
+    ///
+    /// If a font profile cache path is given, the font files found are
+    /// profiled without content hashes, and the profile is rewritten when new
+    /// or modified font files are found. Note that profiling re-reads these
+    /// font files, and the profile is not used to skip searching fonts.
     pub fn resolve_opts(&mut self, opts: CompileFontOpts) -> ZResult<()> {
-        if opts
-            .font_profile_cache_path
-            .to_str()
-            .map(|e| !e.is_empty())
-            .unwrap_or_default()
-        {
+        let profile_path = opts.font_profile_cache_path;
+        let can_profile = !profile_path.as_os_str().is_empty();
+        if can_profile {
             self.set_can_profile(true);
+            match self.add_profile_by_path(&profile_path) {
+                Ok(()) | Err(FontProfileError::NotFound(..)) => {}
+                Err(err) => log::warn!("{err}, regenerating the font profile"),
+            }
         }
 
         // Note: the order of adding fonts is important.
@@ -133,6 +148,14 @@ impl SystemFontSearcher {
         // flush source1 and source2 before adding source3
         self.flush();
 
+        // The font profile cache is best-effort, so failing to write it
+        // doesn't fail the compilation.
+        if can_profile && self.profile_rebuilder.is_dirty() {
+            if let Err(err) = self.write_profile(&profile_path) {
+                log::warn!("{err}");
+            }
+        }
+
         // Source3: add the fonts in memory.
         for font_data in opts.with_embedded_fonts {
             self.add_memory_font(match font_data {
@@ -144,42 +167,54 @@ impl SystemFontSearcher {
         Ok(())
     }
 
+    /// Sets whether to profile the font files found by searching, see
+    /// [`FontProfile`].
     pub fn set_can_profile(&mut self, can_profile: bool) {
         self.profile_rebuilder.can_profile = can_profile;
     }
 
-    pub fn add_profile_by_path(&mut self, profile_path: &Path) {
-        // let begin = std::time::Instant::now();
-        // profile_path is in format of json.gz
-        let profile_file = File::open(profile_path).unwrap();
-        let profile_gunzip = flate2::read::GzDecoder::new(profile_file);
-        let profile: FontProfile = serde_json::from_reader(profile_gunzip).unwrap();
+    /// Sets whether to hash the content of the profiled font files, which is
+    /// needed to detect changed font files regardless of their modification
+    /// times. It is off by default since it reads every profiled font file.
+    pub fn set_profile_hash(&mut self, with_hash: bool) {
+        self.profile_rebuilder.with_hash = with_hash;
+    }
 
-        if self.profile_rebuilder.profile.version != profile.version
-            || self.profile_rebuilder.profile.build_info != profile.build_info
-        {
-            return;
-        }
+    /// Loads a font profile, whose items are reused when profiling the font
+    /// files not modified since then. The items of modified font files are
+    /// regenerated.
+    pub fn add_profile_by_path(&mut self, profile_path: &Path) -> Result<(), FontProfileError> {
+        let profile = FontProfile::read(profile_path, build_info::VERSION)?;
 
         for item in profile.items {
-            let path = match item.path() {
-                Some(path) => path,
-                None => continue,
+            let Some(path) = item.path() else {
+                continue;
             };
             let path = PathBuf::from(path);
+            self.profile_rebuilder.path_items.insert(path, item);
+        }
+        Ok(())
+    }
 
-            if let Ok(m) = std::fs::metadata(&path) {
-                let modified = m.modified().ok();
-                if !modified.map(|m| item.mtime_is_exact(m)).unwrap_or_default() {
-                    continue;
-                }
-            }
+    /// Gets the profile of the font files found by searching.
+    pub fn profile(&self) -> &FontProfile {
+        &self.profile_rebuilder.profile
+    }
 
-            self.profile_rebuilder.path_items.insert(path, item.clone());
-            self.profile_rebuilder.profile.items.push(item);
+    /// Gets the changes to the loaded profile, which are complete after the
+    /// searched fonts are flushed.
+    pub fn profile_stats(&self) -> FontProfileStats {
+        let rebuilder = &self.profile_rebuilder;
+        FontProfileStats {
+            reused: rebuilder.reused,
+            regenerated: rebuilder.regenerated,
+            removed: rebuilder.path_items.len(),
         }
-        // let end = std::time::Instant::now();
-        // println!("profile_rebuilder init took {:?}", end - begin);
+    }
+
+    /// Writes the profile of the font files found by searching.
+    pub fn write_profile(&self, profile_path: &Path) -> Result<(), FontProfileError> {
+        self.profile_rebuilder.profile.write(profile_path)
     }
 
     #[cfg(feature = "lazy-fontdb")]
@@ -251,7 +286,8 @@ impl SystemFontSearcher {
                 // println!("searched font: {idx} {:?}", path);
 
                 Some((
-                    info?,
+                    path.to_owned(),
+                    info,
                     FontSlot::new_boxed(LazyBufferFontLoader::new(
                         LazyFile::new(path.to_owned()),
                         face.index(),
@@ -260,9 +296,15 @@ impl SystemFontSearcher {
             })
             .collect::<Vec<_>>()
             .into_iter()
-            .for_each(|(info, font)| {
-                self.book.push(info);
-                self.fonts.push(font);
+            .for_each(|(path, info, font)| {
+                if self.profile_rebuilder.can_profile {
+                    self.profile_rebuilder.search_file(&path);
+                }
+
+                if let Some(info) = info {
+                    self.book.push(info);
+                    self.fonts.push(font);
+                }
             });
 
         self.db = Database::new();
@@ -288,6 +330,10 @@ impl SystemFontSearcher {
 
             // println!("searched font: {idx} {:?}", path);
 
+            if self.profile_rebuilder.can_profile {
+                self.profile_rebuilder.search_file(path);
+            }
+
             if let Some(info) = info {
                 self.book.push(info);
                 self.fonts.push(
@@ -307,6 +353,32 @@ impl SystemFontSearcher {
 
     /// Add an in-memory font.
     pub fn add_memory_font(&mut self, data: Bytes) {
+        self.add_named_memory_font(data, "<memory>");
+    }
+
+    /// Add the font files in an archive, e.g. fonts bundled with a workspace
+    /// into a zip file. Like [`Self::add_memory_font`], the searcher must be
+    /// flushed before.
+    pub fn add_archive_fonts(&mut self, archive: &Archive) {
+        let is_font = |path: &Path| {
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            matches!(ext.to_lowercase().as_str(), "ttf" | "otf" | "ttc" | "otc")
+        };
+
+        for path in archive.file_paths().filter(|path| is_font(path)) {
+            match archive.read(path) {
+                Ok(data) => {
+                    self.add_named_memory_font(data, &format!("archive: {}", path.display()))
+                }
+                Err(err) => log::warn!("failed to read font {path:?} in archive: {err}"),
+            }
+        }
+    }
+
+    fn add_named_memory_font(&mut self, data: Bytes, name: &str) {
         if !self.db.is_empty() {
             panic!("dirty font search state, please flush the searcher before adding memory fonts");
         }
@@ -319,7 +391,7 @@ impl SystemFontSearcher {
                     index: index as u32,
                 })
                 .describe(DataSource::Memory(MemoryDataSource {
-                    name: "<memory>".to_owned(),
+                    name: name.to_owned(),
                 })),
             );
         }
@@ -0,0 +1,141 @@
+use std::collections::BTreeSet;
+
+use typst::layout::{Frame, FrameItem};
+use typst::model::Document;
+use typst::text::Font;
+
+/// The glyphs of a font used by a document.
+#[derive(Debug, Clone)]
+pub struct FontUsage {
+    /// The used font.
+    pub font: Font,
+    /// The ids of the used glyphs.
+    pub glyphs: BTreeSet<u16>,
+    /// The characters shaped into the used glyphs.
+    pub chars: BTreeSet<char>,
+}
+
+impl FontUsage {
+    /// Collects the fonts used by a document, in the order they first appear.
+    pub fn collect(doc: &Document) -> Vec<Self> {
+        let mut usages = vec![];
+        for page in &doc.pages {
+            Self::collect_frame(&page.frame, &mut usages);
+        }
+        usages
+    }
+
+    fn collect_frame(frame: &Frame, usages: &mut Vec<Self>) {
+        for (_, item) in frame.items() {
+            let text = match item {
+                FrameItem::Group(group) => {
+                    Self::collect_frame(&group.frame, usages);
+                    continue;
+                }
+                FrameItem::Text(text) => text,
+                _ => continue,
+            };
+
+            let usage = match usages.iter().position(|u| u.font == text.font) {
+                Some(idx) => &mut usages[idx],
+                None => {
+                    usages.push(Self {
+                        font: text.font.clone(),
+                        glyphs: BTreeSet::new(),
+                        chars: BTreeSet::new(),
+                    });
+                    usages.last_mut().unwrap()
+                }
+            };
+
+            for glyph in &text.glyphs {
+                usage.glyphs.insert(glyph.id);
+                let chars = text.text.get(glyph.range()).unwrap_or_default().chars();
+                usage.chars.extend(chars);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reflexo::test_utils::{test_font, triangle};
+    use typst::foundations::Bytes;
+    use typst::layout::{Abs, Em, GroupItem, Page, Point, Size};
+    use typst::syntax::Span;
+    use typst::text::{Glyph, Lang, TextItem};
+    use typst::visualize::Color;
+
+    use super::*;
+
+    fn text(font: &Font, text: &str, glyphs: &[(u16, std::ops::Range<u16>)]) -> FrameItem {
+        FrameItem::Text(TextItem {
+            font: font.clone(),
+            size: Abs::pt(10.0),
+            fill: Color::BLACK.into(),
+            stroke: None,
+            lang: Lang::ENGLISH,
+            text: text.into(),
+            glyphs: glyphs
+                .iter()
+                .map(|(id, range)| Glyph {
+                    id: *id,
+                    x_advance: Em::new(0.5),
+                    x_offset: Em::zero(),
+                    range: range.clone(),
+                    span: (Span::detached(), 0),
+                })
+                .collect(),
+        })
+    }
+
+    fn page(items: Vec<FrameItem>) -> Page {
+        let mut frame = Frame::soft(Size::zero());
+        for item in items {
+            frame.push(Point::zero(), item);
+        }
+        Page {
+            frame,
+            numbering: None,
+            number: 1,
+        }
+    }
+
+    #[test]
+    fn test_collect() {
+        let glyphs = vec![triangle(100); 5];
+        let a = Font::new(Bytes::from(test_font(&glyphs)), 0).unwrap();
+        let b = Font::new(Bytes::from(test_font(&glyphs[..4])), 0).unwrap();
+        assert_ne!(a, b);
+
+        let mut group = Frame::soft(Size::zero());
+        group.push(Point::zero(), text(&b, "A", &[(1, 0..1)]));
+        group.push(Point::zero(), text(&a, "B", &[(2, 0..1)]));
+
+        let doc = Document {
+            pages: vec![
+                page(vec![
+                    text(&a, "AB", &[(1, 0..1), (2, 1..2)]),
+                    FrameItem::Group(GroupItem::new(group)),
+                ]),
+                // A ligature of two characters, and a missing glyph.
+                page(vec![text(&a, "CD\u{20AC}", &[(3, 0..2), (0, 2..5)])]),
+            ],
+            ..Default::default()
+        };
+
+        let usages = FontUsage::collect(&doc);
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].font, a);
+        assert_eq!(usages[0].glyphs, BTreeSet::from([0, 1, 2, 3]));
+        assert_eq!(
+            usages[0].chars,
+            BTreeSet::from(['A', 'B', 'C', 'D', '\u{20AC}'])
+        );
+        assert_eq!(usages[1].font, b);
+        assert_eq!(usages[1].glyphs, BTreeSet::from([1]));
+        assert_eq!(usages[1].chars, BTreeSet::from(['A']));
+
+        assert!(FontUsage::collect(&Document::default()).is_empty());
+    }
+}
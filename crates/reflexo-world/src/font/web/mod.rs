@@ -9,7 +9,8 @@ use typst::text::{
 use wasm_bindgen::prelude::*;
 
 use super::{
-    BufferFontLoader, FontLoader, FontProfile, FontResolverImpl, FontSlot, PartialFontBook,
+    BufferFontLoader, FontLoader, FontProfile, FontResolverImpl, FontSlot, GlyphPack,
+    PartialFontBook, FONT_PROFILE_VERSION,
 };
 use crate::font::cache::FontInfoCache;
 use crate::font::info::typst_typographic_family;
@@ -372,6 +373,10 @@ impl FontLoader for WebFontLoader {
 
         Font::new(blob, self.index)
     }
+
+    fn index(&self) -> u32 {
+        self.index
+    }
 }
 
 /// Searches for fonts.
@@ -386,7 +391,7 @@ impl BrowserFontSearcher {
     /// Create a new, empty system searcher.
     pub fn new() -> Self {
         let profile = FontProfile {
-            version: "v1beta".to_owned(),
+            version: FONT_PROFILE_VERSION.to_owned(),
             ..Default::default()
         };
         let mut searcher = Self {
@@ -470,10 +475,17 @@ impl BrowserFontSearcher {
         }
     }
 
-    pub async fn add_glyph_pack(&mut self) -> ZResult<()> {
-        Err(error_once!(
-            "BrowserFontSearcher.add_glyph_pack is not implemented"
-        ))
+    /// Adds the fonts in a glyph pack, see [`GlyphPack`].
+    pub async fn add_glyph_pack(&mut self, pack: GlyphPack) -> ZResult<()> {
+        pack.check()?;
+
+        for font in pack.fonts {
+            let (info, slot) = font.into_slot();
+            self.book.push(info);
+            self.fonts.push(slot);
+        }
+
+        Ok(())
     }
 }
 
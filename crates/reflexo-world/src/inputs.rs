@@ -0,0 +1,126 @@
+use std::path::Path;
+
+use typst::diag::{eco_format, StrResult};
+use typst::foundations::{Dict, Value};
+
+/// The format of structured data for `sys.inputs`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputsFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl InputsFormat {
+    /// Guesses the format by the extension of a path.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Some(match ext.as_str() {
+            "json" => Self::Json,
+            "toml" => Self::Toml,
+            "yaml" | "yml" => Self::Yaml,
+            _ => return None,
+        })
+    }
+}
+
+/// Parses structured data into the dictionary for `sys.inputs`.
+///
+/// Objects, arrays, numbers, booleans and strings in the data are converted
+/// into the corresponding typst values. The data must be a dictionary at the
+/// top level.
+pub fn parse_inputs(data: &[u8], format: InputsFormat) -> StrResult<Dict> {
+    let value: Value = match format {
+        InputsFormat::Json => serde_json::from_slice(data).map_err(|err| eco_format!("{err}"))?,
+        InputsFormat::Toml => {
+            let data = std::str::from_utf8(data).map_err(|err| eco_format!("{err}"))?;
+            toml::from_str(data).map_err(|err| eco_format!("{err}"))?
+        }
+        InputsFormat::Yaml => serde_yaml::from_slice(data).map_err(|err| eco_format!("{err}"))?,
+    };
+
+    match value {
+        Value::Dict(dict) => Ok(dict),
+        value => Err(eco_format!(
+            "inputs must be a dictionary, found {}",
+            value.ty()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::foundations::{array, dict};
+
+    use super::*;
+
+    fn expected() -> Dict {
+        dict! {
+            "title" => "Report",
+            "draft" => true,
+            "version" => 2,
+            "ratio" => 0.5,
+            "authors" => array!["Alice", "Bob"],
+            "meta" => dict! { "lang" => "en" },
+        }
+    }
+
+    #[test]
+    fn test_parse_json_inputs() {
+        let data = br#"{
+            "title": "Report", "draft": true, "version": 2, "ratio": 0.5,
+            "authors": ["Alice", "Bob"], "meta": { "lang": "en" }
+        }"#;
+        assert_eq!(parse_inputs(data, InputsFormat::Json), Ok(expected()));
+    }
+
+    #[test]
+    fn test_parse_toml_inputs() {
+        let data = br#"
+            title = "Report"
+            draft = true
+            version = 2
+            ratio = 0.5
+            authors = ["Alice", "Bob"]
+            [meta]
+            lang = "en"
+        "#;
+        assert_eq!(parse_inputs(data, InputsFormat::Toml), Ok(expected()));
+    }
+
+    #[test]
+    fn test_parse_yaml_inputs() {
+        let data = b"
+title: Report
+draft: true
+version: 2
+ratio: 0.5
+authors: [Alice, Bob]
+meta:
+  lang: en
+";
+        assert_eq!(parse_inputs(data, InputsFormat::Yaml), Ok(expected()));
+    }
+
+    #[test]
+    fn test_parse_inputs_errors() {
+        let err = parse_inputs(b"[1, 2]", InputsFormat::Json).unwrap_err();
+        assert_eq!(err, "inputs must be a dictionary, found array");
+        assert!(parse_inputs(b"{", InputsFormat::Json).is_err());
+        assert!(parse_inputs(b"title = ", InputsFormat::Toml).is_err());
+        assert!(parse_inputs(b"\xff", InputsFormat::Toml).is_err());
+        let err = parse_inputs(b"text", InputsFormat::Yaml).unwrap_err();
+        assert_eq!(err, "inputs must be a dictionary, found string");
+    }
+
+    #[test]
+    fn test_inputs_format_from_path() {
+        let format = |path: &str| InputsFormat::from_path(Path::new(path));
+        assert_eq!(format("inputs.json"), Some(InputsFormat::Json));
+        assert_eq!(format("inputs.TOML"), Some(InputsFormat::Toml));
+        assert_eq!(format("inputs.yml"), Some(InputsFormat::Yaml));
+        assert_eq!(format("inputs.yaml"), Some(InputsFormat::Yaml));
+        assert_eq!(format("inputs.txt"), None);
+        assert_eq!(format("-"), None);
+    }
+}
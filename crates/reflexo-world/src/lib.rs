@@ -5,6 +5,9 @@ pub mod config;
 pub mod entry;
 pub use entry::*;
 
+pub mod inputs;
+pub use inputs::*;
+
 pub mod world;
 pub use world::*;
 
@@ -1,36 +1,62 @@
 use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
     path::Path,
     sync::{Arc, OnceLock},
+    time::{Duration, Instant},
 };
 
 use log::error;
 use parking_lot::Mutex;
 use reqwest::blocking::Response;
+use sha2::{Digest, Sha256};
 use typst::{
     diag::{eco_format, EcoString},
     syntax::package::PackageVersion,
 };
+use typst_timing::TimingScope;
 
+use super::lock::{archive_hash, content_hash, LockedPackage, PackageLock};
 use super::{DummyNotifier, Notifier, PackageError, PackageRegistry, PackageSpec};
+use crate::config::{
+    PackageRegistryOpts, PackageSource, DEFAULT_PACKAGE_INDEX_TTL, DEFAULT_PACKAGE_RETRIES,
+};
+
+/// The minimum interval between progress reports of a download whose total
+/// size is unknown.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct HttpRegistry {
-    notifier: Arc<Mutex<dyn Notifier + Send>>,
+    notifier: Mutex<Arc<dyn Notifier + Send + Sync>>,
+
+    opts: PackageRegistryOpts,
+    /// The lockfile, loaded on the first resolution.
+    lock: OnceLock<Result<Mutex<PackageLock>, EcoString>>,
+    /// The packages prepared and verified so far.
+    prepared: Mutex<HashMap<PackageSpec, Arc<Path>>>,
 
     packages: OnceLock<Vec<(PackageSpec, Option<EcoString>)>>,
 }
 
 impl Default for HttpRegistry {
     fn default() -> Self {
+        Self::new(PackageRegistryOpts::default())
+    }
+}
+
+impl HttpRegistry {
+    /// Creates a registry fetching packages from the given sources.
+    pub fn new(opts: PackageRegistryOpts) -> Self {
         Self {
-            notifier: Arc::new(Mutex::<DummyNotifier>::default()),
+            notifier: Mutex::new(Arc::new(DummyNotifier)),
+            opts,
+            lock: OnceLock::new(),
+            prepared: Mutex::default(),
 
-            // todo: reset cache
             packages: OnceLock::new(),
         }
     }
-}
 
-impl HttpRegistry {
     pub fn local_path(&self) -> Option<Box<Path>> {
         if let Some(data_dir) = dirs::data_dir() {
             if data_dir.exists() {
@@ -61,127 +87,735 @@ impl HttpRegistry {
     }
 
     /// Make a package available in the on-disk cache.
+    ///
+    /// If a lockfile is configured, the package is verified against the
+    /// lockfile, or recorded into the lockfile if it is not locked yet.
     pub fn prepare_package(&self, spec: &PackageSpec) -> Result<Arc<Path>, PackageError> {
+        if let Some(dir) = self.prepared.lock().get(spec) {
+            return Ok(dir.clone());
+        }
+
+        let lock = self.lock()?;
+        let locked = lock.and_then(|lock| lock.lock().get(spec).cloned());
+        let (dir, checksum) = self.locate_package(spec, locked.as_ref())?;
+
+        match (lock, locked) {
+            (Some(_), Some(locked)) => locked.verify_content(spec, &dir)?,
+            (Some(lock), None) => {
+                let mut lock = lock.lock();
+                lock.insert(LockedPackage {
+                    spec: spec.to_string(),
+                    checksum,
+                    content: content_hash(&dir)?,
+                });
+                if let Some(path) = &self.opts.lockfile {
+                    lock.write(path).map_err(|err| {
+                        PackageError::Other(Some(eco_format!(
+                            "failed to write lockfile {}: {err}",
+                            path.display()
+                        )))
+                    })?;
+                }
+            }
+            (None, _) => {}
+        }
+
+        self.prepared.lock().insert(spec.clone(), dir.clone());
+        Ok(dir)
+    }
+
+    /// Gets the lockfile if it is configured.
+    fn lock(&self) -> Result<Option<&Mutex<PackageLock>>, PackageError> {
+        let Some(path) = &self.opts.lockfile else {
+            return Ok(None);
+        };
+
+        let lock = self.lock.get_or_init(|| {
+            PackageLock::read(path)
+                .map(Mutex::new)
+                .map_err(|err| eco_format!("failed to read lockfile {}: {err}", path.display()))
+        });
+        match lock {
+            Ok(lock) => Ok(Some(lock)),
+            Err(err) => Err(PackageError::Other(Some(err.clone()))),
+        }
+    }
+
+    /// Finds a package on disk or downloads it, returning the directory of
+    /// the package and the checksum of the archive if it is downloaded.
+    fn locate_package(
+        &self,
+        spec: &PackageSpec,
+        locked: Option<&LockedPackage>,
+    ) -> Result<(Arc<Path>, Option<String>), PackageError> {
         let subdir = format!(
             "typst/packages/{}/{}/{}",
             spec.namespace, spec.name, spec.version
         );
 
+        for path in &self.opts.paths {
+            let dir = path
+                .join(spec.namespace.as_str())
+                .join(spec.name.as_str())
+                .join(spec.version.to_string());
+            if dir.exists() {
+                return Ok((dir.into(), None));
+            }
+        }
+
         if let Some(data_dir) = dirs::data_dir() {
             let dir = data_dir.join(&subdir);
             if dir.exists() {
-                return Ok(dir.into());
+                return Ok((dir.into(), None));
             }
         }
 
-        if let Some(cache_dir) = dirs::cache_dir() {
-            let dir = cache_dir.join(&subdir);
+        let cache_dir = dirs::cache_dir().map(|cache_dir| cache_dir.join(&subdir));
+        if let Some(dir) = cache_dir.as_ref().filter(|dir| dir.exists()) {
+            return Ok((dir.as_path().into(), None));
+        }
 
-            // Download from network if it doesn't exist yet.
-            if spec.namespace == "preview" && !dir.exists() {
-                self.download_package(spec, &dir)?;
+        // Tries the sources in order, falling back to the next source if a
+        // source doesn't provide the package.
+        let mut error = PackageError::NotFound(spec.clone());
+        for source in self.opts.sources(&spec.namespace) {
+            let url = match source {
+                PackageSource::Dir(dir) => {
+                    let dir = dir
+                        .join(spec.namespace.as_str())
+                        .join(spec.name.as_str())
+                        .join(spec.version.to_string());
+                    if dir.exists() {
+                        return Ok((dir.into(), None));
+                    }
+                    continue;
+                }
+                PackageSource::Url(url) => package_url(&url, spec),
+            };
+            if self.opts.offline {
+                error = PackageError::Other(Some(eco_format!(
+                    "{spec} is not downloaded yet and cannot be downloaded in offline mode"
+                )));
+                continue;
             }
+            let Some(dir) = &cache_dir else {
+                continue;
+            };
 
-            if dir.exists() {
-                return Ok(dir.into());
+            // Download from network if it doesn't exist yet.
+            match self.download_package(spec, &url, dir, locked) {
+                Ok(checksum) => return Ok((dir.as_path().into(), Some(checksum))),
+                Err(err) => {
+                    log::warn!("failed to download package {spec} from {url}: {err}");
+                    error = err;
+                }
             }
         }
 
-        Err(PackageError::NotFound(spec.clone()))
+        Err(error)
     }
 
-    /// Download a package over the network.
-    fn download_package(&self, spec: &PackageSpec, package_dir: &Path) -> Result<(), PackageError> {
-        let url = format!(
-            "https://packages.typst.org/preview/{}-{}.tar.gz",
-            spec.name, spec.version
-        );
+    /// Download a package over the network, returning the checksum of the
+    /// archive.
+    fn download_package(
+        &self,
+        spec: &PackageSpec,
+        url: &str,
+        package_dir: &Path,
+        locked: Option<&LockedPackage>,
+    ) -> Result<String, PackageError> {
+        let _scope = TimingScope::new("download package", None);
+        let notifier = self.notifier.lock().clone();
+        let retries = self.opts.retries.unwrap_or(DEFAULT_PACKAGE_RETRIES);
 
-        self.notifier.lock().downloading(spec);
-        threaded_http(&url, |resp| {
-            let reader = match resp.and_then(|r| r.error_for_status()) {
-                Ok(response) => response,
-                Err(err) if matches!(err.status().map(|s| s.as_u16()), Some(404)) => {
-                    return Err(PackageError::NotFound(spec.clone()))
-                }
-                Err(err) => return Err(PackageError::NetworkFailed(Some(eco_format!("{err}")))),
+        let mut attempt = 0;
+        let archive = loop {
+            notifier.downloading(spec);
+            let err = match self.fetch_archive(spec, url, notifier.as_ref()) {
+                Ok(archive) => break archive,
+                Err(err) => err,
             };
+            notifier.failed(spec, &err);
 
-            let decompressed = flate2::read::GzDecoder::new(reader);
+            // Only network failures are worth retrying.
+            if !matches!(err, PackageError::NetworkFailed(..)) || attempt >= retries {
+                return Err(err);
+            }
+            attempt += 1;
+            log::warn!("failed to download package {spec}, retrying ({attempt}/{retries}): {err}");
+            std::thread::sleep(Duration::from_millis(500 << attempt.min(4)));
+        };
+
+        let unpacked = (|| {
+            // Verifies the archive before unpacking it.
+            if let Some(locked) = locked {
+                locked.verify_archive(spec, &archive)?;
+            }
+
+            let decompressed = flate2::read::GzDecoder::new(archive.as_slice());
             tar::Archive::new(decompressed)
                 .unpack(package_dir)
                 .map_err(|err| {
                     std::fs::remove_dir_all(package_dir).ok();
                     PackageError::MalformedArchive(Some(eco_format!("{err}")))
                 })
+        })();
+
+        match unpacked {
+            Ok(()) => notifier.downloaded(spec),
+            Err(err) => {
+                notifier.failed(spec, &err);
+                return Err(err);
+            }
+        }
+        Ok(archive_hash(&archive))
+    }
+
+    /// Fetches the archive of a package, reporting the progress to the
+    /// notifier.
+    fn fetch_archive(
+        &self,
+        spec: &PackageSpec,
+        url: &str,
+        notifier: &(dyn Notifier + Send + Sync),
+    ) -> Result<Vec<u8>, PackageError> {
+        let network_err =
+            |err: &dyn std::fmt::Display| PackageError::NetworkFailed(Some(eco_format!("{err}")));
+
+        threaded_http(url, self.timeout(), |resp| {
+            let mut resp = match resp.and_then(|r| r.error_for_status()) {
+                Ok(response) => response,
+                Err(err) if matches!(err.status().map(|s| s.as_u16()), Some(404)) => {
+                    return Err(PackageError::NotFound(spec.clone()))
+                }
+                Err(err) => return Err(network_err(&err)),
+            };
+
+            let total = resp.content_length();
+            let mut archive = vec![];
+            let mut buf = vec![0; 64 * 1024];
+            // Throttles the progress to whole percents, or to an interval if
+            // the total size is unknown.
+            let mut last_percent = None;
+            let mut last_time = None::<Instant>;
+            loop {
+                if notifier.is_cancelled(spec) {
+                    return Err(PackageError::Other(Some(eco_format!(
+                        "downloading {spec} is cancelled"
+                    ))));
+                }
+
+                let read = resp.read(&mut buf).map_err(|err| network_err(&err))?;
+                if read == 0 {
+                    break;
+                }
+                archive.extend_from_slice(&buf[..read]);
+
+                let received = archive.len() as u64;
+                let report = match total.filter(|total| *total > 0) {
+                    Some(total) => {
+                        let percent = received * 100 / total;
+                        let report = last_percent < Some(percent);
+                        last_percent = Some(percent);
+                        report
+                    }
+                    None => last_time.is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL),
+                };
+                if report {
+                    last_time = Some(Instant::now());
+                    notifier.progress(spec, received, total);
+                }
+            }
+
+            Ok(archive)
         })
         .ok_or_else(|| PackageError::Other(Some(eco_format!("cannot spawn http thread"))))?
     }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.opts.timeout.map(Duration::from_secs)
+    }
+}
+
+/// Gets the URL to download a package from, see [`PackageSource::Url`].
+fn package_url(url: &str, spec: &PackageSpec) -> String {
+    if url.contains("{name}") {
+        url.replace("{namespace}", &spec.namespace)
+            .replace("{name}", &spec.name)
+            .replace("{version}", &spec.version.to_string())
+    } else {
+        format!(
+            "{}/{}/{}-{}.tar.gz",
+            url.trim_end_matches('/'),
+            spec.namespace,
+            spec.name,
+            spec.version
+        )
+    }
+}
+
+impl HttpRegistry {
+    /// Loads the package index of the `preview` namespace.
+    ///
+    /// The registries are tried in order. The index of a registry is read
+    /// from its on-disk copy if the copy is younger than the TTL, and
+    /// otherwise fetched from the registry. In offline mode or if no registry
+    /// responds, the on-disk copies are used regardless of their age.
+    fn load_index(&self) -> Vec<(PackageSpec, Option<EcoString>)> {
+        let cache_dir = dirs::cache_dir().map(|dir| dir.join("typst-ts/package-index"));
+        self.load_index_in(cache_dir.as_deref())
+    }
+
+    /// Loads the package index with the on-disk copies in the given
+    /// directory, see [`Self::load_index`].
+    fn load_index_in(&self, cache_dir: Option<&Path>) -> Vec<(PackageSpec, Option<EcoString>)> {
+        let registries = self.opts.sources("preview").into_iter();
+        let urls = registries
+            .filter_map(|source| match source {
+                PackageSource::Url(url) if !url.contains("{name}") => {
+                    Some(format!("{}/preview/index.json", url.trim_end_matches('/')))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let copy_of = |url: &str| cache_dir.map(|dir| index_copy(dir, url));
+        let read_copy = |url: &str| parse_index(&std::fs::read(copy_of(url)?).ok()?);
+
+        let ttl = Duration::from_secs(self.opts.index_ttl.unwrap_or(DEFAULT_PACKAGE_INDEX_TTL));
+        for url in &urls {
+            let copy = copy_of(url);
+            let age = copy
+                .as_ref()
+                .and_then(|copy| std::fs::metadata(copy).ok()?.modified().ok())
+                .map(|modified| modified.elapsed().unwrap_or_default());
+            if self.opts.offline || age.is_some_and(|age| age < ttl) {
+                if let Some(index) = read_copy(url) {
+                    return index;
+                }
+            }
+            if self.opts.offline {
+                continue;
+            }
+
+            let Some(data) = fetch_index(url, self.timeout()) else {
+                continue;
+            };
+            let Some(index) = parse_index(&data) else {
+                error!("Failed to parse package index from {}", url);
+                continue;
+            };
+
+            if let Some(copy) = copy {
+                let written = copy
+                    .parent()
+                    .map_or(Ok(()), std::fs::create_dir_all)
+                    .and_then(|_| std::fs::write(&copy, &data));
+                if let Err(err) = written {
+                    log::warn!("failed to save package index to {}: {err}", copy.display());
+                }
+            }
+            return index;
+        }
+
+        urls.iter()
+            .find_map(|url| read_copy(url))
+            .unwrap_or_default()
+    }
+
+    /// Lists the packages in the data directory, which are usually linked
+    /// from local paths.
+    fn local_packages(&self) -> Vec<(PackageSpec, Option<EcoString>)> {
+        #[derive(serde::Deserialize)]
+        struct LocalPackageManifest {
+            package: LocalPackageInfo,
+        }
+
+        #[derive(serde::Deserialize)]
+        struct LocalPackageInfo {
+            description: Option<EcoString>,
+        }
+
+        let Some(dir) = self.local_path() else {
+            return vec![];
+        };
+        let read_dir = |dir: &Path| {
+            let entries = std::fs::read_dir(dir).into_iter().flatten().flatten();
+            entries.filter(|entry| entry.path().is_dir())
+        };
+
+        let mut packages = vec![];
+        for namespace in read_dir(&dir) {
+            for name in read_dir(&namespace.path()) {
+                for version in read_dir(&name.path()) {
+                    let Ok(version_str) = version.file_name().into_string() else {
+                        continue;
+                    };
+                    let Ok(version_num) = version_str.parse::<PackageVersion>() else {
+                        continue;
+                    };
+                    let spec = PackageSpec {
+                        namespace: namespace.file_name().to_string_lossy().as_ref().into(),
+                        name: name.file_name().to_string_lossy().as_ref().into(),
+                        version: version_num,
+                    };
+
+                    let manifest = std::fs::read_to_string(version.path().join("typst.toml"));
+                    let manifest = manifest.ok().and_then(|manifest| {
+                        toml::from_str::<LocalPackageManifest>(&manifest).ok()
+                    });
+                    packages.push((spec, manifest.and_then(|m| m.package.description)));
+                }
+            }
+        }
+
+        packages
+    }
 }
 
 impl PackageRegistry for HttpRegistry {
+    fn set_notifier(&self, notifier: Arc<dyn Notifier + Send + Sync>) {
+        *self.notifier.lock() = notifier;
+    }
+
+    fn reset(&mut self) {
+        self.packages = OnceLock::new();
+        self.prepared.lock().clear();
+    }
+
     fn resolve(&self, spec: &PackageSpec) -> Result<std::sync::Arc<Path>, PackageError> {
         self.prepare_package(spec)
     }
 
     fn packages(&self) -> &[(PackageSpec, Option<EcoString>)] {
         self.packages.get_or_init(|| {
-            let url = "https://packages.typst.org/preview/index.json";
-
-            threaded_http(url, |resp| {
-                let reader = match resp.and_then(|r| r.error_for_status()) {
-                    Ok(response) => response,
-                    Err(err) => {
-                        // todo: silent error
-                        error!("Failed to fetch package index: {} from {}", err, url);
-                        return vec![];
-                    }
-                };
-
-                #[derive(serde::Deserialize)]
-                struct RemotePackageIndex {
-                    name: EcoString,
-                    version: PackageVersion,
-                    description: Option<EcoString>,
-                }
+            let mut packages = self.load_index();
 
-                let index: Vec<RemotePackageIndex> = match serde_json::from_reader(reader) {
-                    Ok(index) => index,
-                    Err(err) => {
-                        error!("Failed to parse package index: {} from {}", err, url);
-                        return vec![];
-                    }
-                };
+            // Merges the local packages, which may shadow the packages in the
+            // index.
+            let local = self.local_packages();
+            let local_specs = local.iter().map(|(spec, _)| spec).collect::<HashSet<_>>();
+            packages.retain(|(spec, _)| !local_specs.contains(spec));
+            packages.extend(local);
 
-                index
-                    .into_iter()
-                    .map(|e| {
-                        (
-                            PackageSpec {
-                                namespace: "preview".into(),
-                                name: e.name,
-                                version: e.version,
-                            },
-                            e.description,
-                        )
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default()
+            packages
         })
     }
 }
 
+/// Fetches the raw package index.
+fn fetch_index(url: &str, timeout: Option<Duration>) -> Option<Vec<u8>> {
+    threaded_http(url, timeout, |resp| {
+        match resp.and_then(|r| r.error_for_status()?.bytes()) {
+            Ok(data) => Some(data.to_vec()),
+            Err(err) => {
+                // todo: silent error
+                error!("Failed to fetch package index: {} from {}", err, url);
+                None
+            }
+        }
+    })
+    .flatten()
+}
+
+/// Gets the path of the on-disk copy of the package index at the URL, which
+/// is keyed by the hash of the URL so that registries do not share copies.
+fn index_copy(cache_dir: &Path, url: &str) -> std::path::PathBuf {
+    let hash = hex::encode(Sha256::digest(url.as_bytes()));
+    cache_dir.join(format!("preview-{}.json", &hash[..16]))
+}
+
+/// Parses the package index of the `preview` namespace.
+fn parse_index(data: &[u8]) -> Option<Vec<(PackageSpec, Option<EcoString>)>> {
+    #[derive(serde::Deserialize)]
+    struct RemotePackageIndex {
+        name: EcoString,
+        version: PackageVersion,
+        description: Option<EcoString>,
+    }
+
+    let index: Vec<RemotePackageIndex> = serde_json::from_slice(data).ok()?;
+    let index = index.into_iter().map(|e| {
+        (
+            PackageSpec {
+                namespace: "preview".into(),
+                name: e.name,
+                version: e.version,
+            },
+            e.description,
+        )
+    });
+    Some(index.collect())
+}
+
 fn threaded_http<T: Send + Sync>(
     url: &str,
+    timeout: Option<Duration>,
     f: impl FnOnce(Result<Response, reqwest::Error>) -> T + Send + Sync,
 ) -> Option<T> {
     std::thread::scope(|s| {
         s.spawn(|| {
-            let client = reqwest::blocking::Client::builder().build().unwrap();
+            let mut builder = reqwest::blocking::Client::builder();
+            if let Some(timeout) = timeout {
+                builder = builder.connect_timeout(timeout).timeout(timeout);
+            }
+            let client = builder.build().unwrap();
             f(client.get(url).send())
         })
         .join()
         .ok()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reflexo-http-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn package_archive(files: &[(&str, &str)]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, content.as_bytes())
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// Serves the given paths over HTTP on a local port, responding 404 to
+    /// other paths, and returns the base URL.
+    fn serve(routes: Vec<(&'static str, u16, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut buf = [0; 4096];
+                let read = stream.read(&mut buf).unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..read]);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = routes
+                    .iter()
+                    .find(|(p, ..)| *p == path)
+                    .map_or((404, &[][..]), |(_, status, body)| {
+                        (*status, body.as_slice())
+                    });
+                let head = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                stream.write_all(head.as_bytes()).ok();
+                stream.write_all(body).ok();
+            }
+        });
+        format!("http://{addr}")
+    }
+
+    fn spec(s: &str) -> PackageSpec {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_download_package() {
+        let archive = package_archive(&[("typst.toml", "[package]"), ("lib.typ", "#let x = 1")]);
+        let url = serve(vec![("/corp/example-0.1.0.tar.gz", 200, archive.clone())]);
+        let dir = temp_dir("download");
+
+        let spec = spec("@corp/example:0.1.0");
+        let registry = HttpRegistry::default();
+        let checksum = registry
+            .download_package(&spec, &package_url(&url, &spec), &dir, None)
+            .unwrap();
+
+        assert_eq!(checksum, archive_hash(&archive));
+        let lib = std::fs::read_to_string(dir.join("lib.typ")).unwrap();
+        assert_eq!(lib, "#let x = 1");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_package_errors() {
+        let url = serve(vec![
+            ("/corp/broken-0.1.0.tar.gz", 200, b"not an archive".to_vec()),
+            ("/corp/failing-0.1.0.tar.gz", 500, vec![]),
+        ]);
+        let dir = temp_dir("download-errors");
+        let registry = HttpRegistry::new(PackageRegistryOpts {
+            retries: Some(0),
+            ..Default::default()
+        });
+        let download = |s: &str| {
+            let spec = spec(s);
+            registry.download_package(&spec, &package_url(&url, &spec), &dir, None)
+        };
+
+        let err = download("@corp/missing:0.1.0").unwrap_err();
+        assert!(matches!(err, PackageError::NotFound(..)), "{err:?}");
+        let err = download("@corp/failing:0.1.0").unwrap_err();
+        assert!(matches!(err, PackageError::NetworkFailed(..)), "{err:?}");
+        let err = download("@corp/broken:0.1.0").unwrap_err();
+        assert!(matches!(err, PackageError::MalformedArchive(..)), "{err:?}");
+    }
+
+    #[derive(Default)]
+    struct RecordingNotifier {
+        progress: Mutex<Vec<(u64, Option<u64>)>>,
+        cancelled: bool,
+    }
+
+    impl Notifier for RecordingNotifier {
+        fn progress(&self, _spec: &PackageSpec, received: u64, total: Option<u64>) {
+            self.progress.lock().push((received, total));
+        }
+
+        fn is_cancelled(&self, _spec: &PackageSpec) -> bool {
+            self.cancelled
+        }
+    }
+
+    #[test]
+    fn test_fetch_progress() {
+        let archive = vec![0; 4 * 1024 * 1024];
+        let url = serve(vec![("/corp/large-0.1.0.tar.gz", 200, archive.clone())]);
+        let spec = spec("@corp/large:0.1.0");
+        let url = package_url(&url, &spec);
+        let registry = HttpRegistry::default();
+
+        let notifier = RecordingNotifier::default();
+        let fetched = registry.fetch_archive(&spec, &url, &notifier).unwrap();
+        assert_eq!(fetched, archive);
+
+        // Reported at most once per percent.
+        let progress = notifier.progress.into_inner();
+        let total = archive.len() as u64;
+        assert!(
+            !progress.is_empty() && progress.len() <= 101,
+            "{progress:?}"
+        );
+        assert!(progress.iter().all(|(_, t)| *t == Some(total)));
+        assert!(progress.windows(2).all(|w| w[0].0 < w[1].0));
+
+        let notifier = RecordingNotifier {
+            cancelled: true,
+            ..Default::default()
+        };
+        let err = registry.fetch_archive(&spec, &url, &notifier).unwrap_err();
+        assert!(err.to_string().contains("cancelled"), "{err}");
+        assert!(notifier.progress.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let index = parse_index(
+            br#"[
+                {"name": "example", "version": "0.1.0", "description": "An example"},
+                {"name": "bare", "version": "1.2.3", "extra": true}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            index,
+            [
+                (spec("@preview/example:0.1.0"), Some("An example".into())),
+                (spec("@preview/bare:1.2.3"), None),
+            ]
+        );
+
+        assert_eq!(parse_index(b"[]"), Some(vec![]));
+        assert_eq!(parse_index(b"not json"), None);
+        assert_eq!(parse_index(br#"[{"name": "example"}]"#), None);
+        assert_eq!(parse_index(br#"[{"name": "x", "version": "1"}]"#), None);
+    }
+
+    fn index_registry(url: &str, offline: bool, index_ttl: Option<u64>) -> HttpRegistry {
+        HttpRegistry::new(PackageRegistryOpts {
+            registries: vec![url.to_owned()],
+            offline,
+            index_ttl,
+            timeout: Some(5),
+            ..Default::default()
+        })
+    }
+
+    fn names(index: Vec<(PackageSpec, Option<EcoString>)>) -> Vec<String> {
+        index
+            .into_iter()
+            .map(|(spec, _)| spec.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_load_index() {
+        let index = br#"[{"name": "served", "version": "0.1.0"}]"#.to_vec();
+        let url = serve(vec![("/preview/index.json", 200, index.clone())]);
+        let missing = serve(vec![]);
+        let dir = temp_dir("index");
+        let copy = index_copy(&dir, &format!("{url}/preview/index.json"));
+        let load =
+            |url: &str, offline, ttl| index_registry(url, offline, ttl).load_index_in(Some(&dir));
+
+        // Offline without a copy, nothing is loaded.
+        assert!(load(&url, true, None).is_empty());
+
+        // The fetched index is saved to a copy keyed by the URL.
+        assert_eq!(names(load(&url, false, None)), ["@preview/served:0.1.0"]);
+        assert_eq!(std::fs::read(&copy).unwrap(), index);
+        let other = index_copy(&dir, &format!("{missing}/preview/index.json"));
+        assert_ne!(copy, other);
+        assert!(!other.exists());
+
+        // A fresh copy is used instead of fetching.
+        std::fs::write(&copy, br#"[{"name": "cached", "version": "0.1.0"}]"#).unwrap();
+        assert_eq!(names(load(&url, false, None)), ["@preview/cached:0.1.0"]);
+        // A stale copy is refreshed.
+        assert_eq!(names(load(&url, false, Some(0))), ["@preview/served:0.1.0"]);
+
+        // A stale copy is used in offline mode, or if the registry fails.
+        std::fs::write(&copy, br#"[{"name": "cached", "version": "0.1.0"}]"#).unwrap();
+        assert_eq!(names(load(&url, true, Some(0))), ["@preview/cached:0.1.0"]);
+        std::fs::copy(&copy, &other).unwrap();
+        assert_eq!(
+            names(load(&missing, false, Some(0))),
+            ["@preview/cached:0.1.0"]
+        );
+
+        // Registries do not share copies.
+        std::fs::remove_file(&other).unwrap();
+        assert!(load(&missing, false, Some(0)).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dir_source() {
+        let dir = temp_dir("dir-source");
+        let package_dir = dir.join("corp/reflexo-dir-source/0.1.0");
+        std::fs::create_dir_all(&package_dir).unwrap();
+        std::fs::write(package_dir.join("typst.toml"), "[package]").unwrap();
+
+        let registry = HttpRegistry::new(PackageRegistryOpts {
+            namespaces: [("corp".into(), vec![PackageSource::Dir(dir.clone())])].into(),
+            ..Default::default()
+        });
+        let resolved = registry.resolve(&spec("@corp/reflexo-dir-source:0.1.0"));
+        assert_eq!(resolved.unwrap().as_ref(), package_dir.as_path());
+        let err = registry.resolve(&spec("@corp/reflexo-dir-source:0.2.0"));
+        assert!(matches!(err, Err(PackageError::NotFound(..))));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
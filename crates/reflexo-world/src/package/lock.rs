@@ -0,0 +1,312 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use typst::diag::{eco_format, PackageError, PackageResult};
+
+use super::PackageSpec;
+
+/// The version of the lockfile format.
+pub const LOCK_FILE_VERSION: u32 = 1;
+
+/// The packages resolved by compilations, recorded for reproducible builds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageLock {
+    /// The version of the lockfile format.
+    pub version: u32,
+    /// The locked packages, sorted by their specs.
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Default for PackageLock {
+    fn default() -> Self {
+        Self {
+            version: LOCK_FILE_VERSION,
+            packages: vec![],
+        }
+    }
+}
+
+/// A package recorded in the lockfile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedPackage {
+    /// The spec of the package, e.g. `@preview/example:0.1.0`.
+    pub spec: String,
+    /// The SHA-256 hash of the downloaded archive in the form of
+    /// `sha256:<hex>`, or `None` if the package is not downloaded, e.g. it is
+    /// read from a local directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<String>,
+    /// The SHA-256 hash of the files in the package, see [`content_hash`].
+    pub content: String,
+}
+
+impl PackageLock {
+    /// The file name of the lockfile, which is placed at the root of a
+    /// workspace.
+    pub const FILE_NAME: &'static str = "typst-ts.lock";
+
+    /// Reads a lockfile, returning an empty lock if the file doesn't exist.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.to_string()),
+        };
+
+        let lock: Self = toml::from_str(&content).map_err(|err| err.to_string())?;
+        if lock.version != LOCK_FILE_VERSION {
+            return Err(format!("unsupported lockfile version {}", lock.version));
+        }
+        Ok(lock)
+    }
+
+    /// Writes the lockfile.
+    ///
+    /// The content is written to a temporary file next to the lockfile and
+    /// then renamed to it, so that concurrent readers never see a partially
+    /// written lockfile.
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        let content = toml::to_string_pretty(self).map_err(|err| err.to_string())?;
+        let content =
+            format!("# This file is generated by typst-ts. Do not edit it manually.\n\n{content}");
+
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let tmp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+        std::fs::write(&tmp_path, content).map_err(|err| err.to_string())?;
+        std::fs::rename(&tmp_path, path).map_err(|err| {
+            let _ = std::fs::remove_file(&tmp_path);
+            err.to_string()
+        })
+    }
+
+    /// Gets the locked package by its spec.
+    pub fn get(&self, spec: &PackageSpec) -> Option<&LockedPackage> {
+        let spec = spec.to_string();
+        self.packages.iter().find(|p| p.spec == spec)
+    }
+
+    /// Records a package, replacing the previous record of the package.
+    pub fn insert(&mut self, package: LockedPackage) {
+        match self
+            .packages
+            .binary_search_by(|p| p.spec.cmp(&package.spec))
+        {
+            Ok(i) => self.packages[i] = package,
+            Err(i) => self.packages.insert(i, package),
+        }
+    }
+}
+
+impl LockedPackage {
+    /// Checks the archive of the package against the lock.
+    pub fn verify_archive(&self, spec: &PackageSpec, archive: &[u8]) -> PackageResult<()> {
+        let Some(expected) = &self.checksum else {
+            return Ok(());
+        };
+
+        let actual = archive_hash(archive);
+        if *expected != actual {
+            return Err(PackageError::MalformedArchive(Some(eco_format!(
+                "checksum of {spec} does not match the lockfile, expected {expected}, found {actual}"
+            ))));
+        }
+        Ok(())
+    }
+
+    /// Checks the files of the package against the lock.
+    pub fn verify_content(&self, spec: &PackageSpec, dir: &Path) -> PackageResult<()> {
+        let actual = content_hash(dir)?;
+        if self.content != actual {
+            return Err(PackageError::Other(Some(eco_format!(
+                "files of {spec} at {} do not match the lockfile, expected {}, found {actual}",
+                dir.display(),
+                self.content,
+            ))));
+        }
+        Ok(())
+    }
+}
+
+/// Computes the SHA-256 hash of an archive in the form of `sha256:<hex>`.
+pub fn archive_hash(archive: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(archive)))
+}
+
+/// Computes the SHA-256 hash of the files in a directory in the form of
+/// `sha256:<hex>`.
+///
+/// The hash covers the relative paths and the contents of the files, in the
+/// order of the paths, so it doesn't depend on the location of the directory
+/// or the modification times of the files.
+pub fn content_hash(dir: &Path) -> PackageResult<String> {
+    let mut files = vec![];
+    collect_files(dir, PathBuf::new(), &mut files)
+        .map_err(|err| PackageError::Other(Some(eco_format!("{err}"))))?;
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for path in files {
+        let content = std::fs::read(dir.join(&path))
+            .map_err(|err| PackageError::Other(Some(eco_format!("{err}"))))?;
+        let path = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        hasher.update(path.as_bytes());
+        hasher.update([0]);
+        hasher.update((content.len() as u64).to_le_bytes());
+        hasher.update(&content);
+    }
+
+    Ok(format!("sha256:{}", hex::encode(hasher.finalize())))
+}
+
+fn collect_files(dir: &Path, rel: PathBuf, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir.join(&rel))? {
+        let entry = entry?;
+        let rel = rel.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(dir, rel, files)?;
+        } else {
+            files.push(rel);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("reflexo-lock-{name}-{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn spec() -> PackageSpec {
+        "@preview/example:0.1.0".parse().unwrap()
+    }
+
+    #[test]
+    fn test_archive_hash() {
+        assert_eq!(
+            archive_hash(b""),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(archive_hash(b"a"), archive_hash(b"b"));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let a = temp_dir("content-a");
+        let b = temp_dir("content-b");
+        for dir in [&a, &b] {
+            std::fs::create_dir_all(dir.join("src")).unwrap();
+            std::fs::write(dir.join("typst.toml"), "[package]").unwrap();
+            std::fs::write(dir.join("src/lib.typ"), "#let x = 1").unwrap();
+        }
+        // Independent of the location of the directory.
+        assert_eq!(content_hash(&a).unwrap(), content_hash(&b).unwrap());
+
+        // Depends on the contents and the paths of the files.
+        std::fs::write(b.join("src/lib.typ"), "#let x = 2").unwrap();
+        assert_ne!(content_hash(&a).unwrap(), content_hash(&b).unwrap());
+        std::fs::write(b.join("src/lib.typ"), "#let x = 1").unwrap();
+        std::fs::rename(b.join("src/lib.typ"), b.join("src/main.typ")).unwrap();
+        assert_ne!(content_hash(&a).unwrap(), content_hash(&b).unwrap());
+
+        std::fs::remove_dir_all(&a).unwrap();
+        std::fs::remove_dir_all(&b).unwrap();
+    }
+
+    #[test]
+    fn test_verify_mismatch() {
+        let dir = temp_dir("verify");
+        std::fs::write(dir.join("lib.typ"), "#let x = 1").unwrap();
+        let locked = LockedPackage {
+            spec: spec().to_string(),
+            checksum: Some(archive_hash(b"archive")),
+            content: content_hash(&dir).unwrap(),
+        };
+
+        assert!(locked.verify_archive(&spec(), b"archive").is_ok());
+        let err = locked.verify_archive(&spec(), b"tampered").unwrap_err();
+        assert!(matches!(err, PackageError::MalformedArchive(..)), "{err:?}");
+
+        assert!(locked.verify_content(&spec(), &dir).is_ok());
+        std::fs::write(dir.join("lib.typ"), "#let x = 2").unwrap();
+        let err = locked.verify_content(&spec(), &dir).unwrap_err();
+        assert!(
+            err.to_string().contains("do not match the lockfile"),
+            "{err}"
+        );
+
+        // Packages read from directories have no archive to verify.
+        let locked = LockedPackage {
+            checksum: None,
+            ..locked
+        };
+        assert!(locked.verify_archive(&spec(), b"tampered").is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_lock_round_trip() {
+        let dir = temp_dir("round-trip");
+        let path = dir.join(PackageLock::FILE_NAME);
+        assert_eq!(PackageLock::read(&path), Ok(PackageLock::default()));
+
+        let mut lock = PackageLock::default();
+        for (spec, checksum) in [("@preview/b:0.1.0", None), ("@preview/a:0.2.0", Some("x"))] {
+            lock.insert(LockedPackage {
+                spec: spec.into(),
+                checksum: checksum.map(str::to_owned),
+                content: "sha256:00".into(),
+            });
+        }
+        lock.insert(LockedPackage {
+            spec: "@preview/b:0.1.0".into(),
+            checksum: None,
+            content: "sha256:01".into(),
+        });
+        let specs = lock
+            .packages
+            .iter()
+            .map(|p| p.spec.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(specs, ["@preview/a:0.2.0", "@preview/b:0.1.0"]);
+
+        lock.write(&path).unwrap();
+        assert_eq!(PackageLock::read(&path), Ok(lock.clone()));
+        let b = lock.get(&"@preview/b:0.1.0".parse().unwrap()).unwrap();
+        assert_eq!(b.content, "sha256:01");
+        assert!(lock.get(&spec()).is_none());
+
+        // No temporary files are left.
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_lock_read_errors() {
+        let dir = temp_dir("read-errors");
+        let path = dir.join(PackageLock::FILE_NAME);
+
+        std::fs::write(&path, "version = 2").unwrap();
+        let err = PackageLock::read(&path).unwrap_err();
+        assert_eq!(err, "unsupported lockfile version 2");
+
+        std::fs::write(&path, "version = ").unwrap();
+        assert!(PackageLock::read(&path).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
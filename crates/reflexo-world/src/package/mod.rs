@@ -6,6 +6,7 @@ pub use typst::diag::PackageError;
 pub use typst::syntax::package::PackageSpec;
 
 pub mod dummy;
+pub mod lock;
 
 #[cfg(feature = "browser")]
 pub mod browser;
@@ -27,10 +28,48 @@ pub trait PackageRegistry {
     fn packages(&self) -> &[(PackageSpec, Option<EcoString>)] {
         &[]
     }
+
+    /// Sets the notifier receiving the status of package downloads.
+    ///
+    /// This function is optional to implement for registries that download
+    /// packages.
+    fn set_notifier(&self, _notifier: Arc<dyn Notifier + Send + Sync>) {}
 }
 
+/// Receives the status of package downloads.
 pub trait Notifier {
+    /// Called when a package starts downloading.
     fn downloading(&self, _spec: &PackageSpec) {}
+
+    /// Called when chunks of a package are received, with the number of
+    /// bytes received so far and the total number of bytes if known.
+    ///
+    /// Registries may throttle the calls, e.g. the HTTP registry calls it
+    /// once per percent of the total size, or at most every 100 milliseconds
+    /// if the total size is unknown.
+    fn progress(&self, _spec: &PackageSpec, _received: u64, _total: Option<u64>) {}
+
+    /// Called when a package is downloaded.
+    fn downloaded(&self, _spec: &PackageSpec) {}
+
+    /// Called when a download fails, which may be retried later.
+    fn failed(&self, _spec: &PackageSpec, _error: &PackageError) {}
+
+    /// Whether to cancel downloading a package, which is checked whenever a
+    /// chunk is received, e.g. because the compiler requesting the package is
+    /// stopped.
+    fn is_cancelled(&self, _spec: &PackageSpec) -> bool {
+        false
+    }
+}
+
+/// The status of a package download, see [`Notifier`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadStatus {
+    Started,
+    Progress { received: u64, total: Option<u64> },
+    Finished,
+    Failed(EcoString),
 }
 
 #[derive(Debug, Default, Clone, Copy, Hash)]
@@ -1,16 +1,20 @@
 // use std::sync::Arc;
 
 use core::fmt;
-use std::{num::NonZeroUsize, sync::Arc};
+use std::{
+    num::NonZeroUsize,
+    sync::{Arc, OnceLock},
+};
 
 use parking_lot::{Mutex, RwLock};
-use reflexo::hash::FxHashMap;
+use reflexo::hash::{hash128, FxHashMap};
 use reflexo::{ImmutPath, QueryRef};
 use reflexo_vfs::{Bytes, FileId, FsProvider, TypstFileId};
 use typst::{
     diag::{FileError, FileResult},
     syntax::Source,
 };
+use typst_timing::{timed, TimingScope};
 
 /// incrementally query a value from a self holding state
 type IncrQueryRef<S, E> = QueryRef<S, E, Option<S>>;
@@ -57,6 +61,11 @@ impl<T: Revised> SharedState<T> {
 pub struct SourceCache {
     last_accessed_rev: NonZeroUsize,
     fid: FileId,
+    /// The hash of the file content read by the revision that the cache entry
+    /// is carried over from, if any.
+    prev_hash: Option<u128>,
+    /// The hash of the file content read in this revision.
+    hash: OnceLock<u128>,
     source: IncrFileQuery<Source>,
     buffer: FileQuery<Bytes>,
 }
@@ -67,12 +76,39 @@ impl fmt::Debug for SourceCache {
     }
 }
 
+impl SourceCache {
+    /// Records the content read in this revision.
+    fn read(&self, content: FileResult<Bytes>) -> FileResult<Bytes> {
+        if let Ok(content) = &content {
+            self.hash.get_or_init(|| hash128(content));
+        }
+        content
+    }
+
+    /// Whether the content is unchanged since the revision that the cache entry
+    /// is carried over from.
+    fn is_hit(&self) -> bool {
+        self.prev_hash.is_some() && self.hash.get().copied() == self.prev_hash
+    }
+}
+
 impl Revised for SourceCache {
     fn last_accessed_rev(&self) -> NonZeroUsize {
         self.last_accessed_rev
     }
 }
 
+/// The statistics of the cache entries for the files accessed in a revision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceCacheStats {
+    /// The number of files whose cache entries are carried over from a
+    /// previous revision, in which they had the same content.
+    pub hits: usize,
+    /// The number of files which are loaded from scratch or changed since the
+    /// previous revision.
+    pub misses: usize,
+}
+
 pub struct SourceState {
     pub revision: NonZeroUsize,
     pub slots: Arc<Mutex<FxHashMap<TypstFileId, SourceCache>>>,
@@ -157,6 +193,22 @@ impl SourceDb {
         w
     }
 
+    /// Returns the statistics of the cache entries for the stored files.
+    ///
+    /// The slots are shared by the worlds derived by [`CompilerWorld::task`],
+    /// so the statistics are only those of a single compilation if the world
+    /// is a fresh snapshot of the universe.
+    ///
+    /// [`CompilerWorld::task`]: crate::CompilerWorld::task
+    pub fn cache_stats(&self) -> SourceCacheStats {
+        let slots = self.slots.lock();
+        let hits = slots.values().filter(|slot| slot.is_hit()).count();
+        SourceCacheStats {
+            hits,
+            misses: slots.len() - hits,
+        }
+    }
+
     /// Get all the files that are currently in the VFS.
     ///
     /// This is typically corresponds to the file dependencies of a single
@@ -176,7 +228,11 @@ impl SourceDb {
 
     /// Get file content by path.
     pub fn file(&self, id: TypstFileId, fid: FileId, p: &impl FsProvider) -> FileResult<Bytes> {
-        self.slot(id, fid, |slot| slot.buffer.compute(|| p.read(fid)).cloned())
+        self.slot(id, fid, |slot| {
+            slot.buffer
+                .compute(|| slot.read(timed!("read file", p.read(fid))))
+                .cloned()
+        })
     }
 
     /// Get source content by path and assign the source with a given typst
@@ -187,10 +243,11 @@ impl SourceDb {
         self.slot(id, fid, |slot| {
             slot.source
                 .compute_with_context(|prev| {
-                    let content = p.read(fid)?;
+                    let content = slot.read(timed!("read file", p.read(fid)))?;
                     let next = from_utf8_or_bom(&content)?.to_owned();
 
                     // otherwise reparse the source
+                    let _scope = TimingScope::new("parse", None);
                     match prev {
                         Some(mut source) if self.do_reparse => {
                             source.replace(&next);
@@ -215,6 +272,8 @@ impl SourceDb {
                 .map(|e| SourceCache {
                     last_accessed_rev: self.revision.max(e.last_accessed_rev),
                     fid,
+                    prev_hash: e.hash.get().copied(),
+                    hash: OnceLock::new(),
                     source: IncrFileQuery::with_context(
                         e.source
                             .get_uninitialized()
@@ -228,6 +287,8 @@ impl SourceDb {
                 .unwrap_or_else(|| SourceCache {
                     last_accessed_rev: self.revision,
                     fid,
+                    prev_hash: None,
+                    hash: OnceLock::new(),
                     source: IncrFileQuery::with_context(None),
                     buffer: FileQuery::default(),
                 })
@@ -254,3 +315,92 @@ fn from_utf8_or_bom(buf: &[u8]) -> FileResult<&str> {
         buf
     })?)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use reflexo_vfs::Time;
+    use typst::syntax::VirtualPath;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MemoryFs(Mutex<FxHashMap<FileId, Bytes>>);
+
+    impl MemoryFs {
+        fn write(&self, fid: FileId, content: &str) {
+            self.0.lock().insert(fid, content.as_bytes().into());
+        }
+    }
+
+    impl FsProvider for MemoryFs {
+        fn file_path(&self, id: FileId) -> ImmutPath {
+            Path::new(&format!("/{}.typ", id.0)).into()
+        }
+
+        fn mtime(&self, _id: FileId) -> FileResult<Time> {
+            Ok(Time::UNIX_EPOCH)
+        }
+
+        fn read(&self, id: FileId) -> FileResult<Bytes> {
+            let content = self.0.lock().get(&id).cloned();
+            content.ok_or_else(|| FileError::NotFound(self.file_path(id).to_path_buf()))
+        }
+
+        fn is_file(&self, id: FileId) -> FileResult<bool> {
+            Ok(self.0.lock().contains_key(&id))
+        }
+    }
+
+    fn revision(shared: &Arc<RwLock<SharedState<SourceCache>>>, rev: usize) -> SourceDb {
+        SourceDb {
+            revision: NonZeroUsize::new(rev).unwrap(),
+            shared: shared.clone(),
+            slots: Default::default(),
+            do_reparse: true,
+        }
+    }
+
+    fn stats(hits: usize, misses: usize) -> SourceCacheStats {
+        SourceCacheStats { hits, misses }
+    }
+
+    #[test]
+    fn test_cache_stats() {
+        let fs = MemoryFs::default();
+        let files = [FileId(0), FileId(1), FileId(2)];
+        let ids = files.map(|fid| {
+            let path = fs.file_path(fid);
+            TypstFileId::new(None, VirtualPath::new(&path))
+        });
+        for fid in files {
+            fs.write(fid, "= Hello");
+        }
+
+        let shared = Arc::<RwLock<SharedState<SourceCache>>>::default();
+        let mut db = revision(&shared, 1);
+        db.source(ids[0], files[0], &fs).unwrap();
+        db.file(ids[1], files[1], &fs).unwrap();
+        assert_eq!(db.cache_stats(), stats(0, 2));
+        db.take_state().commit_impl(&mut shared.write());
+
+        // only the unchanged files carried over are hits
+        fs.write(files[1], "= World");
+        let mut db = revision(&shared, 2);
+        db.source(ids[0], files[0], &fs).unwrap();
+        db.file(ids[1], files[1], &fs).unwrap();
+        db.source(ids[2], files[2], &fs).unwrap();
+        assert_eq!(db.cache_stats(), stats(1, 2));
+        db.take_state().commit_impl(&mut shared.write());
+
+        // the changed file is compared with its content in the last revision
+        fs.write(files[0], "= World");
+        let db = revision(&shared, 3);
+        db.source(ids[0], files[0], &fs).unwrap();
+        db.source(ids[1], files[1], &fs).unwrap();
+        db.source(ids[2], files[2], &fs).unwrap();
+        assert_eq!(db.cache_stats(), stats(2, 1));
+        assert_eq!(db.source(ids[0], files[0], &fs).unwrap().text(), "= World");
+    }
+}
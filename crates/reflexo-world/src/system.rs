@@ -1,11 +1,17 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use comemo::Prehashed;
 use reflexo::error::prelude::*;
-use reflexo_vfs::{system::SystemAccessModel, Vfs};
+use reflexo_vfs::{
+    archive::{Archive, ArchiveAccessModel},
+    system::SystemAccessModel,
+    Vfs,
+};
 
 use crate::{
     config::CompileOpts,
+    entry::EntryState,
     font::{system::SystemFontSearcher, FontResolverImpl},
     package::http::HttpRegistry,
 };
@@ -17,8 +23,9 @@ pub struct SystemCompilerFeat;
 impl crate::CompilerFeat for SystemCompilerFeat {
     /// Uses [`FontResolverImpl`] directly.
     type FontResolver = FontResolverImpl;
-    /// It accesses a physical file system.
-    type AccessModel = SystemAccessModel;
+    /// It accesses a physical file system, in which archives can be mounted as
+    /// directories.
+    type AccessModel = ArchiveAccessModel<SystemAccessModel>;
     /// It performs native HTTP requests for fetching package data.
     type Registry = HttpRegistry;
 }
@@ -32,21 +39,55 @@ impl TypstSystemUniverse {
     /// Create [`TypstSystemWorld`] with the given options.
     /// See SystemCompilerFeat for instantiation details.
     /// See [`CompileOpts`] for available options.
+    ///
+    /// If the workspace root is a file, it is mounted as an archive, so that
+    /// the sources, images and fonts bundled into a zip or tar file are
+    /// compiled without unpacking.
     pub fn new(mut opts: CompileOpts) -> ZResult<Self> {
         let inputs = std::mem::take(&mut opts.inputs);
+        let registry = HttpRegistry::new(std::mem::take(&mut opts.package_registry));
+        let entry: EntryState = opts.entry.clone().try_into()?;
+
+        let mut access_model = ArchiveAccessModel::new(SystemAccessModel);
+        let archive = match entry.root() {
+            Some(root) => Self::workspace_archive(&root)?.map(|archive| (root, archive)),
+            None => None,
+        };
+        let fonts = Self::resolve_fonts(opts, archive.as_ref().map(|(_, archive)| archive))?;
+        if let Some((root, archive)) = archive {
+            access_model.mount(root, archive);
+        }
+
         Ok(Self::new_raw(
-            opts.entry.clone().try_into()?,
+            entry,
             Some(Arc::new(Prehashed::new(inputs))),
-            Vfs::new(SystemAccessModel {}),
-            HttpRegistry::default(),
-            Arc::new(Self::resolve_fonts(opts)?),
+            Vfs::new(access_model),
+            registry,
+            Arc::new(fonts),
         ))
     }
 
-    /// Resolve fonts from given options.
-    fn resolve_fonts(opts: CompileOpts) -> ZResult<FontResolverImpl> {
+    /// Opens the workspace root as an archive if it is a file.
+    pub fn workspace_archive(root: &Path) -> ZResult<Option<Archive>> {
+        if !root.is_file() {
+            return Ok(None);
+        }
+
+        Archive::open(root)
+            .map(Some)
+            .map_err(map_string_err_with_args(
+                "failed to open workspace archive",
+                [("path", root.display().to_string())],
+            ))
+    }
+
+    /// Resolve fonts from given options and the workspace archive.
+    fn resolve_fonts(opts: CompileOpts, archive: Option<&Archive>) -> ZResult<FontResolverImpl> {
         let mut searcher = SystemFontSearcher::new();
         searcher.resolve_opts(opts.into())?;
+        if let Some(archive) = archive {
+            searcher.add_archive_fonts(archive);
+        }
         Ok(searcher.into())
     }
 }
@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeSet,
     num::NonZeroUsize,
     ops::Deref,
     path::{Path, PathBuf},
@@ -7,11 +8,11 @@ use std::{
 
 use chrono::{DateTime, Datelike, Local};
 use comemo::Prehashed;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use reflexo::ImmutPath;
 use reflexo_vfs::{notify::FilesystemEvent, Vfs};
 use typst::{
-    diag::{eco_format, At, EcoString, FileError, FileResult, SourceResult},
+    diag::{eco_format, At, EcoString, FileError, FileResult, SourceResult, StrResult},
     foundations::{Bytes, Datetime, Dict},
     syntax::{FileId, Source, Span, VirtualPath},
     text::{Font, FontBook},
@@ -21,6 +22,7 @@ use typst::{
 use crate::{
     entry::{EntryManager, EntryReader, EntryState, DETACHED_ENTRY},
     font::FontResolver,
+    inputs::{parse_inputs, InputsFormat},
     package::{PackageRegistry, PackageSpec},
     parser::{
         get_semantic_tokens_full, get_semantic_tokens_legend, OffsetEncoding, SemanticToken,
@@ -183,6 +185,7 @@ impl<F: CompilerFeat> CompilerUniverse<F> {
                 shared: self.shared.clone(),
                 slots: Default::default(),
             },
+            fonts: Default::default(),
             now: OnceLock::new(),
         };
 
@@ -338,6 +341,8 @@ pub struct CompilerWorld<F: CompilerFeat> {
 
     /// Provides source database for typst compiler.
     pub source_db: SourceDb,
+    /// The indices of the fonts accessed during a single lifecycle.
+    fonts: Arc<Mutex<BTreeSet<usize>>>,
     /// The current datetime if requested. This is stored here to ensure it is
     /// always the same within one compilation. Reset between compilations.
     now: OnceLock<DateTime<Local>>,
@@ -364,6 +369,19 @@ pub struct TaskInputs {
     pub inputs: Option<Arc<Prehashed<Dict>>>,
 }
 
+impl TaskInputs {
+    /// Sets the dictionary for `sys.inputs`.
+    pub fn with_inputs(mut self, inputs: Dict) -> Self {
+        self.inputs = Some(Arc::new(Prehashed::new(inputs)));
+        self
+    }
+
+    /// Sets `sys.inputs` from structured data, see [`parse_inputs`].
+    pub fn with_inputs_data(self, data: &[u8], format: InputsFormat) -> StrResult<Self> {
+        Ok(self.with_inputs(parse_inputs(data, format)?))
+    }
+}
+
 impl<F: CompilerFeat> CompilerWorld<F> {
     pub fn task(&self, mutant: TaskInputs) -> CompilerWorld<F> {
         // Fetch to avoid inconsistent state.
@@ -379,6 +397,7 @@ impl<F: CompilerFeat> CompilerWorld<F> {
             registry: self.registry.clone(),
             vfs: self.vfs.snapshot(),
             source_db: self.source_db.clone(),
+            fonts: self.fonts.clone(),
             now: self.now.clone(),
         }
     }
@@ -387,6 +406,12 @@ impl<F: CompilerFeat> CompilerWorld<F> {
         self.inputs.clone()
     }
 
+    /// Gets the indices in the font book of the fonts accessed during the
+    /// lifecycle of the world.
+    pub fn accessed_fonts(&self) -> Vec<usize> {
+        self.fonts.lock().iter().copied().collect()
+    }
+
     /// Resolve the real path for a file id.
     pub fn path_for_id(&self, id: FileId) -> Result<PathBuf, FileError> {
         if id == *DETACHED_ENTRY {
@@ -473,6 +498,7 @@ impl<F: CompilerFeat> World for CompilerWorld<F> {
 
     /// Metadata about all known fonts.
     fn font(&self, id: usize) -> Option<Font> {
+        self.fonts.lock().insert(id);
         self.font_resolver.font(id)
     }
 
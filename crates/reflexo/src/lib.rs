@@ -127,3 +127,6 @@ pub mod vector {
         const _: () = assert!(core::mem::align_of::<ArchivedPatternItem>() == 8);
     }
 }
+
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
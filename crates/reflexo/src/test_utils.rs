@@ -0,0 +1,96 @@
+//! Fixtures shared by the tests of the crates in the workspace, enabled by
+//! the `test-utils` feature.
+
+/// Encodes 16-bit words in big-endian.
+pub fn words(words: &[u16]) -> Vec<u8> {
+    words.iter().flat_map(|w| w.to_be_bytes()).collect()
+}
+
+/// A simple glyph of a right triangle with legs of the given size.
+pub fn triangle(size: i16) -> Vec<u8> {
+    let mut glyph = words(&[1, 0, 0, size as u16, size as u16]);
+    // The end point of the contour and the length of instructions.
+    glyph.extend([0, 2, 0, 0]);
+    // Three on-curve points with 16-bit deltas.
+    glyph.extend([1, 1, 1]);
+    for delta in [0, size, -size, 0, 0, size] {
+        glyph.extend(delta.to_be_bytes());
+    }
+    glyph
+}
+
+/// Builds the tables of a TrueType font named `Test` of the given glyphs, in
+/// which `A`, `B`, `C` and `D` are mapped to the glyphs 1 to 4.
+pub fn test_font_tables(glyphs: &[Vec<u8>]) -> Vec<([u8; 4], Vec<u8>)> {
+    let num_glyphs = glyphs.len() as u16;
+
+    let mut head = vec![0; 54];
+    head[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+    head[12..16].copy_from_slice(&0x5F0F3CF5u32.to_be_bytes());
+    head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+    head[50..52].copy_from_slice(&1u16.to_be_bytes());
+
+    let mut hhea = vec![0; 36];
+    hhea[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+    hhea[4..6].copy_from_slice(&800u16.to_be_bytes());
+    hhea[34..36].copy_from_slice(&num_glyphs.to_be_bytes());
+
+    let maxp = [&0x00005000u32.to_be_bytes()[..], &num_glyphs.to_be_bytes()].concat();
+    let hmtx = words(&[500, 0].repeat(glyphs.len()));
+
+    let mut glyf = vec![];
+    let mut loca = vec![0u32];
+    for glyph in glyphs {
+        glyf.extend(glyph);
+        loca.push(glyf.len() as u32);
+    }
+    let loca = loca.iter().flat_map(|o| o.to_be_bytes()).collect();
+
+    #[rustfmt::skip]
+    let cmap = words(&[
+        0, 1, 3, 1, 0, 12,
+        4, 32, 0, 4, 4, 1, 0,
+        0x44, 0xFFFF, 0, 0x41, 0xFFFF, 1u16.wrapping_sub(0x41), 1, 0, 0,
+    ]);
+
+    let mut name = words(&[0, 1, 18, 3, 1, 0x409, 1, 8, 0]);
+    name.extend("Test".encode_utf16().flat_map(u16::to_be_bytes));
+
+    vec![
+        (*b"cmap", cmap),
+        (*b"glyf", glyf),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", loca),
+        (*b"maxp", maxp),
+        (*b"name", name),
+    ]
+}
+
+/// Builds a TrueType font named `Test` of the given glyphs, see
+/// [`test_font_tables`].
+pub fn test_font(glyphs: &[Vec<u8>]) -> Vec<u8> {
+    write_font(test_font_tables(glyphs))
+}
+
+/// Writes the tables into a font, sorted by their tags and padded to four
+/// bytes, in which checksums are left zero.
+pub fn write_font(mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let mut font = 0x00010000u32.to_be_bytes().to_vec();
+    font.extend(words(&[tables.len() as u16, 0, 0, 0]));
+    let mut offset = 12 + 16 * tables.len();
+    for (tag, table) in &tables {
+        font.extend(tag);
+        font.extend([0; 4]);
+        font.extend((offset as u32).to_be_bytes());
+        font.extend((table.len() as u32).to_be_bytes());
+        offset += table.len().next_multiple_of(4);
+    }
+    for (_, table) in &tables {
+        font.extend(table);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+    font
+}
@@ -468,7 +468,7 @@ impl From<TransformItem> for Transform {
             TransformItem::Matrix(m) => *m,
             TransformItem::Scale(m) => Transform::from_scale(m.0, m.1),
             TransformItem::Translate(m) => Transform::from_translate(m.x, m.y),
-            TransformItem::Rotate(_m) => todo!(),
+            TransformItem::Rotate(m) => tiny_skia_path::Transform::from_rotate(m.0).into(),
             TransformItem::Skew(m) => Transform::from_skew(m.0, m.1),
             TransformItem::Clip(_m) => Transform::identity(),
         }
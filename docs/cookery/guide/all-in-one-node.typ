@@ -76,6 +76,16 @@ compiler.pdf({ mainFileContent });
 compiler.plainSvg({ mainFileContent });
 // As SVG that only fits for web browsers but contains more features, like text selection.
 compiler.svg({ mainFileContent });
+// The options of the SVG override the defaults of the exporter.
+compiler.svg({ mainFileContent }, { withResponsiveJs: true, renderTextElement: false });
+// Renders texts as `<text>` elements in the used fonts, embedded as WOFF2 web fonts.
+compiler.svg({ mainFileContent }, { embedWebFonts: true });
+// Minifies SVG more strictly, keeping two decimal places in path data and translations.
+compiler.svg({ mainFileContent }, { strictMinify: true, numericPrecision: 2 });
+// As a list of standalone SVGs, one per page.
+compiler.svgPages({ mainFileContent });
+// The tight bounding box of the content of each page, e.g. to crop the SVGs.
+compiler.pageBboxes({ mainFileContent });
 ```
 
 == Querying
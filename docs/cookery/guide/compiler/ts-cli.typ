@@ -59,6 +59,31 @@ Path to typst workspace.
 typst-ts-cli -w /repos/root/ -e main.typ
 ```
 
+The workspace can also be a zip, tar or gzip compressed tar archive bundling the sources, images and fonts of a project, which is compiled without unpacking. The entry is then relative to the root of the archive, and the outputs are written next to the archive by default. Watching a workspace archive is not supported.
+
+```bash
+typst-ts-cli -w bundle.zip -e main.typ
+```
+
+=== `-t,--target` option
+
+Use a named target in the project configuration file `typst-ts.toml` at the workspace root. It is supported by the `compile`, `query` and `query-repl` commands. Command line options override the settings of the target, where paths in the configuration are relative to the workspace root. Unknown keys in the configuration are reported as errors.
+
+```toml
+[targets.book]
+entry = "book/main.typ"
+inputs = { edition = "print" }
+formats = ["pdf"]
+output = "dist/book"
+font-paths = ["assets/fonts"]
+creation-timestamp = 1704067200
+```
+
+```bash
+typst-ts-cli compile --target book
+typst-ts-cli compile --target book --watch --input edition=web
+```
+
 === `--watch` option
 
 Watch file dependencies and compile the document.
@@ -98,6 +123,75 @@ Output to directory, default in the same directory as the entry file.
 typst-ts-cli compile ... -o dist
 ```
 
+=== `--pages` option
+
+Only export the selected pages, by their physical page numbers starting from 1. It applies to the `pdf`, `png`, `svg`, `svg_html`, `text` and `vector` formats.
+
+```bash
+# the first page
+typst-ts-cli compile ... --pages 1
+# the first page, and the pages from 3 to 5
+typst-ts-cli compile ... --pages 1,3-5
+# the pages from 3 to the end
+typst-ts-cli compile ... --pages 3-
+```
+
+=== `--svg-*` options
+
+Override the default options of the `svg` and `svg_html` formats, each taking `true` or `false`:
+- `--svg-text-element`: render text elements, which make the text selectable and searchable, default: `true`.
+- `--svg-builtin-css`: include the builtin CSS, default: `true`.
+- `--svg-responsive-js`: include the JavaScript for interactive and responsive actions, default: `true` for `svg_html` and `false` for `svg`.
+- `--svg-stable-glyph-id`: use glyph ids which are stable across SVG files, default: `true`.
+- `--svg-debug-info`: attach debug info to SVG elements, default: `false`.
+- `--svg-rasterize-text`: rasterize text, which takes effect only if the compiler is built with the `aggresive-browser-rasterization` feature, default: `false`.
+- `--svg-web-fonts`: render text as `<text>` elements in the used fonts, which are subset and embedded as WOFF2 web fonts, default: `false`. The text is selectable and accessible to screen readers. Text that cannot be represented in its font, e.g. ligatures, text with gradient fills and right-to-left text, is still rendered as glyph outlines.
+
+```bash
+typst-ts-cli compile ... --format svg --svg-text-element false --svg-builtin-css false
+```
+
+=== `--svg-per-page` option
+
+Write a standalone SVG for each page as `<name>-<n>.svg` in the `svg` format, instead of one SVG with all pages stacked vertically. Each SVG only carries the glyphs, gradients and patterns used by its page.
+
+```bash
+typst-ts-cli compile ... --format svg --svg-per-page
+```
+
+=== `--svg-strict-minify` and `--svg-precision` options
+
+Minify SVG more strictly with `--svg-strict-minify true`, which deduplicates identical clip paths and gradients, drops empty groups and merges the presentation attributes shared by elements into CSS classes. Round the numbers in path data and translations to `N` decimal places with `--svg-precision N`. The size of each SVG before and after minification is printed to the standard error.
+
+```bash
+typst-ts-cli compile ... --format svg --svg-strict-minify true --svg-precision 2
+```
+
+=== `--diagnostic-format` option, default: `human`
+
+The format to emit diagnostics in. The `json` format prints one JSON object per line, carrying the severity, message, file path, byte range, line and column, hints and trace of each diagnostic.
+
+```bash
+typst-ts-cli compile ... --diagnostic-format short
+typst-ts-cli compile ... --diagnostic-format json
+```
+
+=== `--no-font-diagnostics` option
+
+By default, the compiler warns about each font family given to `text` by a string literal that is not found, and each character which no font covers, so that it is rendered as a missing glyph ("tofu"). Families given by variables or passed through `text.with(font: ..)` are not checked. The flag disables the warnings.
+
+```bash
+typst-ts-cli compile ... --no-font-diagnostics
+```
+
+=== `--deps` and `--deps-json` options
+
+Write the files read by the compilation, i.e. source files, images, data files, files of packages and font files. `--deps` writes a Makefile-style depfile, whose targets are the exported files (every written page for per-page formats), for build systems like Make and Ninja. `--deps-json` writes a JSON manifest, which additionally carries the SHA-256 hash of each file and the used packages.
+
+```bash
+typst-ts-cli compile ... --deps main.d --deps-json main.deps.json
+```
+
 === `--trace` option
 
 Comma seperated options to trace execution of typst compiler when compiling documents:
@@ -113,6 +207,75 @@ typst-ts-cli compile ... --trace=verbosity=2
 typst-ts-cli compile ... --trace=verbosity=3
 ```
 
+=== `--timings` option
+
+Record the timings of compilation stages and write them to the given path as a Chrome trace-event JSON file, which can be viewed in `chrome://tracing` or #link("https://ui.perfetto.dev")[Perfetto]. The trace contains spans of parsing, evaluation, layout of elements, exporting in each format, reading files, loading fonts and downloading packages. Since typst does not expose the statistics of its memoization cache, the trace carries a `source cache (<entry>)` counter per compiled entry instead, with the number of files reused unchanged from previous compilations (`hits`) and those loaded from scratch or changed since (`misses`). In watch mode, the file is rewritten after each compilation.
+
+```bash
+typst-ts-cli compile ... --timings timings.json
+```
+
+=== `--entries` and `-j,--jobs` options
+
+Compile additional entry files along with `--entry`, which can be omitted, in a single run, so that fonts, packages and caches are loaded once and shared between documents. Glob patterns like `docs/**/*.typ` are expanded, where `*` and `?` match within a path component and `**` matches any number of path components. Each document is exported next to its entry file, or into the `--output` directory, under the directory of the entry file relative to the workspace root, e.g. `docs/a/main.typ` is exported to `out/docs/a/main.pdf` with `--output out`. Entries whose outputs would overwrite each other are rejected. The command fails if any of the documents fails to compile.
+
+The `-j,--jobs` option sets the number of documents to compile in parallel, default: `1`.
+
+```bash
+typst-ts-cli compile \
+  -e main.typ --entries 'docs/**/*.typ' -j 4 --format pdf
+```
+
+=== `--inputs-file` and `--inputs-format` options
+
+Read the dictionary visible through `sys.inputs` from a JSON, TOML or YAML file, use `-` to read it from stdin. Unlike the `--input` option, which only passes strings, objects, arrays, numbers and booleans in the file are passed as dictionaries, arrays, numbers and booleans. Pairs given by `--input` override the entries of the file.
+
+The format is inferred from the extension of the file (`.json`, `.toml`, `.yaml` or `.yml`), or is specified by `--inputs-format json|toml|yaml`, which is required when reading from stdin.
+
+```bash
+typst-ts-cli compile ... --inputs-file data.json --input version=1.0
+cat data.yaml | typst-ts-cli compile ... --inputs-file - --inputs-format yaml
+```
+
+=== `--package-registry` and `--package-namespace` options
+
+Fetch packages from mirrors or private registries. The `--package-registry` option sets the base URL of a registry serving the `@preview` namespace, from which packages are downloaded at `{url}/preview/{name}-{version}.tar.gz`, default: `https://packages.typst.org`.
+
+The `--package-namespace` option maps a namespace to a source, which is one of:
+- a URL template containing `{namespace}`, `{name}` and `{version}` placeholders,
+- a base URL of a registry, from which packages are downloaded at `{url}/{namespace}/{name}-{version}.tar.gz`,
+- a local directory, in which packages are stored at `{dir}/{namespace}/{name}/{version}`, the same layout as directories given by `--package-path` and written by `package vendor`.
+
+Both options can be given multiple times, and the sources are tried in the given order until one provides the package. Downloaded packages are cached in the same way as packages from the `@preview` namespace.
+
+```bash
+typst-ts-cli compile ... \
+  --package-registry https://mirror.example.com \
+  --package-namespace 'corp=https://packages.example.com/{name}-{version}.tar.gz' \
+  --package-namespace corp=/mnt/shared/typst-packages
+```
+
+=== `--package-timeout` and `--package-retries` options
+
+Set the timeout in seconds of each request to package registries, and the number of times to retry a download after network failures, default: `2`. The progress of downloads is logged during compilation, so that a slow download in watch mode is visible.
+
+```bash
+typst-ts-cli compile ... --package-timeout 60 --package-retries 5
+```
+
+=== `--offline` and `--lockfile` options
+
+The `--offline` option forbids downloading packages, so that compilation fails with an error if a package is not on disk yet.
+
+The `--lockfile` option records every resolved package into a lockfile, with the SHA-256 checksum of its downloaded archive and of its files. Once a package is recorded, downloaded archives and packages on disk are verified against the lockfile before use, and a mismatch fails the compilation. If the option is not given, `typst-ts.lock` at the workspace root is used when it exists.
+
+```bash
+# Records the packages used by the document.
+typst-ts-cli compile ... --lockfile typst-ts.lock
+# Builds reproducibly with the recorded packages.
+typst-ts-cli compile ... --offline
+```
+
 === Example: compile a document with watching dependencies
 
 ```bash
@@ -129,6 +292,26 @@ typst-ts-cli compile \
   --format svg
 ```
 
+=== Example: compile a document into PNG images
+
+Each page is written to a separate file, e.g. `main-1.png`, `main-2.png`, etc. The `--pixel-per-pt` option controls the scale of the rasterized pages, default: `3`.
+
+```bash
+typst-ts-cli compile \
+  -e "fuzzers/corpora/math/main.typ"
+  --format png --pixel-per-pt 2
+```
+
+=== Example: extract text from a document
+
+The `text` format concatenates text in the order of layout frames. With `--text-layout`, text is ordered geometrically in reading order (e.g. column by column), lines are broken on baseline changes, and pages are separated by form feeds. The `text_json` format writes the text and the bounding boxes of text items of each page into `main.text.json`.
+
+```bash
+typst-ts-cli compile \
+  -e "fuzzers/corpora/math/main.typ"
+  --format text --text-layout --format text_json
+```
+
 === Example: compile a document into SVG wrapped with HTML
 
 ```bash
@@ -161,6 +344,31 @@ typst-ts-cli compile \
 typst-ts-cli package list
 ```
 
+=== Example: search packages
+
+Search packages whose names or descriptions contain the query, showing the latest version of each package. The index of the `@preview` namespace is cached on disk for a day, and the `--refresh` flag fetches it again. Packages in the local data path are also searched.
+
+```bash
+typst-ts-cli package search cetz
+```
+
+=== Example: install packages into the cache
+
+```bash
+typst-ts-cli package install @preview/cetz:0.2.2 @preview/tablex:0.0.8
+```
+
+=== Example: vendor packages used by a document
+
+Compile the document to find the packages it imports, including the packages imported by packages, and copy them into a project-local directory, `vendor` by default. Packages are stored at `{dir}/{namespace}/{name}/{version}`, which can be searched by the `--package-path` option of other commands, so that the document can be compiled without network access.
+
+If the document fails to compile, the packages imported after the error cannot be discovered, so the command fails without vendoring anything. Pass `--allow-partial` to vendor the packages discovered so far instead.
+
+```bash
+typst-ts-cli package vendor -e main.typ --dir vendor
+typst-ts-cli compile -e main.typ --package-path vendor --offline
+```
+
 === Example: link a package to `@preview` namespace
 
 ```bash
@@ -173,6 +381,43 @@ typst-ts-cli package link --manifest path/to/typst.toml
 typst-ts-cli package unlink --manifest path/to/typst.toml
 ```
 
+== Font commands
+
+=== Example: list fonts in JSON
+
+Print the fonts of each family, with the path to the font file (`null` for fonts in memory), the index of the font in the file, the SHA-256 hash of the covered code points and the variant.
+
+```bash
+typst-ts-cli font list --json
+```
+
+=== Example: report the fonts used by a document
+
+Compile the document and print each font it uses, with the glyphs and the code points shaped into them. The `--json` flag prints the report in JSON.
+
+```bash
+typst-ts-cli font usage -e main.typ
+typst-ts-cli font usage -e main.typ --json
+```
+
+=== Example: bundle the glyphs used by a document into a glyph pack
+
+Compile the document and bundle the fonts it uses into a glyph pack, `<entry>.glyphs.json` by default. The outlines of glyphs unused by the document are removed from TrueType fonts, while fonts with CFF outlines are kept whole. The pack can be loaded by the `load_glyph_pack` method of a renderer built with the `build_glyph_pack` feature, to render the document without the full font files.
+
+```bash
+typst-ts-cli font pack -e main.typ --pack main.glyphs.json
+```
+
+=== Example: verify and update a font profile
+
+`font measure` profiles the font files into a gzipped JSON file, which can be used as a cache by the `fontProfileCachePath` option of the compiler. Only these commands hash the content of the font files. A compilation with `fontProfileCachePath` profiles new or modified font files by their modification times and rewrites the profile, which costs re-reading these font files but doesn't skip searching fonts. `font profile verify` checks whether the font files are modified, changed in content or removed since they were profiled, and exits with a non-zero code if any item is out of date. `font profile update` regenerates only the out-of-date items and the items without content hashes and removes the items of removed font files. Profiles generated by another version of the compiler are regenerated entirely.
+
+```bash
+typst-ts-cli font measure --font-path fonts --output fonts.profile.json.gz
+typst-ts-cli font profile verify --profile fonts.profile.json.gz
+typst-ts-cli font profile update --font-path fonts --profile fonts.profile.json.gz
+```
+
 == CLI Options
 
 Help:
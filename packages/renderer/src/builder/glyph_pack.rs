@@ -6,7 +6,8 @@ use crate::{TypstRenderer, TypstRendererBuilder};
 #[wasm_bindgen]
 impl TypstRendererBuilder {
     pub async fn add_glyph_pack(&mut self, pack: JsValue) -> ZResult<()> {
-        let pack = serde_wasm_bindgen::from_value(pack).unwrap();
+        let pack =
+            serde_wasm_bindgen::from_value(pack).map_err(map_string_err("GlyphBundleFmt"))?;
         self.searcher.add_glyph_pack(pack).await
     }
 }
@@ -15,9 +16,8 @@ impl TypstRendererBuilder {
 impl TypstRenderer {
     pub fn load_glyph_pack(&self, v: JsValue) -> ZResult<()> {
         let mut font_resolver = self.session_mgr.font_resolver.write().unwrap();
-        font_resolver.add_glyph_packs(
-            serde_wasm_bindgen::from_value(v).map_err(map_string_err("GlyphBundleFmt"))?,
-        );
-        Ok(())
+        font_resolver.add_glyph_packs(vec![
+            serde_wasm_bindgen::from_value(v).map_err(map_string_err("GlyphBundleFmt"))?
+        ])
     }
 }
@@ -92,7 +92,14 @@ export class NodeCompiler {
   /** Simply compiles the document as a plain SVG. */
   plainSvg(compiledOrBy: NodeTypstDocument | CompileDocArgs): string;
   /** Simply compiles the document as a rich-contented SVG (for browsers). */
-  svg(compiledOrBy: NodeTypstDocument | CompileDocArgs): string;
+  svg(compiledOrBy: NodeTypstDocument | CompileDocArgs, opts?: RenderSvgOpts): string;
+  /** Simply compiles the document as a standalone SVG per page. */
+  svgPages(compiledOrBy: NodeTypstDocument | CompileDocArgs, opts?: RenderSvgOpts): Array<string>;
+  /**
+   * Calculates the tight bounding box of the content of each page, which
+   * is `null` for a blank page.
+   */
+  pageBboxes(compiledOrBy: NodeTypstDocument | CompileDocArgs): Array<PageBBox | undefined | null>;
 }
 
 /** A node error. */
@@ -158,7 +165,10 @@ export class NodeTypstDocument {
 export interface CompileArgs {
   /** Adds additional directories to search for fonts */
   fontArgs?: Array<NodeAddFontPaths | NodeAddFontBlobs>;
-  /** Path to typst workspace. */
+  /**
+   * Path to typst workspace, or to a zip or tar archive bundling the
+   * workspace.
+   */
   workspace?: string;
   /** Adds a string key-value pair visible through `sys.inputs` */
   inputs?: Record<string, string>;
@@ -195,6 +205,21 @@ export interface NodeAddFontPaths {
   fontPaths: Array<string>;
 }
 
+/**
+ * The bounding box of the content of a page, in the coordinates of the
+ * page.
+ */
+export interface PageBBox {
+  /** The x coordinate of the left edge. */
+  x: number;
+  /** The y coordinate of the top edge. */
+  y: number;
+  /** The width of the box. */
+  width: number;
+  /** The height of the box. */
+  height: number;
+}
+
 /** Arguments to query the document. */
 export interface QueryDocArgs {
   /** The query selector. */
@@ -212,3 +237,44 @@ export interface RenderPdfOpts {
    */
   creationTimestamp?: number;
 }
+
+/**
+ * Arguments to render an SVG, which override the default options of the
+ * exporter.
+ */
+export interface RenderSvgOpts {
+  /**
+   * Whether to render text elements, which make the text in SVG
+   * selectable and searchable.
+   */
+  renderTextElement?: boolean;
+  /** Whether to include the builtin CSS. */
+  withBuiltinCss?: boolean;
+  /**
+   * Whether to include the JavaScript for interactive and responsive
+   * actions.
+   */
+  withResponsiveJs?: boolean;
+  /** Whether to use glyph ids which are stable across SVG files. */
+  useStableGlyphId?: boolean;
+  /** Whether to attach debug info to SVG elements. */
+  attachDebugInfo?: boolean;
+  /**
+   * Whether to rasterize text, which takes effect only if the renderer is
+   * built with the `aggresive-browser-rasterization` feature.
+   */
+  rasterizeText?: boolean;
+  /**
+   * Whether to render texts as `<text>` elements in the used fonts, which
+   * are subset and embedded as WOFF2 web fonts.
+   */
+  embedWebFonts?: boolean;
+  /**
+   * Whether to minify SVG more strictly, which deduplicates identical clip
+   * paths and gradients, drops empty groups and merges shared
+   * presentation attributes into CSS classes.
+   */
+  strictMinify?: boolean;
+  /** The number of decimal places kept in path data and translations. */
+  numericPrecision?: number;
+}
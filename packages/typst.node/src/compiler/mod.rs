@@ -12,7 +12,7 @@ use reflexo_typst::error::prelude::*;
 use reflexo_typst::font::system::SystemFontSearcher;
 use reflexo_typst::package::http::HttpRegistry;
 use reflexo_typst::typst::{foundations::IntoValue, prelude::Prehashed};
-use reflexo_typst::vfs::{system::SystemAccessModel, Vfs};
+use reflexo_typst::vfs::{archive::ArchiveAccessModel, system::SystemAccessModel, Vfs};
 use reflexo_typst::{
     Bytes, CompileDriver, PureCompiler, TypstDict, TypstSystemUniverse, TypstSystemWorld,
 };
@@ -71,7 +71,8 @@ pub struct NodeCompileArgs {
     /// Adds additional directories to search for fonts
     pub font_args: Option<Vec<Either<NodeAddFontPaths, NodeAddFontBlobs>>>,
 
-    /// Path to typst workspace.
+    /// Path to typst workspace, or to a zip or tar archive bundling the
+    /// workspace.
     pub workspace: Option<String>,
 
     /// Adds a string key-value pair visible through `sys.inputs`
@@ -121,10 +122,17 @@ pub fn create_driver(
         ..CompileFontOpts::default()
     })?;
 
+    // Mounts the workspace if it is an archive, e.g. a bundle in zip.
+    let mut access_model = ArchiveAccessModel::new(SystemAccessModel);
+    if let Some(archive) = TypstSystemUniverse::workspace_archive(&workspace_dir)? {
+        searcher.add_archive_fonts(&archive);
+        access_model.mount(workspace_dir.as_path().into(), archive);
+    }
+
     let world = TypstSystemUniverse::new_raw(
         EntryState::new_rooted(workspace_dir.into(), None),
         args.inputs.map(create_inputs),
-        Vfs::new(SystemAccessModel {}),
+        Vfs::new(access_model),
         HttpRegistry::default(),
         Arc::new(searcher.into()),
     );
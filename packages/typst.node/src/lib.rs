@@ -19,6 +19,8 @@ use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use reflexo_typst::error::prelude::*;
 use reflexo_typst::foundations::IntoValue;
+#[cfg(feature = "svg")]
+use reflexo_typst::svg::{SvgExportFeature, SvgExportOptions, SvgExportOverrides};
 use reflexo_typst::syntax::Span;
 use reflexo_typst::typst::diag::{At, SourceResult};
 use reflexo_typst::{
@@ -144,6 +146,75 @@ pub struct RenderPdfOpts {
     pub creation_timestamp: Option<i64>,
 }
 
+/// Arguments to render an SVG, which override the default options of the
+/// exporter.
+#[napi(object)]
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg(feature = "svg")]
+pub struct RenderSvgOpts {
+    /// Whether to render text elements, which make the text in SVG
+    /// selectable and searchable.
+    pub render_text_element: Option<bool>,
+    /// Whether to include the builtin CSS.
+    pub with_builtin_css: Option<bool>,
+    /// Whether to include the JavaScript for interactive and responsive
+    /// actions.
+    pub with_responsive_js: Option<bool>,
+    /// Whether to use glyph ids which are stable across SVG files.
+    pub use_stable_glyph_id: Option<bool>,
+    /// Whether to attach debug info to SVG elements.
+    pub attach_debug_info: Option<bool>,
+    /// Whether to rasterize text, which takes effect only if the renderer is
+    /// built with the `aggresive-browser-rasterization` feature.
+    pub rasterize_text: Option<bool>,
+    /// Whether to render texts as `<text>` elements in the used fonts, which
+    /// are subset and embedded as WOFF2 web fonts.
+    pub embed_web_fonts: Option<bool>,
+    /// Whether to minify SVG more strictly, which deduplicates identical clip
+    /// paths and gradients, drops empty groups and merges shared
+    /// presentation attributes into CSS classes.
+    pub strict_minify: Option<bool>,
+    /// The number of decimal places kept in path data and translations.
+    pub numeric_precision: Option<u32>,
+}
+
+#[cfg(feature = "svg")]
+impl RenderSvgOpts {
+    /// Gets the overrides of the default options of an SVG exporter.
+    fn overrides(&self) -> SvgExportOverrides {
+        SvgExportOverrides {
+            render_text_element: self.render_text_element,
+            with_builtin_css: self.with_builtin_css,
+            with_responsive_js: self.with_responsive_js,
+            use_stable_glyph_id: self.use_stable_glyph_id,
+            attach_debug_info: self.attach_debug_info,
+            rasterize_text: self.rasterize_text,
+            embed_web_fonts: self.embed_web_fonts,
+            strict_minify: self.strict_minify,
+            numeric_precision: self
+                .numeric_precision
+                .map(|p| u8::try_from(p).unwrap_or(u8::MAX)),
+            ..Default::default()
+        }
+    }
+}
+
+/// The bounding box of the content of a page, in the coordinates of the
+/// page.
+#[napi(object)]
+#[derive(Serialize, Deserialize, Debug)]
+#[cfg(feature = "svg")]
+pub struct PageBBox {
+    /// The x coordinate of the left edge.
+    pub x: f64,
+    /// The y coordinate of the top edge.
+    pub y: f64,
+    /// The width of the box.
+    pub width: f64,
+    /// The height of the box.
+    pub height: f64,
+}
+
 /// Either a compiled document or compile arguments.
 type MayCompileOpts<'a> = Either<&'a NodeTypstDocument, CompileDocArgs>;
 
@@ -367,11 +438,63 @@ impl NodeCompiler {
     }
 
     /// Simply compiles the document as a rich-contented SVG (for browsers).
-    #[napi(ts_args_type = "compiledOrBy: NodeTypstDocument | CompileDocArgs")]
+    #[napi(ts_args_type = "compiledOrBy: NodeTypstDocument | CompileDocArgs, opts?: RenderSvgOpts")]
     #[cfg(feature = "svg")]
-    pub fn svg(&mut self, compiled_or_by: MayCompileOpts) -> Result<String, NodeError> {
+    pub fn svg(
+        &mut self,
+        compiled_or_by: MayCompileOpts,
+        opts: Option<RenderSvgOpts>,
+    ) -> Result<String, NodeError> {
         type Exporter = reflexo_typst::PureSvgExporter;
-        self.compile_as(Exporter::default(), compiled_or_by)
+        let e = match opts {
+            Some(opts) => Exporter::default().with_options(
+                SvgExportOptions::from_feature::<SvgExportFeature>().merge(&opts.overrides()),
+            ),
+            None => Exporter::default(),
+        };
+        self.compile_as::<_, String, _>(e, compiled_or_by)
+    }
+
+    /// Simply compiles the document as a standalone SVG per page.
+    #[napi(ts_args_type = "compiledOrBy: NodeTypstDocument | CompileDocArgs, opts?: RenderSvgOpts")]
+    #[cfg(feature = "svg")]
+    pub fn svg_pages(
+        &mut self,
+        compiled_or_by: MayCompileOpts,
+        opts: Option<RenderSvgOpts>,
+    ) -> Result<Vec<String>, NodeError> {
+        type Exporter = reflexo_typst::PureSvgPagesExporter;
+        let e = match opts {
+            Some(opts) => Exporter::default().with_options(
+                SvgExportOptions::from_feature::<SvgExportFeature>().merge(&opts.overrides()),
+            ),
+            None => Exporter::default(),
+        };
+        self.compile_as::<_, Vec<String>, _>(e, compiled_or_by)
+    }
+
+    /// Calculates the tight bounding box of the content of each page, which
+    /// is `null` for a blank page.
+    #[napi(ts_args_type = "compiledOrBy: NodeTypstDocument | CompileDocArgs")]
+    #[cfg(feature = "svg")]
+    pub fn page_bboxes(
+        &mut self,
+        compiled_or_by: MayCompileOpts,
+    ) -> Result<Vec<Option<PageBBox>>, NodeError> {
+        type Exporter = reflexo_typst::SvgPageBBoxesExporter;
+        let bboxes: Vec<Option<reflexo_typst::svg::ir::Rect>> =
+            self.compile_as(Exporter::default(), compiled_or_by)?;
+        Ok(bboxes
+            .into_iter()
+            .map(|bbox| {
+                bbox.map(|r| PageBBox {
+                    x: r.lo.x.0 as f64,
+                    y: r.lo.y.0 as f64,
+                    width: r.width().0 as f64,
+                    height: r.height().0 as f64,
+                })
+            })
+            .collect())
     }
 }
 
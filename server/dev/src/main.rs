@@ -86,7 +86,7 @@ fn compile_corpus(args: CompileCorpusArgs) {
     let mut compile = |cat: String, name: String| {
         let entry = corpus_path.join(cat).join(name).clean();
 
-        let exporter = typst_ts_cli::export::prepare_exporters(&compile_args, Some(&entry));
+        let (exporter, _) = typst_ts_cli::export::prepare_exporters(&compile_args, Some(&entry));
 
         let exporter_layer = &mut driver.compiler.compiler;
 